@@ -1,6 +1,9 @@
 // Import necessary traits and modules from adw and gtk crates
 use adw::{prelude::*, subclass::prelude::*};
-use gtk::glib;
+use gettextrs::gettext;
+use gtk::glib::{self, clone};
+
+use crate::bus_discovery::{self, DiscoveredBus};
 
 // Define a custom struct to represent the Cancelled error
 pub struct Cancelled;
@@ -17,6 +20,14 @@ mod imp {
         // Define a template child entry_row of type adw::EntryRow
         #[template_child]
         pub(super) entry_row: TemplateChild<adw::EntryRow>,
+        // Define a template child for the factor of the replay delays
+        #[template_child]
+        pub(super) delay_scale_row: TemplateChild<adw::SpinRow>,
+        // Define template children listing the buses found on the machine
+        #[template_child]
+        pub(super) discovered_buses_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub(super) discovered_buses_list: TemplateChild<gtk::ListBox>,
     }
 
     // Implement the ObjectSubclass trait for AddressDialog
@@ -50,6 +61,13 @@ mod imp {
             self.obj().update_record_response_enabled();
             // Set focus on the entry_row
             self.entry_row.grab_focus();
+
+            // List the buses found on the machine once discovered
+            let obj = self.obj();
+            glib::spawn_future_local(clone!(@weak obj => async move {
+                let buses = bus_discovery::discover().await;
+                obj.set_discovered_buses(buses);
+            }));
         }
     }
 
@@ -72,17 +90,55 @@ glib::wrapper! {
 impl AddressDialog {
     // Define an asynchronous method to choose an address
     pub async fn choose(parent: &impl IsA<gtk::Widget>) -> Result<zbus::Address, Cancelled> {
+        Self::choose_with(parent, None, None).await
+    }
+
+    // Same as `choose`, but overrides the heading and the label of the
+    // confirmation response when given
+    pub async fn choose_with(
+        parent: &impl IsA<gtk::Widget>,
+        heading: Option<&str>,
+        response_label: Option<&str>,
+    ) -> Result<zbus::Address, Cancelled> {
         // Create a new instance of AddressDialog
         let this = glib::Object::new::<Self>();
-        // Get the entry_row from the implementation
-        let entry_row = this.imp().entry_row.get();
+        if let Some(heading) = heading {
+            this.set_heading(Some(heading));
+        }
+        if let Some(response_label) = response_label {
+            this.set_response_label("record", response_label);
+        }
+        this.run(parent).await
+    }
+
+    // Same as `choose_with`, but also lets the user edit the factor applied
+    // to the delays between replayed calls, starting from `delay_scale`
+    pub async fn choose_replay(
+        parent: &impl IsA<gtk::Widget>,
+        delay_scale: f64,
+    ) -> Result<(zbus::Address, f64), Cancelled> {
+        let this = glib::Object::new::<Self>();
+        this.set_heading(Some(&gettext("Replay to Address")));
+        this.set_response_label("record", &gettext("_Replay"));
+
+        let imp = this.imp();
+        imp.delay_scale_row.set_value(delay_scale);
+        imp.delay_scale_row.set_visible(true);
+
+        let address = this.run(parent).await?;
+        Ok((address, imp.delay_scale_row.value()))
+    }
 
+    // Present the dialog and return the entered address once confirmed
+    async fn run(&self, parent: &impl IsA<gtk::Widget>) -> Result<zbus::Address, Cancelled> {
         // Await the future result of choose_future method
-        match this.choose_future(parent).await.as_str() {
+        match self.clone().choose_future(parent).await.as_str() {
             // If response is "cancel", return Cancelled error
             "cancel" => Err(Cancelled),
             // If response is "record", parse the address and return
-            "record" => Ok(entry_row
+            "record" => Ok(self
+                .imp()
+                .entry_row
                 .text()
                 .parse()
                 .expect("address must have been validated")),
@@ -91,6 +147,29 @@ impl AddressDialog {
         }
     }
 
+    // Add a row for each of `buses`, filling the entry with its address
+    // when activated
+    fn set_discovered_buses(&self, buses: Vec<DiscoveredBus>) {
+        let imp = self.imp();
+
+        for bus in buses {
+            let address = bus.address.to_string();
+            let row = adw::ActionRow::builder()
+                .title(bus.description)
+                .subtitle(&address)
+                .use_markup(false)
+                .activatable(true)
+                .build();
+            row.connect_activated(clone!(@weak self as obj => move |_| {
+                obj.imp().entry_row.set_text(&address);
+            }));
+            imp.discovered_buses_list.append(&row);
+        }
+
+        imp.discovered_buses_window
+            .set_visible(imp.discovered_buses_list.first_child().is_some());
+    }
+
     // Define a callback to update the record response enabled status
     #[template_callback]
     fn update_record_response_enabled(&self) {
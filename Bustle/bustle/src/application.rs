@@ -1,4 +1,5 @@
 use adw::subclass::prelude::*;
+use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
@@ -11,10 +12,16 @@ use crate::{
 };
 
 mod imp {
+    use std::cell::Cell;
+
     use super::*;
 
     #[derive(Debug, Default)]
-    pub struct Application;
+    pub struct Application {
+        /// Whether the PCAP stream written to the standard input is recorded
+        /// in the first window
+        pub(super) record_stdin: Cell<bool>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for Application {
@@ -23,7 +30,20 @@ mod imp {
         type ParentType = adw::Application;
     }
 
-    impl ObjectImpl for Application {}
+    impl ObjectImpl for Application {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.obj().add_main_option(
+                "stdin",
+                glib::Char::from(b'\0'),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                &gettext("Record the PCAP stream written to the standard input"),
+                None,
+            );
+        }
+    }
 
     impl ApplicationImpl for Application {
         fn activate(&self) {
@@ -37,9 +57,25 @@ mod imp {
             }
 
             let window = Window::with_group(&app);
+            if self.record_stdin.replace(false) {
+                glib::spawn_future_local(clone!(@strong window => async move {
+                    window.record_stdin().await;
+                }));
+            }
             window.present();
         }
 
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if options.contains("stdin") {
+                // The standard input can't be read by the primary instance
+                let app = self.obj();
+                app.set_flags(app.flags() | gio::ApplicationFlags::NON_UNIQUE);
+                self.record_stdin.set(true);
+            }
+
+            self.parent_handle_local_options(options)
+        }
+
         fn startup(&self) {
             tracing::debug!("GtkApplication<Application>::startup");
             self.parent_startup();
@@ -0,0 +1,261 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use gettextrs::gettext;
+use gtk::glib;
+use zbus::address::transport::{Transport, Unix, UnixSocket};
+
+use crate::{i18n::gettext_f, RUNTIME};
+
+/// Depth up to which the runtime directory is searched for sockets
+const MAX_RUNTIME_DIR_DEPTH: usize = 2;
+
+/// Flag of the sockets that accept connections in `/proc/net/unix`
+const SO_ACCEPTCON: u32 = 0x10000;
+
+/// A bus found on this machine
+#[derive(Debug)]
+pub struct DiscoveredBus {
+    pub address: zbus::Address,
+    pub description: String,
+}
+
+/// Returns the buses found on this machine, the well-known ones first, without
+/// duplicates
+pub async fn discover() -> Vec<DiscoveredBus> {
+    let a11y_bus = RUNTIME.spawn(accessibility_bus_address());
+    let others = RUNTIME.spawn_blocking(|| {
+        let mut buses = runtime_dir_buses();
+        buses.extend(user_session_buses());
+        buses.extend(private_daemon_buses());
+        buses
+    });
+
+    let mut buses = Vec::new();
+
+    match zbus::Address::session() {
+        Ok(address) => buses.push(DiscoveredBus {
+            address,
+            description: gettext("Session Bus"),
+        }),
+        Err(err) => tracing::debug!("No session bus address: {:?}", err),
+    }
+    match zbus::Address::system() {
+        Ok(address) => buses.push(DiscoveredBus {
+            address,
+            description: gettext("System Bus"),
+        }),
+        Err(err) => tracing::debug!("No system bus address: {:?}", err),
+    }
+    match a11y_bus.await {
+        Ok(Ok(address)) => buses.push(DiscoveredBus {
+            address,
+            description: gettext("Accessibility Bus"),
+        }),
+        Ok(Err(err)) => tracing::debug!("No accessibility bus: {:?}", err),
+        Err(err) => tracing::warn!("Failed to join accessibility bus lookup: {:?}", err),
+    }
+    match others.await {
+        Ok(others) => buses.extend(others),
+        Err(err) => tracing::warn!("Failed to join bus discovery: {:?}", err),
+    }
+
+    let mut seen = HashSet::new();
+    buses.retain(|bus| seen.insert(bus.address.to_string()));
+    buses
+}
+
+async fn accessibility_bus_address() -> Result<zbus::Address> {
+    let cnx = zbus::Connection::session().await?;
+    let reply = cnx
+        .call_method(
+            Some("org.a11y.Bus"),
+            "/org/a11y/bus",
+            Some("org.a11y.Bus"),
+            "GetAddress",
+            &(),
+        )
+        .await?;
+    let address = reply.body().deserialize::<String>()?;
+    Ok(address.parse()?)
+}
+
+/// Returns the sockets of the runtime directory that look like they belong
+/// to a bus, as most of them do not
+fn runtime_dir_buses() -> Vec<DiscoveredBus> {
+    let runtime_dir = glib::user_runtime_dir();
+
+    let mut paths = Vec::new();
+    find_sockets(&runtime_dir, MAX_RUNTIME_DIR_DEPTH, &mut paths);
+
+    paths
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().contains("bus"))
+        })
+        .map(|path| {
+            let relative_path = path.strip_prefix(&runtime_dir).unwrap_or(&path);
+            DiscoveredBus {
+                description: gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "Socket {path} in the Runtime Directory",
+                    &[("path", &relative_path.display().to_string())],
+                ),
+                address: unix_address(UnixSocket::File(path)),
+            }
+        })
+        .collect()
+}
+
+fn find_sockets(dir: &Path, max_depth: usize, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_socket() {
+            paths.push(entry.path());
+        } else if file_type.is_dir() && max_depth > 1 {
+            find_sockets(&entry.path(), max_depth - 1, paths);
+        }
+    }
+}
+
+/// Returns the session buses of all users at `/run/user/*/bus`
+fn user_session_buses() -> Vec<DiscoveredBus> {
+    let Ok(entries) = fs::read_dir("/run/user") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path().join("bus");
+            let is_socket =
+                fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket());
+            is_socket.then(|| DiscoveredBus {
+                description: gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "Session Bus of User {uid}",
+                    &[("uid", &entry.file_name().to_string_lossy())],
+                ),
+                address: unix_address(UnixSocket::File(path)),
+            })
+        })
+        .collect()
+}
+
+/// Returns the sockets `dbus-daemon` instances listen on, as found in
+/// `/proc`, which includes the ones started privately, e.g., by
+/// `dbus-run-session` or test suites
+fn private_daemon_buses() -> Vec<DiscoveredBus> {
+    let listening_sockets = match listening_unix_sockets() {
+        Ok(listening_sockets) => listening_sockets,
+        Err(err) => {
+            tracing::debug!("Failed to read listening sockets: {:?}", err);
+            return Vec::new();
+        }
+    };
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut buses = Vec::new();
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(pid) = file_name
+            .to_str()
+            .filter(|name| name.parse::<u32>().is_ok())
+        else {
+            continue;
+        };
+
+        let Ok(cmdline) = fs::read(entry.path().join("cmdline")) else {
+            continue;
+        };
+        let is_dbus_daemon = cmdline
+            .split(|byte| *byte == 0)
+            .next()
+            .is_some_and(|program| program.ends_with(b"dbus-daemon"));
+        if !is_dbus_daemon {
+            continue;
+        }
+
+        // Only readable for the processes of the same user
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Some(inode) = fs::read_link(fd.path()).ok().and_then(|target| {
+                target
+                    .to_str()?
+                    .strip_prefix("socket:[")?
+                    .strip_suffix(']')?
+                    .parse::<u64>()
+                    .ok()
+            }) else {
+                continue;
+            };
+
+            if let Some(socket) = listening_sockets.get(&inode) {
+                buses.push(DiscoveredBus {
+                    address: unix_address(socket.clone()),
+                    description: gettext_f(
+                        // Translators: Do NOT translate the contents between '{' and '}', this
+                        // is a variable name.
+                        "dbus-daemon With PID {pid}",
+                        &[("pid", pid)],
+                    ),
+                });
+            }
+        }
+    }
+
+    buses
+}
+
+/// Returns the Unix sockets accepting connections by inode
+fn listening_unix_sockets() -> io::Result<HashMap<u64, UnixSocket>> {
+    let content = fs::read_to_string("/proc/net/unix")?;
+
+    let sockets = content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            // Num RefCount Protocol Flags Type St Inode Path
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [_, _, _, flags, _, _, inode, path] = fields[..] else {
+                return None;
+            };
+
+            if u32::from_str_radix(flags, 16).ok()? & SO_ACCEPTCON == 0 {
+                return None;
+            }
+
+            let socket = match path.strip_prefix('@') {
+                Some(name) => UnixSocket::Abstract(name.into()),
+                None => UnixSocket::File(path.into()),
+            };
+            Some((inode.parse().ok()?, socket))
+        })
+        .collect();
+
+    Ok(sockets)
+}
+
+fn unix_address(socket: UnixSocket) -> zbus::Address {
+    zbus::Address::new(Transport::Unix(Unix::new(socket)))
+}
@@ -2,7 +2,7 @@ use gtk::{glib, subclass::prelude::*};
 use indexmap::IndexSet;
 use zbus::names::{BusName, WellKnownName};
 
-use crate::message::ReceiveIndex;
+use crate::{bus_type::BusType, message::ReceiveIndex, timestamp::Timestamp};
 
 /// The point at which to look up for well-known names.
 #[derive(Clone, Copy)]
@@ -21,9 +21,17 @@ impl From<ReceiveIndex> for LookupPoint {
     }
 }
 
+/// The message at which a connection appeared or disappeared, as told by
+/// `NameOwnerChanged` for its unique name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifetimeEvent {
+    pub receive_index: ReceiveIndex,
+    pub timestamp: Timestamp,
+}
+
 mod imp {
     use std::{
-        cell::{OnceCell, RefCell},
+        cell::{Cell, OnceCell, RefCell},
         collections::BTreeMap,
     };
 
@@ -32,7 +40,11 @@ mod imp {
     #[derive(Default)]
     pub struct BusNameItem {
         pub(super) name: OnceCell<BusName<'static>>,
+        pub(super) bus_type: Cell<BusType>,
         pub(super) wk_name_log: RefCell<BTreeMap<ReceiveIndex, IndexSet<WellKnownName<'static>>>>,
+        pub(super) connected: Cell<Option<LifetimeEvent>>,
+        pub(super) disconnected: Cell<Option<LifetimeEvent>>,
+        pub(super) last_referenced: Cell<Option<ReceiveIndex>>,
     }
 
     #[glib::object_subclass]
@@ -53,6 +65,11 @@ impl BusNameItem {
         self.imp().name.get().unwrap()
     }
 
+    /// Returns the bus `name` belongs to
+    pub fn bus_type(&self) -> BusType {
+        self.imp().bus_type.get()
+    }
+
     /// Returns a copy of the well-known names that were known at the given
     /// lookup point.
     pub fn wk_names(&self, lookup_point: LookupPoint) -> IndexSet<WellKnownName<'static>> {
@@ -71,10 +88,33 @@ impl BusNameItem {
         }
     }
 
+    /// Returns a copy of the well-known names known at each index where they
+    /// changed, in receive order
+    pub fn wk_name_log(&self) -> Vec<(ReceiveIndex, IndexSet<WellKnownName<'static>>)> {
+        self.imp()
+            .wk_name_log
+            .borrow()
+            .iter()
+            .map(|(receive_index, wk_names)| (*receive_index, wk_names.clone()))
+            .collect()
+    }
+
+    /// Returns when the connection of `name` appeared, if it was recorded
+    pub fn connected(&self) -> Option<LifetimeEvent> {
+        self.imp().connected.get()
+    }
+
+    /// Returns when the connection of `name` disappeared, if it was recorded
+    pub fn disconnected(&self) -> Option<LifetimeEvent> {
+        self.imp().disconnected.get()
+    }
+
     /// This must only be called on `BusNameList`
-    pub fn new(name: BusName<'static>) -> Self {
+    pub fn new(bus_type: BusType, name: BusName<'static>) -> Self {
         let this = glib::Object::new::<Self>();
-        this.imp().name.set(name).unwrap();
+        let imp = this.imp();
+        imp.name.set(name).unwrap();
+        imp.bus_type.set(bus_type);
         this
     }
 
@@ -95,4 +135,103 @@ impl BusNameItem {
             wk_names, receive_index
         );
     }
+
+    /// This must only be called on `BusNameList`
+    pub fn set_connected(&self, connected: LifetimeEvent) {
+        self.imp().connected.set(Some(connected));
+    }
+
+    /// This must only be called on `BusNameList`
+    pub fn set_disconnected(&self, disconnected: LifetimeEvent) {
+        self.imp().disconnected.set(Some(disconnected));
+    }
+
+    /// This must only be called on `BusNameList`
+    pub fn set_last_referenced(&self, receive_index: ReceiveIndex) {
+        self.imp().last_referenced.set(Some(receive_index));
+    }
+
+    /// Returns whether a message at `receive_index` or later is sent to or
+    /// from `name`, or changes its well-known names or connection
+    pub fn is_referenced_since(&self, receive_index: ReceiveIndex) -> bool {
+        let imp = self.imp();
+        imp.last_referenced
+            .get()
+            .is_some_and(|last_referenced| last_referenced >= receive_index)
+            || imp
+                .wk_name_log
+                .borrow()
+                .range(receive_index..)
+                .next()
+                .is_some()
+            || [imp.connected.get(), imp.disconnected.get()]
+                .into_iter()
+                .flatten()
+                .any(|lifetime_event| lifetime_event.receive_index >= receive_index)
+    }
+
+    /// Forgets the log entries that are no longer needed to look up the
+    /// well-known names at `receive_index` or later
+    ///
+    /// This must only be called on `BusNameList`
+    pub fn trim_wk_name_log(&self, receive_index: ReceiveIndex) {
+        let mut wk_name_log = self.imp().wk_name_log.borrow_mut();
+
+        // Keep the last entry before `receive_index`, as it still holds the
+        // well-known names at that point, unless another entry replaces it
+        let mut retained = wk_name_log.split_off(&receive_index);
+        if !retained.contains_key(&receive_index) {
+            if let Some((index, wk_names)) = wk_name_log.pop_last() {
+                retained.insert(index, wk_names);
+            }
+        }
+        *wk_name_log = retained;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wk_names(wk_names: &[&'static str]) -> IndexSet<WellKnownName<'static>> {
+        wk_names
+            .iter()
+            .map(|wk_name| WellKnownName::from_static_str(wk_name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn trim_wk_name_log() {
+        let bus_name_item = BusNameItem::new(BusType::Session, BusName::try_from(":1.1").unwrap());
+        bus_name_item.insert_wk_name_log(ReceiveIndex::new(1), wk_names(&["org.Foo"]));
+        bus_name_item.insert_wk_name_log(ReceiveIndex::new(3), wk_names(&["org.Foo", "org.Bar"]));
+        bus_name_item.insert_wk_name_log(ReceiveIndex::new(5), wk_names(&["org.Bar"]));
+
+        // The entry at 3 still tells the names at 4
+        bus_name_item.trim_wk_name_log(ReceiveIndex::new(4));
+        assert_eq!(
+            bus_name_item.wk_name_log(),
+            [
+                (ReceiveIndex::new(3), wk_names(&["org.Foo", "org.Bar"])),
+                (ReceiveIndex::new(5), wk_names(&["org.Bar"])),
+            ]
+        );
+        assert_eq!(
+            bus_name_item.wk_names(ReceiveIndex::new(4).into()),
+            wk_names(&["org.Foo", "org.Bar"])
+        );
+
+        bus_name_item.trim_wk_name_log(ReceiveIndex::new(5));
+        assert_eq!(
+            bus_name_item.wk_name_log(),
+            [(ReceiveIndex::new(5), wk_names(&["org.Bar"]))]
+        );
+
+        // Nothing is left to trim
+        bus_name_item.trim_wk_name_log(ReceiveIndex::new(8));
+        assert_eq!(
+            bus_name_item.wk_name_log(),
+            [(ReceiveIndex::new(5), wk_names(&["org.Bar"]))]
+        );
+    }
 }
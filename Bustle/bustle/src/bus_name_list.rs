@@ -1,8 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{bail, Context, Result};
-use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+use gtk::{
+    gio,
+    glib::{self, closure_local},
+    prelude::*,
+    subclass::prelude::*,
+};
 use indexmap::{map::Entry, IndexSet};
+use once_cell::sync::Lazy;
 use zbus::{
     fdo::DBusProxy,
     names::{BusName, UniqueName, WellKnownName},
@@ -11,21 +17,29 @@ use zbus::{
 };
 
 use crate::{
-    bus_name_item::{BusNameItem, LookupPoint},
+    bus_name_item::{BusNameItem, LifetimeEvent, LookupPoint},
+    bus_type::BusType,
     message::{Message, ReceiveIndex},
     message_type::MessageType,
 };
 
 mod imp {
-    use std::cell::RefCell;
+    use std::{cell::RefCell, collections::BTreeMap};
 
+    use glib::subclass::Signal;
     use indexmap::IndexMap;
 
     use super::*;
 
     #[derive(Default)]
     pub struct BusNameList {
-        pub(super) inner: RefCell<IndexMap<BusName<'static>, BusNameItem>>,
+        /// Names of each bus, listed one bus after the other so that the
+        /// same unique name on different buses are different items
+        pub(super) inner: RefCell<BTreeMap<BusType, IndexMap<BusName<'static>, BusNameItem>>>,
+        /// Connections that appeared before any of their messages, which are
+        /// given to their item once it is inserted
+        pub(super) pending_connections:
+            RefCell<HashMap<(BusType, UniqueName<'static>), LifetimeEvent>>,
     }
 
     #[glib::object_subclass]
@@ -35,7 +49,17 @@ mod imp {
         type Interfaces = (gio::ListModel,);
     }
 
-    impl ObjectImpl for BusNameList {}
+    impl ObjectImpl for BusNameList {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("name-lost")
+                    .param_types([BusType::static_type(), String::static_type()])
+                    .build()]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
 
     impl ListModelImpl for BusNameList {
         fn item_type(&self) -> glib::Type {
@@ -43,15 +67,24 @@ mod imp {
         }
 
         fn n_items(&self) -> u32 {
-            self.inner.borrow().len() as u32
+            self.inner
+                .borrow()
+                .values()
+                .map(|names| names.len() as u32)
+                .sum()
         }
 
         fn item(&self, position: u32) -> Option<glib::Object> {
-            self.inner
-                .borrow()
-                .get_index(position as usize)
-                .map(|(_, v)| v.upcast_ref::<glib::Object>())
-                .cloned()
+            let mut position = position as usize;
+            for names in self.inner.borrow().values() {
+                if position < names.len() {
+                    return names
+                        .get_index(position)
+                        .map(|(_, v)| v.clone().upcast::<glib::Object>());
+                }
+                position -= names.len();
+            }
+            None
         }
     }
 }
@@ -62,31 +95,159 @@ glib::wrapper! {
 }
 
 impl BusNameList {
+    /// Connects to the signal emitted when a name has no owner anymore, as
+    /// told by `NameOwnerChanged`, either because a well-known name was
+    /// released or because a connection was closed, along with the bus it
+    /// was on
+    pub fn connect_name_lost<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, BusType, &str) + 'static,
+    {
+        self.connect_closure(
+            "name-lost",
+            false,
+            closure_local!(|obj: &Self, bus_type: BusType, name: &str| {
+                f(obj, bus_type, name);
+            }),
+        )
+    }
+
     pub fn handle_message(&self, message: &Message) -> Result<()> {
-        match message.message_type() {
+        let res = match message.message_type() {
             MessageType::MethodCall => self.handle_method_call_message(message),
             MessageType::MethodReturn | MessageType::Error => {
                 self.handle_method_return_message(message)
             }
             MessageType::Signal => self.handle_signal_message(message),
+        };
+        self.set_last_referenced(message);
+        res
+    }
+
+    /// Removes the names, and the connections waiting for their name, that
+    /// no message at `receive_index` or later refers to anymore
+    pub fn remove_unreferenced(&self, receive_index: ReceiveIndex) {
+        self.imp()
+            .pending_connections
+            .borrow_mut()
+            .retain(|_, connected| connected.receive_index >= receive_index);
+
+        // Positions of the removed names, as they are at the time of their
+        // removal
+        let mut removed = Vec::new();
+        let mut offset = 0;
+        for names in self.imp().inner.borrow_mut().values_mut() {
+            let mut index = offset;
+            names.retain(|_, bus_name_item| {
+                let is_referenced = bus_name_item.is_referenced_since(receive_index);
+                if is_referenced {
+                    index += 1;
+                } else {
+                    removed.push(index as u32);
+                }
+                is_referenced
+            });
+            offset += names.len();
+        }
+
+        // Consecutive names end up at the same position once removed
+        let mut removed = removed.into_iter().peekable();
+        while let Some(position) = removed.next() {
+            let mut n_removed = 1;
+            while removed.next_if_eq(&position).is_some() {
+                n_removed += 1;
+            }
+            self.items_changed(position, n_removed, 0);
+        }
+    }
+
+    /// Forgets the well-known name changes that are no longer needed to look
+    /// up the names of messages at `receive_index` or later
+    pub fn trim_wk_name_logs(&self, receive_index: ReceiveIndex) {
+        for bus_name_item in self
+            .imp()
+            .inner
+            .borrow()
+            .values()
+            .flat_map(|names| names.values())
+        {
+            bus_name_item.trim_wk_name_log(receive_index);
         }
     }
 
     /// Returns the `BusNameItem` with the given `bus_name` as item's `bus_name`
-    pub fn get(&self, bus_name: &BusName<'_>) -> Option<BusNameItem> {
-        self.imp().inner.borrow().get(bus_name).cloned()
+    /// on `bus_type`
+    pub fn get(&self, bus_type: BusType, bus_name: &BusName<'_>) -> Option<BusNameItem> {
+        self.imp()
+            .inner
+            .borrow()
+            .get(&bus_type)?
+            .get(&item_name(bus_name))
+            .cloned()
+    }
+
+    /// Returns the `BusNameItem` that owns `bus_name` on `bus_type` at the
+    /// given lookup point, either as its unique name or as one of its
+    /// well-known names
+    pub fn lookup(
+        &self,
+        bus_type: BusType,
+        bus_name: &BusName<'_>,
+        lookup_point: LookupPoint,
+    ) -> Option<BusNameItem> {
+        self.get(bus_type, bus_name).or_else(|| match bus_name {
+            BusName::Unique(_) => None,
+            BusName::WellKnown(wk_name) => self
+                .imp()
+                .inner
+                .borrow()
+                .get(&bus_type)?
+                .values()
+                .find(|bus_name_item| bus_name_item.wk_names(lookup_point).contains(wk_name))
+                .cloned(),
+        })
+    }
+
+    /// Returns the position of the first name of `bus_type`
+    fn offset_of(&self, bus_type: BusType) -> usize {
+        self.imp()
+            .inner
+            .borrow()
+            .range(..bus_type)
+            .map(|(_, names)| names.len())
+            .sum()
+    }
+
+    /// Records that the names `message` is sent from and to are referenced
+    /// at its receive index
+    fn set_last_referenced(&self, message: &Message) {
+        let names = [message.sender().map(BusName::from), message.destination()];
+        for name in names.into_iter().flatten() {
+            if let Some(bus_name_item) =
+                self.lookup(message.bus_type(), &name, message.receive_index().into())
+            {
+                bus_name_item.set_last_referenced(message.receive_index());
+            }
+        }
+    }
+
+    /// Returns whether `bus_type` has a name equal to `bus_name`
+    fn contains(&self, bus_type: BusType, bus_name: &BusName<'_>) -> bool {
+        self.get(bus_type, bus_name).is_some()
     }
 
     fn handle_method_call_message(&self, message: &Message) -> Result<()> {
         debug_assert!(message.message_type().is_method_call());
 
+        let bus_type = message.bus_type();
+
         let sender = message.sender().context("Call message has no sender")?;
-        self.insert_bus_name(sender.to_owned().into());
+        self.insert_bus_name(bus_type, sender.to_owned().into());
 
         let destination = message
             .destination()
             .context("Call message has no destination")?;
-        self.insert_bus_name(destination.to_owned());
+        self.insert_bus_name(bus_type, destination.to_owned());
 
         Ok(())
     }
@@ -111,6 +272,7 @@ impl BusNameList {
         }
 
         let imp = self.imp();
+        let bus_type = message.bus_type();
 
         match call_message
             .destination()
@@ -119,23 +281,22 @@ impl BusNameList {
             BusName::Unique(unique_name) => {
                 // Already inserted on MethodCall handling
                 debug_assert!(
-                    imp.inner
-                        .borrow()
-                        .contains_key(&BusName::from(unique_name.as_ref())),
+                    self.contains(bus_type, &BusName::from(unique_name.as_ref())),
                     "{:?} was not found",
                     unique_name
                 );
             }
             BusName::WellKnown(dest_wk_name) => {
-                if !Message::is_fallback_destination(&BusName::from(dest_wk_name.as_ref())) {
+                let dest_name = BusName::from(dest_wk_name.as_ref());
+                // The bus driver is always keyed by its well-known name
+                if !Message::is_fallback_destination(&dest_name) && !is_bus_driver(&dest_name) {
                     // TODO All of these could be optimized by having a specialized `insert_wk_name`
                     // function, so instead of emitting items-changed thrice, we only emit it once.
 
                     // FIXME This disrupts ordering of names, see `IndexMap.move_index`
-                    let removed = imp
-                        .inner
-                        .borrow_mut()
-                        .shift_remove_full(&BusName::from(dest_wk_name.to_owned()));
+                    let removed = imp.inner.borrow_mut().get_mut(&bus_type).and_then(|names| {
+                        names.shift_remove_full(&BusName::from(dest_wk_name.to_owned()))
+                    });
 
                     if let Some((old_index, old_name, old_bus_name_item)) = removed {
                         debug_assert!(matches!(old_name, BusName::WellKnown(_)));
@@ -144,7 +305,8 @@ impl BusNameList {
                             IndexSet::new()
                         );
 
-                        self.items_changed(old_index as u32, 1, 0);
+                        let offset = self.offset_of(bus_type);
+                        self.items_changed((offset + old_index) as u32, 1, 0);
 
                         // There was a well-known name in bus names map (which we removed), and now
                         // that we already know its unique name through this return message, we can
@@ -152,6 +314,7 @@ impl BusNameList {
                         // to the items's well-known names.
                         let sender = message.sender().context("Return message has no sender")?;
                         self.insert_wk_name(
+                            bus_type,
                             sender.to_owned().into(),
                             dest_wk_name.to_owned(),
                             message.receive_index(),
@@ -163,7 +326,7 @@ impl BusNameList {
                         // First, get the index and the bus name item we just inserted.
                         let inner = imp.inner.borrow();
                         let (new_index, _, new_bus_name_item) =
-                            inner.get_full(&BusName::from(sender)).unwrap();
+                            inner[&bus_type].get_full(&BusName::from(sender)).unwrap();
 
                         // Then add to the log entry the well-known name when it was first discovered,
                         // which is the call message's receive index
@@ -175,14 +338,16 @@ impl BusNameList {
 
                         // Finally, notify that we have modified an item's well-known names
                         drop(inner);
-                        self.items_changed(new_index as u32, 1, 1);
+                        self.items_changed((offset + new_index) as u32, 1, 1);
                     } else {
                         // It was already handled by other return messages.
-                        debug_assert!(imp.inner.borrow().values().any(|bus_name_item| {
-                            bus_name_item
-                                .wk_names(message.receive_index().into())
-                                .contains(&dest_wk_name)
-                        }));
+                        debug_assert!(imp.inner.borrow()[&bus_type].values().any(
+                            |bus_name_item| {
+                                bus_name_item
+                                    .wk_names(message.receive_index().into())
+                                    .contains(&dest_wk_name)
+                            }
+                        ));
                     }
                 }
             }
@@ -195,9 +360,7 @@ impl BusNameList {
             BusName::Unique(unique_name) => {
                 // Already inserted on MethodCall handling
                 debug_assert!(
-                    imp.inner
-                        .borrow()
-                        .contains_key(&BusName::from(unique_name.as_ref())),
+                    self.contains(bus_type, &BusName::from(unique_name.as_ref())),
                     "{:?} was not found",
                     unique_name
                 );
@@ -207,6 +370,7 @@ impl BusNameList {
                     .sender()
                     .context("Call message has no sender")?;
                 self.insert_wk_name(
+                    bus_type,
                     sender.to_owned().into(),
                     dest_wk_name.to_owned(),
                     message.receive_index(),
@@ -249,6 +413,7 @@ impl BusNameList {
             }
             BusName::WellKnown(wk_name) => {
                 self.insert_wk_name(
+                    return_message.bus_type(),
                     owner.to_owned().into(),
                     wk_name.to_owned(),
                     return_message.receive_index(),
@@ -261,6 +426,11 @@ impl BusNameList {
 
     fn handle_signal_message(&self, message: &Message) -> Result<()> {
         debug_assert!(message.message_type().is_signal());
+
+        if let Some(sender) = message.sender() {
+            self.insert_bus_name(message.bus_type(), sender.to_owned().into());
+        }
+
         let header = message.header();
         if header.interface().cloned().as_deref() == DBusProxy::INTERFACE
             && header.member().cloned().as_deref() == Some("NameOwnerChanged")
@@ -270,12 +440,8 @@ impl BusNameList {
             return Ok(());
         }
 
-        if let Some(sender) = message.sender() {
-            self.insert_bus_name(sender.to_owned().into());
-        }
-
         if let Some(destination) = message.destination() {
-            self.insert_bus_name(destination.to_owned());
+            self.insert_bus_name(message.bus_type(), destination.to_owned());
         }
 
         Ok(())
@@ -300,6 +466,10 @@ impl BusNameList {
             bail!("Invalid NOC message; both old and new owner are none");
         }
 
+        if new_owner.is_none() {
+            self.emit_by_name::<()>("name-lost", &[&message.bus_type(), &name.as_str()]);
+        }
+
         let wk_name = match name {
             BusName::Unique(ref unique_name) => {
                 if let Some(old_owner) = old_owner.as_ref() {
@@ -308,6 +478,35 @@ impl BusNameList {
                 if let Some(new_owner) = new_owner.as_ref() {
                     debug_assert_eq!(unique_name, new_owner);
                 }
+
+                let lifetime_event = LifetimeEvent {
+                    receive_index: message.receive_index(),
+                    timestamp: message.timestamp(),
+                };
+                let bus_type = message.bus_type();
+                let pending_connections = &self.imp().pending_connections;
+                if new_owner.is_some() {
+                    if !self.set_lifetime_event(
+                        bus_type,
+                        unique_name,
+                        lifetime_event,
+                        BusNameItem::set_connected,
+                    ) {
+                        pending_connections
+                            .borrow_mut()
+                            .insert((bus_type, unique_name.to_owned()), lifetime_event);
+                    }
+                } else if !self.set_lifetime_event(
+                    bus_type,
+                    unique_name,
+                    lifetime_event,
+                    BusNameItem::set_disconnected,
+                ) {
+                    // The connection never showed up in a message
+                    pending_connections
+                        .borrow_mut()
+                        .remove(&(bus_type, unique_name.to_owned()));
+                }
                 return Ok(());
             }
             BusName::WellKnown(wk_name) => wk_name,
@@ -319,6 +518,7 @@ impl BusNameList {
 
         if let Some(old_owner) = old_owner.as_ref() {
             self.remove_wk_name(
+                message.bus_type(),
                 old_owner.to_owned().into(),
                 wk_name.to_owned(),
                 message.receive_index(),
@@ -327,6 +527,7 @@ impl BusNameList {
 
         if let Some(new_owner) = new_owner.as_ref() {
             self.insert_wk_name(
+                message.bus_type(),
                 new_owner.to_owned().into(),
                 wk_name.to_owned(),
                 message.receive_index(),
@@ -336,32 +537,96 @@ impl BusNameList {
         Ok(())
     }
 
-    /// Insert `bus_name` with empty `wk_names`
-    fn insert_bus_name(&self, bus_name: BusName<'static>) {
-        let index = match self.imp().inner.borrow_mut().entry(bus_name) {
+    /// Records when the connection of `unique_name` appeared or disappeared
+    /// on its item with `set`
+    ///
+    /// Returns false if `unique_name` has no item yet.
+    fn set_lifetime_event(
+        &self,
+        bus_type: BusType,
+        unique_name: &UniqueName<'_>,
+        lifetime_event: LifetimeEvent,
+        set: fn(&BusNameItem, LifetimeEvent),
+    ) -> bool {
+        let index = self
+            .imp()
+            .inner
+            .borrow()
+            .get(&bus_type)
+            .and_then(|names| names.get_full(&BusName::from(unique_name.as_ref())))
+            .map(|(index, _, bus_name_item)| {
+                set(bus_name_item, lifetime_event);
+                index
+            });
+        let Some(index) = index else {
+            return false;
+        };
+
+        let offset = self.offset_of(bus_type);
+        self.items_changed((offset + index) as u32, 1, 1);
+        true
+    }
+
+    /// Creates the item of `bus_name`, with when its connection appeared if
+    /// it was recorded already
+    fn new_bus_name_item(&self, bus_type: BusType, bus_name: BusName<'static>) -> BusNameItem {
+        let connected = match &bus_name {
+            BusName::Unique(unique_name) => self
+                .imp()
+                .pending_connections
+                .borrow_mut()
+                .remove(&(bus_type, unique_name.clone())),
+            BusName::WellKnown(_) => None,
+        };
+
+        let bus_name_item = BusNameItem::new(bus_type, bus_name);
+        if let Some(connected) = connected {
+            bus_name_item.set_connected(connected);
+        }
+        bus_name_item
+    }
+
+    /// Insert `bus_name` of `bus_type` with empty `wk_names`
+    fn insert_bus_name(&self, bus_type: BusType, bus_name: BusName<'static>) {
+        let index = match self
+            .imp()
+            .inner
+            .borrow_mut()
+            .entry(bus_type)
+            .or_default()
+            .entry(item_name(&bus_name))
+        {
             Entry::Occupied(_) => None,
             Entry::Vacant(entry) => {
                 let index = entry.index();
-                let bus_name_item = BusNameItem::new(entry.key().to_owned());
+                let bus_name_item = self.new_bus_name_item(bus_type, entry.key().to_owned());
                 entry.insert(bus_name_item);
                 Some(index)
             }
         };
         if let Some(index) = index {
-            self.items_changed(index as u32, 0, 1);
+            let offset = self.offset_of(bus_type);
+            self.items_changed((offset + index) as u32, 0, 1);
         }
     }
 
     /// Insert `wk_name` to `bus_name`'s `wk_names` or create `bus_name` entry first
     fn insert_wk_name(
         &self,
+        bus_type: BusType,
         bus_name: BusName<'static>,
         wk_name: WellKnownName<'static>,
         receive_index: ReceiveIndex,
     ) {
         let imp = self.imp();
 
-        let (index, removed, added) = match imp.inner.borrow_mut().entry(bus_name) {
+        let (index, removed, added) = match imp
+            .inner
+            .borrow_mut()
+            .entry(bus_type)
+            .or_default()
+            .entry(bus_name)
+        {
             Entry::Occupied(entry) => {
                 let bus_name_item = entry.get();
 
@@ -376,7 +641,7 @@ impl BusNameList {
                 (entry.index(), 1, 1)
             }
             Entry::Vacant(entry) => {
-                let bus_name_item = BusNameItem::new(entry.key().to_owned());
+                let bus_name_item = self.new_bus_name_item(bus_type, entry.key().to_owned());
 
                 let wk_names = IndexSet::from([wk_name]);
                 bus_name_item.insert_wk_name_log(receive_index, wk_names);
@@ -386,12 +651,13 @@ impl BusNameList {
                 (index, 0, 1)
             }
         };
-        self.items_changed(index as u32, removed, added);
+        let offset = self.offset_of(bus_type);
+        self.items_changed((offset + index) as u32, removed, added);
 
         if cfg!(debug_assertions) {
-            // Ensure that all well-known names has a unique owner
+            // Ensure that all well-known names has a unique owner on the bus
             let mut unique = HashSet::new();
-            for (bus_name, bus_name_item) in imp.inner.borrow().iter() {
+            for (bus_name, bus_name_item) in imp.inner.borrow()[&bus_type].iter() {
                 assert_eq!(bus_name, bus_name_item.name());
                 for wk_name in bus_name_item.wk_names(receive_index.into()) {
                     let was_inserted = unique.insert(wk_name.clone());
@@ -404,13 +670,20 @@ impl BusNameList {
     /// Remove `wk_name` from `bus_name`'s `wk_names` only if `bus_name` exists
     fn remove_wk_name(
         &self,
+        bus_type: BusType,
         bus_name: BusName<'static>,
         wk_name: WellKnownName<'static>,
         receive_index: ReceiveIndex,
     ) {
         let imp = self.imp();
 
-        let index = match imp.inner.borrow_mut().entry(bus_name) {
+        let index = match imp
+            .inner
+            .borrow_mut()
+            .entry(bus_type)
+            .or_default()
+            .entry(bus_name)
+        {
             Entry::Occupied(entry) => {
                 let bus_name_item = entry.get();
 
@@ -427,13 +700,14 @@ impl BusNameList {
             Entry::Vacant(_) => None,
         };
         if let Some(index) = index {
-            self.items_changed(index as u32, 1, 1);
+            let offset = self.offset_of(bus_type);
+            self.items_changed((offset + index) as u32, 1, 1);
         }
 
         if cfg!(debug_assertions) {
-            // Ensure that all well-known names has a unique owner
+            // Ensure that all well-known names has a unique owner on the bus
             let mut unique = HashSet::new();
-            for (bus_name, bus_name_item) in imp.inner.borrow().iter() {
+            for (bus_name, bus_name_item) in imp.inner.borrow()[&bus_type].iter() {
                 assert_eq!(bus_name, bus_name_item.name());
                 for wk_name in bus_name_item.wk_names(receive_index.into()) {
                     let was_inserted = unique.insert(wk_name.clone());
@@ -444,8 +718,210 @@ impl BusNameList {
     }
 }
 
+/// Returns whether `bus_name` is the bus driver, either as the well-known name
+/// it is called by or as the unique name it sends messages with
+pub fn is_bus_driver(bus_name: &BusName<'_>) -> bool {
+    Some(bus_name.as_str()) == DBusProxy::DESTINATION
+}
+
+/// Returns the name the item of `bus_name` is keyed by in `BusNameList`
+///
+/// The bus driver has no unique name of its own, so messages it sends and
+/// receives are given to a single item, keyed by its well-known name, to be
+/// drawn as a single column.
+pub fn item_name<'a>(bus_name: &BusName<'a>) -> BusName<'a> {
+    match bus_name {
+        BusName::Unique(_) if is_bus_driver(bus_name) => BusName::WellKnown(
+            WellKnownName::from_static_str_unchecked(DBusProxy::DESTINATION.unwrap()),
+        ),
+        _ => bus_name.clone(),
+    }
+}
+
 impl Default for BusNameList {
     fn default() -> Self {
         glib::Object::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        message_list::MessageList,
+        monitor::Event,
+        test_util::{event, signal},
+    };
+
+    fn name_owner_changed(name: &str, old_owner: &str, new_owner: &str) -> Event {
+        event(
+            zbus::Message::signal(
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "NameOwnerChanged",
+            )
+            .unwrap()
+            .sender("org.freedesktop.DBus")
+            .unwrap()
+            .build(&(name, old_owner, new_owner))
+            .unwrap(),
+            0,
+        )
+    }
+
+    fn names(bus_names: &BusNameList) -> Vec<(BusType, String)> {
+        bus_names
+            .iter::<BusNameItem>()
+            .map(|bus_name_item| {
+                let bus_name_item = bus_name_item.unwrap();
+                (bus_name_item.bus_type(), bus_name_item.name().to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn per_bus() {
+        let message_list = MessageList::default();
+        let bus_names = message_list.bus_names();
+
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        bus_names.connect_items_changed({
+            let changes = Rc::clone(&changes);
+            move |_, position, removed, added| {
+                changes.borrow_mut().push((position, removed, added));
+            }
+        });
+
+        message_list.push_batch(vec![event(signal(":1.1"), 0)], BusType::Session);
+        message_list.push_batch(
+            vec![event(signal(":1.1"), 0), event(signal(":1.2"), 0)],
+            BusType::System,
+        );
+        message_list.push_batch(vec![event(signal(":1.3"), 0)], BusType::Session);
+
+        assert_eq!(
+            names(bus_names),
+            [
+                (BusType::Session, ":1.1".to_string()),
+                (BusType::Session, ":1.3".to_string()),
+                (BusType::System, ":1.1".to_string()),
+                (BusType::System, ":1.2".to_string()),
+            ]
+        );
+        // The session names are inserted before the ones of the system bus
+        assert_eq!(
+            *changes.borrow(),
+            [(0, 0, 1), (1, 0, 1), (2, 0, 1), (1, 0, 1)]
+        );
+
+        assert_eq!(bus_names.offset_of(BusType::Session), 0);
+        assert_eq!(bus_names.offset_of(BusType::System), 2);
+
+        let unique_name = BusName::try_from(":1.2").unwrap();
+        assert!(bus_names.get(BusType::System, &unique_name).is_some());
+        assert!(bus_names.get(BusType::Session, &unique_name).is_none());
+    }
+
+    #[test]
+    fn evict_pending_connections() {
+        let message_list = MessageList::default();
+        let bus_names = message_list.bus_names();
+
+        // Connections that sent nothing yet
+        message_list.push_batch(
+            vec![
+                name_owner_changed(":1.5", "", ":1.5"),
+                name_owner_changed(":1.6", "", ":1.6"),
+            ],
+            BusType::Session,
+        );
+        bus_names.remove_unreferenced(ReceiveIndex::new(1));
+
+        let pending_connections = bus_names.imp().pending_connections.borrow().clone();
+        assert_eq!(
+            pending_connections.into_keys().collect::<Vec<_>>(),
+            [(BusType::Session, UniqueName::try_from(":1.6").unwrap())]
+        );
+
+        // Only the connection that is still retained is known to appear
+        message_list.push_batch(
+            vec![event(signal(":1.5"), 0), event(signal(":1.6"), 0)],
+            BusType::Session,
+        );
+        let connected = |name: &str| {
+            bus_names
+                .get(BusType::Session, &BusName::try_from(name).unwrap())
+                .unwrap()
+                .connected()
+        };
+        assert_eq!(connected(":1.5"), None);
+        assert_eq!(
+            connected(":1.6").map(|connected| connected.receive_index),
+            Some(ReceiveIndex::new(1))
+        );
+
+        // A connection that disappears before sending anything is forgotten
+        message_list.push_batch(
+            vec![
+                name_owner_changed(":1.7", "", ":1.7"),
+                name_owner_changed(":1.7", ":1.7", ""),
+            ],
+            BusType::Session,
+        );
+        assert!(bus_names.imp().pending_connections.borrow().is_empty());
+    }
+
+    #[test]
+    fn bus_driver() {
+        let message_list = MessageList::default();
+        let bus_names = message_list.bus_names();
+
+        let call = zbus::Message::method("/org/freedesktop/DBus", "ListNames")
+            .unwrap()
+            .interface("org.freedesktop.DBus")
+            .unwrap()
+            .sender(":1.1")
+            .unwrap()
+            .destination("org.freedesktop.DBus")
+            .unwrap()
+            .build(&())
+            .unwrap();
+        let reply = zbus::Message::method_reply(&call)
+            .unwrap()
+            .sender("org.freedesktop.DBus")
+            .unwrap()
+            .build(&())
+            .unwrap();
+        message_list.push_batch(
+            vec![
+                event(call, 0),
+                event(reply, 0),
+                name_owner_changed(":1.2", "", ":1.2"),
+            ],
+            BusType::Session,
+        );
+
+        // Called and sending with the same name, in a single item
+        assert_eq!(
+            names(bus_names),
+            [
+                (BusType::Session, ":1.1".to_string()),
+                (BusType::Session, "org.freedesktop.DBus".to_string()),
+            ]
+        );
+        let wk_name =
+            BusName::from(WellKnownName::from_static_str("org.freedesktop.DBus").unwrap());
+        let unique_name =
+            BusName::from(UniqueName::from_static_str("org.freedesktop.DBus").unwrap());
+        let bus_driver = bus_names.get(BusType::Session, &wk_name).unwrap();
+        assert_eq!(*bus_driver.name(), wk_name);
+        assert_eq!(
+            bus_names.get(BusType::Session, &unique_name),
+            Some(bus_driver)
+        );
+        assert!(is_bus_driver(&unique_name));
+        assert_eq!(item_name(&unique_name), wk_name);
+    }
+}
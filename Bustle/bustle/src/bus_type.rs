@@ -0,0 +1,32 @@
+use gtk::glib;
+
+/// The bus a message was recorded on
+///
+/// Recordings of a single bus other than the system bus use the default,
+/// and so do the files that do not tell the bus of their messages.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash, glib::Enum)]
+#[enum_type(name = "BustleBusType")]
+pub enum BusType {
+    #[default]
+    Session,
+    System,
+}
+
+impl BusType {
+    /// Returns the name of the PCAPNG interface the messages of the bus are
+    /// saved under
+    pub fn interface_name(self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::System => "system",
+        }
+    }
+
+    pub fn from_interface_name(name: &str) -> Option<Self> {
+        match name {
+            "session" => Some(Self::Session),
+            "system" => Some(Self::System),
+            _ => None,
+        }
+    }
+}
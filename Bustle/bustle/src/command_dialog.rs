@@ -0,0 +1,83 @@
+use std::ffi::OsString;
+
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::glib;
+
+pub struct Cancelled;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/command_dialog.ui")]
+    pub struct CommandDialog {
+        #[template_child]
+        pub(super) entry_row: TemplateChild<adw::EntryRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CommandDialog {
+        const NAME: &'static str = "BustleCommandDialog";
+        type Type = super::CommandDialog;
+        type ParentType = adw::AlertDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CommandDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.obj().update_record_response_enabled();
+            self.entry_row.grab_focus();
+        }
+    }
+
+    impl WidgetImpl for CommandDialog {}
+    impl AdwDialogImpl for CommandDialog {}
+    impl AdwAlertDialogImpl for CommandDialog {}
+}
+
+glib::wrapper! {
+    /// Asks for a command writing a PCAP stream to its standard output
+    pub struct CommandDialog(ObjectSubclass<imp::CommandDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::AlertDialog;
+}
+
+#[gtk::template_callbacks]
+impl CommandDialog {
+    /// Returns the command line as typed, and its arguments as split by a
+    /// shell
+    pub async fn choose(
+        parent: &impl IsA<gtk::Widget>,
+    ) -> Result<(String, Vec<OsString>), Cancelled> {
+        let this = glib::Object::new::<Self>();
+        let entry_row = this.imp().entry_row.get();
+
+        match this.choose_future(parent).await.as_str() {
+            "cancel" => Err(Cancelled),
+            "record" => {
+                let command_line = entry_row.text().to_string();
+                let argv = glib::shell_parse_argv(&command_line)
+                    .expect("command line must have been validated");
+                Ok((command_line, argv))
+            }
+            response_id => unreachable!("unexpected response id `{}`", response_id),
+        }
+    }
+
+    #[template_callback]
+    fn update_record_response_enabled(&self) {
+        self.set_response_enabled(
+            "record",
+            glib::shell_parse_argv(self.imp().entry_row.text()).is_ok(),
+        );
+    }
+}
@@ -1,11 +1,14 @@
+use std::time::Duration;
+
 use adw::prelude::*;
+use gettextrs::gettext;
 use gtk::{
     glib::{self, closure_local},
     subclass::prelude::*,
 };
-use zbus::zvariant;
+use zbus::{names::BusName, zvariant};
 
-use crate::message::Message;
+use crate::{bus_name_item::BusNameItem, i18n::gettext_f, message::Message, timestamp::Timestamp};
 
 mod imp {
     use std::cell::RefCell;
@@ -32,8 +35,12 @@ mod imp {
         #[template_child]
         pub(super) sender_row: TemplateChild<adw::ActionRow>,
         #[template_child]
+        pub(super) sender_lifetime_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub(super) destination_row: TemplateChild<adw::ActionRow>,
         #[template_child]
+        pub(super) destination_lifetime_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub(super) component_row: TemplateChild<adw::ActionRow>,
         #[template_child]
         pub(super) component_color: TemplateChild<ColorWidget>,
@@ -148,6 +155,24 @@ impl DetailsView {
             .set_subtitle(&message.map(|m| m.sender_display()).unwrap_or_default());
         imp.destination_row
             .set_subtitle(&message.map(|m| m.destination_display()).unwrap_or_default());
+
+        let sender_lifetime = message.and_then(|m| {
+            let bus_name_item = m.bus_name_item(&BusName::from(m.sender()?))?;
+            Some(lifetime_display(&bus_name_item, m))
+        });
+        imp.sender_lifetime_row
+            .set_visible(sender_lifetime.is_some());
+        imp.sender_lifetime_row
+            .set_subtitle(&sender_lifetime.unwrap_or_default());
+        let destination_lifetime = message.and_then(|m| {
+            let bus_name_item = m.bus_name_item(&m.destination()?)?;
+            Some(lifetime_display(&bus_name_item, m))
+        });
+        imp.destination_lifetime_row
+            .set_visible(destination_lifetime.is_some());
+        imp.destination_lifetime_row
+            .set_subtitle(&destination_lifetime.unwrap_or_default());
+
         let message_tag = message.map(|m| m.message_tag()).unwrap_or_default();
         imp.component_color.set_rgba(message_tag.color());
         imp.component_row.set_subtitle(&message_tag.name());
@@ -190,8 +215,7 @@ impl DetailsView {
 
         let response_time = message.and_then(|m| m.response_time());
         imp.response_time_row.set_visible(
-            response_time.is_some()
-                && message.map_or(false, |m| m.message_type().is_method_return()),
+            response_time.is_some() && message.is_some_and(|m| m.message_type().is_method_return()),
         );
         imp.response_time_row.set_subtitle(
             &response_time
@@ -212,6 +236,44 @@ impl DetailsView {
     }
 }
 
+/// Returns when the connection of `bus_name_item` appeared and disappeared,
+/// relative to the first message still recorded
+fn lifetime_display(bus_name_item: &BusNameItem, message: &Message) -> String {
+    let start = message
+        .message_list()
+        .and_then(|message_list| message_list.message_at(0))
+        .map_or(Duration::ZERO, |first| first.timestamp().into());
+    let elapsed_display = |timestamp: Timestamp| {
+        let elapsed = Timestamp::from(Duration::from(timestamp).saturating_sub(start));
+        format!("{} ms", elapsed.as_millis_f64().round())
+    };
+
+    match (bus_name_item.connected(), bus_name_item.disconnected()) {
+        (Some(connected), Some(disconnected)) => gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Connected at {connected}, disconnected at {disconnected}",
+            &[
+                ("connected", &elapsed_display(connected.timestamp)),
+                ("disconnected", &elapsed_display(disconnected.timestamp)),
+            ],
+        ),
+        (Some(connected), None) => gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Connected at {connected}",
+            &[("connected", &elapsed_display(connected.timestamp))],
+        ),
+        (None, Some(disconnected)) => gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Connected before the recording, disconnected at {disconnected}",
+            &[("disconnected", &elapsed_display(disconnected.timestamp))],
+        ),
+        (None, None) => gettext("Connected before the recording"),
+    }
+}
+
 #[gtk::template_callbacks]
 impl DetailsView {
     #[template_callback]
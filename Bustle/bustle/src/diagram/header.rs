@@ -1,7 +1,7 @@
 use adw::prelude::*;
 use gtk::{
     glib::{self, clone},
-    graphene::Point,
+    graphene::{Point, Rect},
     pango,
     subclass::prelude::*,
 };
@@ -13,6 +13,7 @@ use crate::{
 };
 
 const TEXT_PADDING: i32 = 3;
+const BUS_SEPARATOR_WIDTH: f32 = 2.0;
 const EXPAND_ANIMATION_DURATION_MS: u32 = 200;
 
 mod imp {
@@ -135,6 +136,20 @@ mod imp {
                 cursor_x += column_width;
             }
 
+            // Separate the names of the session bus from the system bus ones
+            if let Some(bus_boundary) = self.model.get().and_then(|model| model.bus_boundary()) {
+                let x = obj.first_column_x() + (bus_boundary as f32 - 0.5) * column_width;
+                snapshot.append_color(
+                    &color.with_alpha(color.alpha() * 0.6),
+                    &Rect::new(
+                        x - BUS_SEPARATOR_WIDTH / 2.0,
+                        0.0,
+                        BUS_SEPARATOR_WIDTH,
+                        obj.height() as f32,
+                    ),
+                );
+            }
+
             self.parent_snapshot(snapshot);
         }
     }
@@ -213,7 +228,9 @@ impl Header {
             let name = bus_name_item.name();
             let name_display = match *name {
                 BusName::Unique(ref unique_name) => unique_name.as_str(),
-                BusName::WellKnown(ref wk_name) => wk_name.split('.').last().unwrap_or_default(),
+                BusName::WellKnown(ref wk_name) => {
+                    wk_name.split('.').next_back().unwrap_or_default()
+                }
             };
             let mut lines = vec![format!(
                 r#"<b><span size="small">{}</span></b>"#,
@@ -228,7 +245,7 @@ impl Header {
                     .filter_map(|other_name| {
                         other_name
                             .split('.')
-                            .last()
+                            .next_back()
                             .map(|last| format!(r#"<span size="x-small">{}</span>"#, last))
                     }),
             );
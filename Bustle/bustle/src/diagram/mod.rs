@@ -160,6 +160,11 @@ impl Diagram {
         self.imp().view.scroll_to(message, flags)
     }
 
+    /// Draws the messages again, as their state may have changed
+    pub fn queue_draw_messages(&self) {
+        self.imp().view.queue_draw();
+    }
+
     /// Returns the x coordinate where the first column should be drawn.
     fn first_column_initial_x(&self) -> f32 {
         adw::LengthUnit::Sp.to_px(FIRST_COLUMN_INITIAL_X_SP, Some(&self.settings())) as f32
@@ -11,6 +11,7 @@ use indexmap::IndexMap;
 use zbus::names::BusName;
 
 use crate::{
+    bus_name_list::item_name,
     diagram::row::Row,
     filtered_message_model::FilteredMessageModel,
     message::{Message, ReceiveIndex},
@@ -18,7 +19,16 @@ use crate::{
 };
 
 const COLUMN_LINE_WIDTH: f32 = 1.0;
+const BUS_SEPARATOR_LINE_WIDTH: f32 = 2.0;
 const ARROW_LINE_WIDTH: f32 = 2.0;
+const LIFETIME_MARKER_SIZE: f32 = 6.0;
+
+/// Where a message falls relative to the drawn rows
+enum RowPosition {
+    Above,
+    At(f32),
+    Below,
+}
 
 /// Determines on what side the arc will curve
 enum ArcSide {
@@ -126,7 +136,7 @@ mod imp {
                 return;
             };
 
-            obj.draw_columns(snapshot);
+            obj.draw_columns(snapshot, &obj.drawn_rows());
 
             for row in self.rows.borrow().iter() {
                 let Some(row) = row.upgrade() else {
@@ -317,17 +327,20 @@ impl View {
         Ok(())
     }
 
-    /// Returns the x coordinate where the given name must be placed
-    fn x_for_name(&self, name: &BusName<'_>, receive_index: ReceiveIndex) -> Result<f32> {
+    /// Returns the x coordinate where the given name, as seen by `message`,
+    /// must be placed
+    fn x_for_name(&self, name: &BusName<'_>, message: &Message) -> Result<f32> {
         let model = self.model();
         let bus_names = model.filtered_bus_names();
+        let bus_type = message.bus_type();
+        let receive_index = message.receive_index();
 
-        let bus_name_index = bus_names.get_index_of(name).or_else(|| {
+        let bus_name_index = bus_names.get_index_of(bus_type, name).or_else(|| {
             tracing::trace!("`{}` was not found in keys; looking for other names", name);
             match name {
                 BusName::Unique(_) => None,
                 BusName::WellKnown(ref wk_name) => {
-                    bus_names.get_index_of_wk_name(wk_name, receive_index.into())
+                    bus_names.get_index_of_wk_name(bus_type, wk_name, receive_index.into())
                 }
             }
         });
@@ -464,7 +477,51 @@ impl View {
         }
     }
 
-    fn draw_columns(&self, snapshot: &gtk::Snapshot) {
+    /// Returns the receive index and center y coordinate of the rows that
+    /// are drawn, ordered by receive index
+    fn drawn_rows(&self) -> Vec<(ReceiveIndex, f32)> {
+        let mut drawn_rows = self
+            .imp()
+            .rows
+            .borrow()
+            .iter()
+            .filter_map(|row| {
+                let row = row.upgrade().filter(|row| row.is_drawable())?;
+                let message = row.message()?;
+                let row_center_y = row
+                    .compute_point(
+                        self,
+                        &Point::new(row.width() as f32 / 2.0, row.height() as f32 / 2.0),
+                    )?
+                    .y();
+                Some((message.receive_index(), row_center_y))
+            })
+            .collect::<Vec<_>>();
+        drawn_rows.sort_by_key(|(receive_index, _)| *receive_index);
+        drawn_rows
+    }
+
+    /// Returns where the message at `receive_index` falls relative to
+    /// `drawn_rows`, halfway between the rows around it if it is not drawn,
+    /// e.g., because it is filtered out
+    fn row_position(
+        &self,
+        drawn_rows: &[(ReceiveIndex, f32)],
+        receive_index: ReceiveIndex,
+    ) -> RowPosition {
+        let next = drawn_rows.partition_point(|(index, _)| *index < receive_index);
+        match (
+            next.checked_sub(1).map(|prev| drawn_rows[prev]),
+            drawn_rows.get(next),
+        ) {
+            (_, Some((index, y))) if *index == receive_index => RowPosition::At(*y),
+            (Some((_, prev_y)), Some((_, next_y))) => RowPosition::At((prev_y + next_y) / 2.0),
+            (None, _) => RowPosition::Above,
+            (Some(_), None) => RowPosition::Below,
+        }
+    }
+
+    fn draw_columns(&self, snapshot: &gtk::Snapshot, drawn_rows: &[(ReceiveIndex, f32)]) {
         let color = self.color();
         let height = self.height();
 
@@ -475,12 +532,50 @@ impl View {
         let bus_names = model.filtered_bus_names();
 
         let path_builder = gsk::PathBuilder::new();
+        // Columns of connections that were closed are greyed out below the
+        // point they disappeared
+        let disconnected_path_builder = gsk::PathBuilder::new();
+        let marker_path_builder = gsk::PathBuilder::new();
 
         let mut cursor_x = first_column_x;
-        for _ in bus_names.iter() {
+        for bus_name_item in bus_names.iter() {
             let x = cursor_x.round();
-            path_builder.move_to(x, 0.0);
-            path_builder.line_to(x, height as f32);
+
+            let disconnected = bus_name_item
+                .disconnected()
+                .map(|disconnected| self.row_position(drawn_rows, disconnected.receive_index));
+            match disconnected {
+                None | Some(RowPosition::Below) => {
+                    path_builder.move_to(x, 0.0);
+                    path_builder.line_to(x, height as f32);
+                }
+                Some(RowPosition::At(y)) => {
+                    path_builder.move_to(x, 0.0);
+                    path_builder.line_to(x, y);
+                    disconnected_path_builder.move_to(x, y);
+                    disconnected_path_builder.line_to(x, height as f32);
+
+                    // Cross
+                    marker_path_builder.move_to(x - LIFETIME_MARKER_SIZE, y - LIFETIME_MARKER_SIZE);
+                    marker_path_builder.line_to(x + LIFETIME_MARKER_SIZE, y + LIFETIME_MARKER_SIZE);
+                    marker_path_builder.move_to(x + LIFETIME_MARKER_SIZE, y - LIFETIME_MARKER_SIZE);
+                    marker_path_builder.line_to(x - LIFETIME_MARKER_SIZE, y + LIFETIME_MARKER_SIZE);
+                }
+                Some(RowPosition::Above) => {
+                    disconnected_path_builder.move_to(x, 0.0);
+                    disconnected_path_builder.line_to(x, height as f32);
+                }
+            }
+
+            let connected = bus_name_item
+                .connected()
+                .map(|connected| self.row_position(drawn_rows, connected.receive_index));
+            if let Some(RowPosition::At(y)) = connected {
+                // Bar
+                marker_path_builder.move_to(x - LIFETIME_MARKER_SIZE, y);
+                marker_path_builder.line_to(x + LIFETIME_MARKER_SIZE, y);
+            }
+
             cursor_x += column_width;
         }
 
@@ -490,6 +585,32 @@ impl View {
         );
         snapshot.append_color(&color.with_alpha(color.alpha() * 0.3), &self.bounds());
         snapshot.pop();
+
+        let disconnected_stroke = gsk::Stroke::new(COLUMN_LINE_WIDTH);
+        disconnected_stroke.set_dash(&[2.0, 4.0]);
+        snapshot.push_stroke(&disconnected_path_builder.to_path(), &disconnected_stroke);
+        snapshot.append_color(&color.with_alpha(color.alpha() * 0.15), &self.bounds());
+        snapshot.pop();
+
+        snapshot.push_stroke(&marker_path_builder.to_path(), &self.arrow_stroke());
+        snapshot.append_color(&color.with_alpha(color.alpha() * 0.6), &self.bounds());
+        snapshot.pop();
+
+        // Separate the columns of the session bus from the system bus ones
+        if let Some(bus_boundary) = bus_names.bus_boundary() {
+            let x = (first_column_x + (bus_boundary as f32 - 0.5) * column_width).round();
+
+            let path_builder = gsk::PathBuilder::new();
+            path_builder.move_to(x, 0.0);
+            path_builder.line_to(x, height as f32);
+
+            snapshot.push_stroke(
+                &path_builder.to_path(),
+                &gsk::Stroke::new(BUS_SEPARATOR_LINE_WIDTH),
+            );
+            snapshot.append_color(&color.with_alpha(color.alpha() * 0.6), &self.bounds());
+            snapshot.pop();
+        }
     }
 
     fn draw_method_call(
@@ -503,8 +624,8 @@ impl View {
             .destination()
             .context("Call message has no destination")?;
 
-        let start_x = self.x_for_name(&BusName::from(sender), message.receive_index())?;
-        let end_x = self.x_for_name(&destination, message.receive_index())?;
+        let start_x = self.x_for_name(&BusName::from(sender), message)?;
+        let end_x = self.x_for_name(&destination, message)?;
 
         let path_builder = gsk::PathBuilder::new();
         path_builder.add_arrow(
@@ -512,8 +633,21 @@ impl View {
             &Point::new(end_x, row_center_y),
             ArrowTipType::TopHalf,
         );
-        snapshot.push_stroke(&path_builder.to_path(), &self.arrow_stroke());
-        snapshot.append_color(&self.color(), &self.bounds());
+
+        // Calls that never got the reply they expected are dashed
+        let is_unanswered = self
+            .model()
+            .message_list()
+            .is_some_and(|message_list| message_list.is_call_unanswered(message));
+        let (arrow_stroke, arrow_color) = if is_unanswered {
+            let stroke = self.arrow_stroke();
+            stroke.set_dash(&[6.0, 4.0]);
+            (stroke, self.error_arrow_color())
+        } else {
+            (self.arrow_stroke(), self.color())
+        };
+        snapshot.push_stroke(&path_builder.to_path(), &arrow_stroke);
+        snapshot.append_color(&arrow_color, &self.bounds());
         snapshot.pop();
 
         Ok(())
@@ -537,12 +671,15 @@ impl View {
         debug_assert!(call_message
             .destination()
             .is_some_and(|call_message_destination| call_message_destination
-                == return_message_sender
+                == item_name(&BusName::from(return_message_sender.as_ref()))
                 || match call_message_destination {
                     BusName::Unique(_) => false,
                     BusName::WellKnown(ref wk_name) => model
                         .filtered_bus_names()
-                        .get(&BusName::from(return_message_sender.as_ref()))
+                        .get(
+                            return_message.bus_type(),
+                            &BusName::from(return_message_sender.as_ref())
+                        )
                         .unwrap()
                         .wk_names(return_message.receive_index().into())
                         .contains(wk_name),
@@ -559,20 +696,17 @@ impl View {
                     BusName::Unique(_) => false,
                     BusName::WellKnown(ref wk_name) => model
                         .filtered_bus_names()
-                        .get(&BusName::from(call_message_sender.as_ref()))
+                        .get(
+                            call_message.bus_type(),
+                            &BusName::from(call_message_sender.as_ref())
+                        )
                         .unwrap()
                         .wk_names(return_message.receive_index().into())
                         .contains(wk_name),
                 }));
 
-        let start_x = self.x_for_name(
-            &BusName::from(return_message_sender),
-            return_message.receive_index(),
-        )?;
-        let end_x = self.x_for_name(
-            &BusName::from(call_message_sender),
-            return_message.receive_index(),
-        )?;
+        let start_x = self.x_for_name(&BusName::from(return_message_sender), return_message)?;
+        let end_x = self.x_for_name(&BusName::from(call_message_sender), return_message)?;
 
         let arrow_path_builder = gsk::PathBuilder::new();
         arrow_path_builder.add_arrow(
@@ -613,10 +747,20 @@ impl View {
                 ArcSide::Right
             },
         );
+        // Flag replies that arrived after the caller most likely timed out
+        let is_late_reply = return_message.is_late_reply();
+
         let arc_stroke = gsk::Stroke::new(ARROW_LINE_WIDTH);
         arc_stroke.set_dash(&[3.0, 3.0]);
         snapshot.push_stroke(&arc_path_builder.to_path(), &arc_stroke);
-        snapshot.append_color(&self.method_arc_color(), &self.bounds());
+        snapshot.append_color(
+            &if is_late_reply {
+                self.error_arrow_color()
+            } else {
+                self.method_arc_color()
+            },
+            &self.bounds(),
+        );
         snapshot.pop();
 
         let response_time_layout = self
@@ -644,7 +788,14 @@ impl View {
                 return_row_center_y - ink_extents.height() as f32,
             ));
         }
-        snapshot.append_layout(response_time_layout, &self.color());
+        snapshot.append_layout(
+            response_time_layout,
+            &if is_late_reply {
+                self.error_arrow_color()
+            } else {
+                self.color()
+            },
+        );
         snapshot.restore();
 
         Ok(())
@@ -660,7 +811,7 @@ impl View {
         const ARROW_END_OFFSET: f32 = 20.0;
 
         let sender = message.sender().context("Signal message has no sender")?;
-        let start_x = self.x_for_name(&BusName::from(sender), message.receive_index())?;
+        let start_x = self.x_for_name(&BusName::from(sender), message)?;
 
         let path_builder = gsk::PathBuilder::new();
 
@@ -669,7 +820,7 @@ impl View {
         if let Some(destination) = message.destination() {
             // This is a targeted signal
 
-            let end_x = self.x_for_name(&destination, message.receive_index())?;
+            let end_x = self.x_for_name(&destination, message)?;
 
             path_builder.add_arrow(
                 &Point::new(
@@ -0,0 +1,130 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{bus_type::BusType, monitor::Event, timestamp::Timestamp};
+
+/// How long events are held back at most, waiting for older events of the
+/// other buses
+pub const MERGE_DELAY: Duration = Duration::from_millis(500);
+
+/// Merges the events recorded on several buses in timestamp order
+///
+/// Each bus delivers its events in order, but in its own batches. An event is
+/// held back until every bus delivered one at least as recent, or until it is
+/// older than `MERGE_DELAY`, so that it cannot be preceded by a later batch.
+#[derive(Debug, Default)]
+pub struct EventMerger {
+    pending: Vec<(Event, BusType)>,
+    /// The most recent timestamp delivered on each bus
+    latest: BTreeMap<BusType, Timestamp>,
+}
+
+impl EventMerger {
+    pub fn new(bus_types: impl IntoIterator<Item = BusType>) -> Self {
+        Self {
+            pending: Vec::new(),
+            latest: bus_types
+                .into_iter()
+                .map(|bus_type| (bus_type, Timestamp::default()))
+                .collect(),
+        }
+    }
+
+    pub fn push(&mut self, events: Vec<Event>, bus_type: BusType) {
+        let latest = self.latest.entry(bus_type).or_default();
+        for event in &events {
+            *latest = (*latest).max(event.timestamp);
+        }
+        self.pending
+            .extend(events.into_iter().map(|event| (event, bus_type)));
+    }
+
+    /// Removes the events that can no longer be preceded by a later batch,
+    /// in timestamp order
+    pub fn pop_ready(&mut self, now: Timestamp) -> Vec<(Event, BusType)> {
+        let all_delivered = self.latest.values().min().copied().unwrap_or_default();
+        let expired = now - Timestamp::from(MERGE_DELAY);
+        let threshold = all_delivered.max(expired);
+
+        // The sort is stable, so events of the same time keep their order
+        self.pending.sort_by_key(|(event, _)| event.timestamp);
+        let n_ready = self
+            .pending
+            .partition_point(|(event, _)| event.timestamp <= threshold);
+        self.pending.drain(..n_ready).collect()
+    }
+
+    /// Removes all the held back events, in timestamp order
+    pub fn pop_all(&mut self) -> Vec<(Event, BusType)> {
+        self.pending.sort_by_key(|(event, _)| event.timestamp);
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, signal};
+
+    fn millis(events: &[(Event, BusType)]) -> Vec<(u64, BusType)> {
+        events
+            .iter()
+            .map(|(event, bus_type)| {
+                (
+                    Duration::from(event.timestamp).as_millis() as u64,
+                    *bus_type,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleaved_batches() {
+        let mut merger = EventMerger::new([BusType::Session, BusType::System]);
+        let now = Timestamp::default();
+
+        merger.push(
+            vec![event(signal(":1.1"), 10), event(signal(":1.1"), 30)],
+            BusType::Session,
+        );
+        assert!(merger.pop_ready(now).is_empty());
+
+        merger.push(
+            vec![event(signal(":1.1"), 20), event(signal(":1.1"), 40)],
+            BusType::System,
+        );
+        assert_eq!(
+            millis(&merger.pop_ready(now)),
+            [
+                (10, BusType::Session),
+                (20, BusType::System),
+                (30, BusType::Session)
+            ]
+        );
+
+        merger.push(
+            vec![event(signal(":1.1"), 35), event(signal(":1.1"), 50)],
+            BusType::Session,
+        );
+        assert_eq!(
+            millis(&merger.pop_ready(now)),
+            [(35, BusType::Session), (40, BusType::System)]
+        );
+
+        assert_eq!(millis(&merger.pop_all()), [(50, BusType::Session)]);
+    }
+
+    #[test]
+    fn expired() {
+        let mut merger = EventMerger::new([BusType::Session, BusType::System]);
+
+        merger.push(
+            vec![event(signal(":1.1"), 10), event(signal(":1.1"), 1000)],
+            BusType::Session,
+        );
+        assert_eq!(
+            millis(&merger.pop_ready(Timestamp::from(Duration::from_millis(600)))),
+            [(10, BusType::Session)]
+        );
+        assert_eq!(millis(&merger.pop_all()), [(1000, BusType::Session)]);
+    }
+}
@@ -60,7 +60,7 @@ mod imp {
             let subtitle_text = bus_name_item
                 .wk_names(LookupPoint::Last)
                 .iter()
-                .filter_map(|n| n.split('.').last()) // filtering out the last part of each name
+                .filter_map(|n| n.split('.').next_back()) // filtering out the last part of each name
                 .collect::<Vec<_>>()
                 .join(", ");
             self.subtitle.set_label(&subtitle_text); // sets the label of the subtitle widget of the BusNameRow to the constructed subtitle text.
@@ -38,6 +38,8 @@ mod imp {
         #[template_child]
         pub(super) bus_name_list_box: TemplateChild<gtk::ListBox>,
         #[template_child]
+        pub(super) bus_driver_check_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
         child: TemplateChild<gtk::Widget>,
     }
 
@@ -166,6 +168,12 @@ mod imp {
                 }),
             );
 
+            model
+                .bind_property("shows-bus-driver", &*self.bus_driver_check_button, "active")
+                .bidirectional()
+                .sync_create()
+                .build();
+
             self.model.set(model).unwrap();
         }
     }
@@ -210,11 +218,13 @@ impl FilterPane {
             let name = bus_name_item.name();
             println!("create_bus_name_row >> name {:?}", name);
             if row.is_active() {
-                let was_removed = model.remove_bus_name_filter(name);
+                let was_removed = model.remove_bus_name_filter(bus_name_item.bus_type(), name);
                 debug_assert!(was_removed);
                 println!("create_bus_name_row >> was_removed {:?}", was_removed);
             } else {
-                model.add_bus_name_filter(name).unwrap();
+                model
+                    .add_bus_name_filter(bus_name_item.bus_type(), name)
+                    .unwrap();
             }
         }));
         row
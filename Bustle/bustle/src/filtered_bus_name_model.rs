@@ -10,7 +10,8 @@ use zbus::names::{BusName, WellKnownName};
 // Import local modules
 use crate::{
     bus_name_item::{BusNameItem, LookupPoint},
-    bus_name_list::BusNameList,
+    bus_name_list::{item_name, BusNameList},
+    bus_type::BusType,
 };
 
 // Define a submodule named 'imp'
@@ -127,33 +128,57 @@ impl FilteredBusNameModel {
         ListModelExtManual::iter(self).map(|item| item.unwrap())
     }
 
-    // Get the BusNameItem with the given bus name
-    pub fn get(&self, bus_name: &BusName<'_>) -> Option<BusNameItem> {
-        self.iter()
-            .find(|bus_name_item| *bus_name_item.name() == *bus_name)
+    // Get the BusNameItem with the given bus name on the given bus
+    pub fn get(&self, bus_type: BusType, bus_name: &BusName<'_>) -> Option<BusNameItem> {
+        let bus_name = item_name(bus_name);
+        self.iter().find(|bus_name_item| {
+            bus_name_item.bus_type() == bus_type && *bus_name_item.name() == bus_name
+        })
     }
 
-    // Get the index of the BusNameItem with the given bus name
-    pub fn get_index_of(&self, bus_name: &BusName<'_>) -> Option<usize> {
-        self.iter()
-            .position(|bus_name_item| *bus_name_item.name() == *bus_name)
+    // Get the index of the BusNameItem with the given bus name on the given bus
+    pub fn get_index_of(&self, bus_type: BusType, bus_name: &BusName<'_>) -> Option<usize> {
+        let bus_name = item_name(bus_name);
+        self.iter().position(|bus_name_item| {
+            bus_name_item.bus_type() == bus_type && *bus_name_item.name() == bus_name
+        })
     }
 
     // Get the index of the BusNameItem containing the well-known name at the specified lookup point
     pub fn get_index_of_wk_name(
         &self,
+        bus_type: BusType,
         wk_name: &WellKnownName<'_>,
         lookup_point: LookupPoint,
     ) -> Option<usize> {
         debug_assert_eq!(
-            self.get(&BusName::from(wk_name.as_ref())),
+            self.get(bus_type, &BusName::from(wk_name.as_ref())),
             None,
             "`get` or `get_index_of` must be used first"
         );
 
-        self.iter()
-            .position(|bus_name_item| bus_name_item.wk_names(lookup_point).contains(wk_name))
+        self.iter().position(|bus_name_item| {
+            bus_name_item.bus_type() == bus_type
+                && bus_name_item.wk_names(lookup_point).contains(wk_name)
+        })
     }
+
+    /// Returns the index of the first name of the system bus, if names of
+    /// both buses are shown
+    ///
+    /// Names are grouped by bus, with the ones of the session bus first.
+    pub fn bus_boundary(&self) -> Option<usize> {
+        bus_boundary(self.iter().map(|bus_name_item| bus_name_item.bus_type()))
+    }
+}
+
+/// Returns the index of the first of `bus_types` that differs from the ones
+/// before it, if any
+fn bus_boundary(mut bus_types: impl Iterator<Item = BusType>) -> Option<usize> {
+    let first_bus_type = bus_types.next()?;
+    bus_types
+        .position(|bus_type| bus_type != first_bus_type)
+        .map(|position| position + 1)
 }
 
 // Implement the Default trait for FilteredBusNameModel
@@ -162,3 +187,33 @@ impl Default for FilteredBusNameModel {
         glib::Object::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary() {
+        assert_eq!(bus_boundary([].into_iter()), None);
+        assert_eq!(
+            bus_boundary([BusType::Session, BusType::Session].into_iter()),
+            None
+        );
+        assert_eq!(
+            bus_boundary([BusType::System, BusType::System].into_iter()),
+            None
+        );
+        assert_eq!(
+            bus_boundary(
+                [
+                    BusType::Session,
+                    BusType::Session,
+                    BusType::System,
+                    BusType::System
+                ]
+                .into_iter()
+            ),
+            Some(2)
+        );
+    }
+}
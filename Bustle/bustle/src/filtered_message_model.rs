@@ -1,3 +1,5 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
 use anyhow::{Context, Result};
 use gtk::{
     gio,
@@ -5,12 +7,14 @@ use gtk::{
     prelude::*,
     subclass::prelude::*,
 };
-use zbus::{fdo::DBusProxy, names::BusName, ProxyDefault};
+use zbus::names::BusName;
 
 use crate::{
     bus_name_item::{BusNameItem, LookupPoint},
+    bus_name_list::{is_bus_driver, item_name},
+    bus_type::BusType,
     filtered_bus_name_model::FilteredBusNameModel,
-    message::Message,
+    message::{Message, ReceiveIndex},
     message_list::MessageList,
     message_tag::MessageTag,
 };
@@ -18,14 +22,10 @@ use crate::{
 mod imp {
     // Import necessary standard library and external dependencies
     use std::{
-        cell::RefCell,
-        collections::{HashMap, HashSet},
+        cell::{Cell, OnceCell, RefCell},
         marker::PhantomData,
     };
 
-    // Import the IndexSet data structure from the indexmap crate
-    use indexmap::IndexSet;
-
     // Import the parent module
     use super::*;
 
@@ -36,11 +36,15 @@ mod imp {
         // A PhantomData field to hold the type information for `has_filter`
         #[property(get = Self::has_filter)]
         pub(super) has_filter: PhantomData<bool>,
+        /// Whether messages to or from the bus driver are shown
+        #[property(get, set = Self::set_shows_bus_driver, explicit_notify)]
+        pub(super) shows_bus_driver: Cell<bool>,
 
         // The inner filter list model
         pub(super) inner: gtk::FilterListModel,
-        // A RefCell to hold the index set of messages in the inner model
-        pub(super) inner_index: RefCell<IndexSet<Message>>,
+        // A RefCell to hold an entry for each message in the inner model, in
+        // the same order, so messages don't have to be kept alive
+        pub(super) inner_index: RefCell<VecDeque<IndexEntry>>,
 
         // The filtered bus name model
         pub(super) filtered_bus_names: FilteredBusNameModel,
@@ -48,10 +52,16 @@ mod imp {
         // A RefCell to hold the indices of message tag filters
         pub(super) message_tag_filter_indices: RefCell<HashMap<MessageTag, u32>>,
         // A RefCell to hold the indices of bus name filters
-        pub(super) bus_name_filter_indices: RefCell<HashMap<BusName<'static>, u32>>,
+        pub(super) bus_name_filter_indices: RefCell<HashMap<(BusType, BusName<'static>), u32>>,
+
+        // A RefCell to hold the used bus names, along with the number of
+        // messages in the inner model using them
+        pub(super) used_bus_names: RefCell<UsedBusNames>,
 
-        // A RefCell to hold the set of used bus names
-        pub(super) used_bus_names: RefCell<HashSet<BusName<'static>>>,
+        /// Messages to exclusively show, if set
+        pub(super) message_subset: RefCell<Option<HashSet<ReceiveIndex>>>,
+        pub(super) message_subset_filter: OnceCell<gtk::CustomFilter>,
+        pub(super) bus_driver_filter: OnceCell<gtk::CustomFilter>,
     }
 
     // Implement GObject subclassing for `FilteredMessageModel`
@@ -85,7 +95,6 @@ mod imp {
             self.inner.connect_items_changed(
                 clone!(@weak obj, @weak bus_names_filter => move |_, position, removed, added| {
                     obj.update_inner_index(position, removed, added);
-                    obj.update_used_names(position, removed, added);
                     bus_names_filter.changed(gtk::FilterChange::Different);
                     obj.items_changed(position, removed, added);
                 }),
@@ -96,16 +105,36 @@ mod imp {
 
             // Create an every filter for message filtering
             let filter = gtk::EveryFilter::new();
-            filter.append(gtk::CustomFilter::new(|message| {
-                let message = message.downcast_ref::<Message>().unwrap();
 
-                // Filter out messages related to DBusProxy
-                // dbus_bus_name_exists_in_dbus(DBusProxy::DESTINATION)
-                //     && (message.destination().as_deref() != Some(DBusProxy::DESTINATION)
-                //         || message.sender().as_deref() != Some(DBusProxy::DESTINATION))
-                message.destination().as_deref() != DBusProxy::DESTINATION
-                    && message.sender().as_deref() != DBusProxy::DESTINATION
-            }));
+            // Filter out messages to or from the bus driver, unless shown
+            let bus_driver_filter =
+                gtk::CustomFilter::new(clone!(@weak obj => @default-panic, move |message| {
+                    let message = message.downcast_ref::<Message>().unwrap();
+                    obj.imp().shows_bus_driver.get()
+                        || !(message
+                            .destination()
+                            .is_some_and(|destination| is_bus_driver(&destination))
+                            || message
+                                .sender()
+                                .is_some_and(|sender| is_bus_driver(&BusName::from(sender))))
+                }));
+            filter.append(bus_driver_filter.clone());
+            self.bus_driver_filter.set(bus_driver_filter).unwrap();
+
+            // Always present, so the indices of the removable filters
+            // appended after it are not affected
+            let message_subset_filter =
+                gtk::CustomFilter::new(clone!(@weak obj => @default-panic, move |message| {
+                    let message = message.downcast_ref::<Message>().unwrap();
+                    let message_subset = obj.imp().message_subset.borrow();
+                    message_subset.as_ref().is_none_or(|message_subset| {
+                        message_subset.contains(&message.receive_index())
+                    })
+                }));
+            filter.append(message_subset_filter.clone());
+            self.message_subset_filter
+                .set(message_subset_filter)
+                .unwrap();
 
             // Set the filter for the inner model
             self.inner.set_filter(Some(&filter));
@@ -141,20 +170,88 @@ mod imp {
     impl FilteredMessageModel {
         // Check if any filter is applied
         fn has_filter(&self) -> bool {
-            self.message_tag_filter_indices.borrow().len() != 0
-                || self.bus_name_filter_indices.borrow().len() != 0
+            !self.message_tag_filter_indices.borrow().is_empty()
+                || !self.bus_name_filter_indices.borrow().is_empty()
+                || self.message_subset.borrow().is_some()
+        }
+
+        fn set_shows_bus_driver(&self, shows_bus_driver: bool) {
+            if shows_bus_driver == self.shows_bus_driver.get() {
+                return;
+            }
+
+            self.shows_bus_driver.set(shows_bus_driver);
+            self.bus_driver_filter
+                .get()
+                .expect("filter was not set on constructed")
+                .changed(if shows_bus_driver {
+                    gtk::FilterChange::LessStrict
+                } else {
+                    gtk::FilterChange::MoreStrict
+                });
+
+            self.obj().notify_shows_bus_driver();
         }
     }
 }
 
+/// A message of the inner model, along with the names it uses
+#[derive(Debug)]
+struct IndexEntry {
+    receive_index: ReceiveIndex,
+    bus_type: BusType,
+    sender: Option<BusName<'static>>,
+    destination: Option<BusName<'static>>,
+}
+
+impl IndexEntry {
+    fn names(&self) -> impl Iterator<Item = &BusName<'static>> {
+        self.sender.iter().chain(self.destination.iter())
+    }
+}
+
 glib::wrapper! {
     pub struct FilteredMessageModel(ObjectSubclass<imp::FilteredMessageModel>)
         @implements gio::ListModel;
 }
 
-impl FilteredMessageModel {
-    /// This also resets the filters
+/// Number of messages using each name of each bus
+type UsedBusNames = HashMap<(BusType, BusName<'static>), usize>;
+
+/// Counts one more use of `name` on `bus_type`, by the name of its item, and
+/// returns the owned name to keep
+fn use_name(
+    used_bus_names: &mut UsedBusNames,
+    bus_type: BusType,
+    name: BusName<'_>,
+) -> BusName<'static> {
+    match used_bus_names.entry((bus_type, item_name(&name).into_owned())) {
+        Entry::Occupied(mut entry) => {
+            *entry.get_mut() += 1;
+            entry.key().1.clone()
+        }
+        Entry::Vacant(entry) => {
+            let name = entry.key().1.clone();
+            entry.insert(1);
+            name
+        }
+    }
+}
 
+/// Counts one less use of `name` on `bus_type`, forgetting it once it is not
+/// used anymore
+fn release_name(used_bus_names: &mut UsedBusNames, bus_type: BusType, name: &BusName<'static>) {
+    let key = (bus_type, name.clone());
+    let Some(count) = used_bus_names.get_mut(&key) else {
+        return;
+    };
+    *count -= 1;
+    if *count == 0 {
+        used_bus_names.remove(&key);
+    }
+}
+
+impl FilteredMessageModel {
     /// Sets the message list for the filtered message model, clearing existing filters.
     ///
     /// # Arguments
@@ -192,12 +289,16 @@ impl FilteredMessageModel {
     }
 
     pub fn get_index_of(&self, message: &Message) -> Option<usize> {
-        self.imp().inner_index.borrow().get_index_of(message)
+        // Filtering keeps the order of the message list, which is sorted by
+        // receive index
+        self.imp()
+            .inner_index
+            .borrow()
+            .binary_search_by_key(&message.receive_index(), |entry| entry.receive_index)
+            .ok()
     }
 
-    pub fn iter(
-        &self,
-    ) -> impl Iterator<Item = Message> + ExactSizeIterator + DoubleEndedIterator + '_ {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Message> + DoubleEndedIterator + '_ {
         ListModelExtManual::iter(self).map(|item| item.unwrap())
     }
 
@@ -256,24 +357,29 @@ impl FilteredMessageModel {
     }
 
     /// Adds filter that filters out messages relevant to `BusNameItem` with
-    /// `bus_name` equals given `name`
+    /// `bus_name` equals given `name` on `bus_type`
     ///
     /// Returns an error if self has no `message_list`
-    pub fn add_bus_name_filter(&self, name: &BusName<'_>) -> Result<()> {
+    pub fn add_bus_name_filter(&self, bus_type: BusType, name: &BusName<'_>) -> Result<()> {
         // Retrieve the `BusNameItem` corresponding to the provided `name` from the message list.
         // If the message list is not provided, return an error.
         let bus_name_item = self
             .message_list()
             .context("Message list was not sent")?
             .bus_names()
-            .get(name)
+            .get(bus_type, name)
             .unwrap();
 
         // Create a custom filter that filters out messages based on the provided `name`.
         let custom_filter = gtk::CustomFilter::new(move |message| {
             let message = message.downcast_ref::<Message>().unwrap();
+            if message.bus_type() != bus_type {
+                return true;
+            }
             let name = bus_name_item.name();
-            !message.sender().is_some_and(|sender| *name == sender)
+            message
+                .sender()
+                .is_none_or(|sender| *name != item_name(&BusName::from(sender)))
                 && !message.destination().is_some_and(|destination| {
                     // If the destination is a Unique name, it is not filtered out.
                     *name == destination
@@ -298,7 +404,7 @@ impl FilteredMessageModel {
             .imp()
             .bus_name_filter_indices
             .borrow_mut()
-            .insert(name.to_owned(), index);
+            .insert((bus_type, name.to_owned()), index);
 
         // Ensure that the previous value for the index was not already set.
         debug_assert!(prev_value.is_none());
@@ -318,12 +424,16 @@ impl FilteredMessageModel {
     }
 
     /// Removes the filter that is relevant to `BusNameItem` with `bus_name`
-    /// equals given `name`
+    /// equals given `name` on `bus_type`
     ///
     /// Returns true if the filter existed and removed
-    pub fn remove_bus_name_filter(&self, name: &BusName<'static>) -> bool {
+    pub fn remove_bus_name_filter(&self, bus_type: BusType, name: &BusName<'static>) -> bool {
         // Try to remove the filter for the given `BusName`
-        let ret = if let Some(index) = self.imp().bus_name_filter_indices.borrow_mut().remove(name)
+        let ret = if let Some(index) = self
+            .imp()
+            .bus_name_filter_indices
+            .borrow_mut()
+            .remove(&(bus_type, name.clone()))
         {
             // If the filter was found and removed, also remove it from the inner filter
             self.inner_filter().remove(index);
@@ -337,9 +447,47 @@ impl FilteredMessageModel {
         ret
     }
 
+    /// Shows only the given messages, along with their associated calls
+    /// and returns, on top of the other filters
+    pub fn set_message_subset_filter(&self, messages: impl IntoIterator<Item = Message>) {
+        let imp = self.imp();
+
+        let message_subset = messages
+            .into_iter()
+            .flat_map(|message| {
+                let associated_message = message.associated_message();
+                std::iter::once(message)
+                    .chain(associated_message)
+                    .map(|message| message.receive_index())
+            })
+            .collect::<HashSet<_>>();
+        imp.message_subset.replace(Some(message_subset));
+        self.message_subset_filter()
+            .changed(gtk::FilterChange::Different);
+
+        self.notify_has_filter();
+    }
+
+    /// Removes the filter set with `set_message_subset_filter`
+    ///
+    /// Returns true if the filter existed and removed
+    pub fn clear_message_subset_filter(&self) -> bool {
+        let ret = self.imp().message_subset.take().is_some();
+        if ret {
+            self.message_subset_filter()
+                .changed(gtk::FilterChange::LessStrict);
+        }
+
+        self.notify_has_filter();
+
+        ret
+    }
+
     pub fn clear_filters(&self) {
         let imp = self.imp();
 
+        self.clear_message_subset_filter();
+
         // Take ownership of the message tag and bus name filter indices
         let message_tag_filter_indices = imp.message_tag_filter_indices.take();
         let bus_name_filter_indices = imp.bus_name_filter_indices.take();
@@ -362,6 +510,13 @@ impl FilteredMessageModel {
         self.notify_has_filter();
     }
 
+    fn message_subset_filter(&self) -> &gtk::CustomFilter {
+        self.imp()
+            .message_subset_filter
+            .get()
+            .expect("filter was not set on constructed")
+    }
+
     fn inner_filter(&self) -> gtk::EveryFilter {
         self.imp()
             .inner
@@ -373,93 +528,67 @@ impl FilteredMessageModel {
 
     fn bus_names_filter_func(&self, bus_name_item: &BusNameItem) -> bool {
         let used_names = self.imp().used_bus_names.borrow();
+        let bus_type = bus_name_item.bus_type();
 
-        used_names.contains(bus_name_item.name())
+        used_names.contains_key(&(bus_type, bus_name_item.name().clone()))
             || bus_name_item
                 .wk_names(LookupPoint::All)
-                .iter()
-                .any(|wk_name| used_names.contains(&BusName::from(wk_name.as_ref())))
+                .into_iter()
+                .any(|wk_name| used_names.contains_key(&(bus_type, BusName::from(wk_name))))
     }
 
+    /// Updates the inner index and the used names in place, so that appending
+    /// or evicting messages does not depend on the number of messages
     fn update_inner_index(&self, position: u32, removed: u32, added: u32) {
         let imp = self.imp();
 
         let mut inner_index = imp.inner_index.borrow_mut();
+        let mut used_bus_names = imp.used_bus_names.borrow_mut();
 
-        // FIXME I wish there is IndexMap.splice
-        let end_part = inner_index.split_off(position as usize);
-        let new_items =
-            (0..added).map(|i| imp.inner.item(position + i).unwrap().downcast().unwrap());
-        inner_index.extend(new_items.chain(end_part.into_iter().skip(removed as usize)));
-
-        debug_assert_eq!(inner_index.len(), imp.inner.n_items() as usize);
-        debug_assert!(self
-            .iter()
-            .zip(inner_index.iter())
-            .all(|(inner_item, inner_index_item)| inner_item == *inner_index_item));
-    }
-
-    fn update_used_names(&self, _position: u32, _removed: u32, _added: u32) {
-        let used_sender_names = self
-            .iter()
-            .filter_map(|message| message.sender().map(|s| BusName::from(s.to_owned())));
-        let used_destination_names = self
-            .iter()
-            .filter_map(|message| message.destination().map(|d| d.to_owned()));
-        self.imp()
-            .used_bus_names
-            .replace(used_sender_names.chain(used_destination_names).collect());
-    }
-
-    /*
-    Chnages Required::>
-    ==> https://docs.gtk.org/gtk4/method.Filter.changed.html
-    ==> Need to emit Signals.
-    ==> Something like `self.emit_by_name::<()>("<signal name>");`
-    */
-
-    pub fn dbus_message_signal_exists_in_dbus_proxy(tag: MessageTag) -> bool {
-        // let active_tags: Vec<MessageTag> = Vec::new();
-        // let last_elements_tag = last_tag;
-        self.dbus_message_signal_exists_in_dbus(tag)
-    }
+        let position = position as usize;
+        for entry in inner_index.drain(position..position + removed as usize) {
+            for name in entry.names() {
+                release_name(&mut used_bus_names, entry.bus_type, name);
+            }
+        }
 
-    pub fn dbus_message_signal_exists_in_dbus(&self, check_tag: MessageTag) -> bool {
-        let message_list = self.message_list().unwrap();
-        let vector = *message_list.imp().inner().borrow();
-        // Making a Vector of <MessageTag> out off Vector<Message>
-        let message_tags: Vec<MessageTag> = vector
-            .iter()
-            .map(|message| {
-                // Extract the MessageTag from each Message
-                message.message_tag()
+        let new_entries = (0..added)
+            .map(|i| {
+                let message = imp
+                    .inner
+                    .item((position as u32) + i)
+                    .and_downcast::<Message>()
+                    .unwrap();
+                let bus_type = message.bus_type();
+                IndexEntry {
+                    receive_index: message.receive_index(),
+                    bus_type,
+                    sender: message.sender().map(|sender| {
+                        use_name(&mut used_bus_names, bus_type, BusName::from(sender))
+                    }),
+                    destination: message
+                        .destination()
+                        .map(|destination| use_name(&mut used_bus_names, bus_type, destination)),
+                }
             })
-            .collect();
-
-        let last_elements_tag = check_tag;
-
-        if let Some(found_tag) = message_tags.iter().find(|&tag| *tag == last_elements_tag) {
-            return true;
+            .collect::<Vec<_>>();
+        if position == inner_index.len() {
+            inner_index.extend(new_entries);
+        } else if !new_entries.is_empty() {
+            let end_part = inner_index.split_off(position);
+            inner_index.extend(new_entries);
+            inner_index.extend(end_part);
         }
-        false
+
+        debug_assert_eq!(inner_index.len(), imp.inner.n_items() as usize);
     }
 
-    // pub fn dbus_message_signal_exists_in_dbus2(&self) -> bool {
-    //     let message_list = self.message_list().unwrap();
-    //     let vector = *message_list.imp().inner().borrow();
-    //     let last_elements_tag = vector.last().unwrap().message_tag();
-
-    //     let message_tags = self
-    //         .imp()
-    //         .message_tag_filter_indices
-    //         .borrow()
-    //         .keys()
-    //         .map(|tag| tag.clone())
-    //         .collect::<Vec<MessageTag>>();
-
-    //     if let Some(found_tag) = message_tags.iter().find(|&tag| *tag == last_elements_tag) {
-    //         return true;
-    //     }
-    //     false
-    // }
+    /// Whether any message of the unfiltered message list has `tag`
+    pub fn dbus_message_signal_exists_in_dbus(&self, tag: MessageTag) -> bool {
+        self.message_list().is_some_and(|message_list| {
+            message_list
+                .iter::<Message>()
+                .any(|message| message.is_ok_and(|message| message.message_tag() == tag))
+        })
+    }
 }
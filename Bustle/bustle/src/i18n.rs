@@ -1,7 +1,7 @@
 // Copied from Fractal GPLv3
 // See https://gitlab.gnome.org/GNOME/fractal/-/blob/c0bc4078bb2cdd511c89fdf41a51275db90bb7ab/src/i18n.rs
 
-use gettextrs::gettext;
+use gettextrs::{gettext, ngettext};
 
 /// Like `gettext`, but replaces named variables using the given key-value
 /// tuples.
@@ -13,6 +13,16 @@ pub fn gettext_f(msgid: &str, args: &[(&str, &str)]) -> String {
     freplace(s, args)
 }
 
+/// Like `ngettext`, but replaces named variables using the given key-value
+/// tuples.
+///
+/// The expected format to replace is `{name}`, where `name` is the first string
+/// in a key-value tuple.
+pub fn ngettext_f(msgid: &str, msgid_plural: &str, n: u32, args: &[(&str, &str)]) -> String {
+    let s = ngettext(msgid, msgid_plural, n);
+    freplace(s, args)
+}
+
 /// Replace variables in the given string using the given key-value tuples.
 ///
 /// The expected format to replace is `{name}`, where `name` is the first string
@@ -0,0 +1,544 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use anyhow::{Context, Result};
+use gtk::{gio, prelude::*};
+use zbus::{names::BusName, zvariant::OwnedValue};
+
+use crate::{
+    bus_name_list::BusNameList, bus_type::BusType, message::Message, message_list::MessageList,
+    message_type::MessageType,
+};
+
+const BUS_DRIVER_NAME: &str = "org.freedesktop.DBus";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Interfaces that every object is expected to implement, so there is no
+/// point in exporting them
+const STANDARD_INTERFACES: &[&str] = &[
+    "org.freedesktop.DBus.Introspectable",
+    "org.freedesktop.DBus.Peer",
+    PROPERTIES_INTERFACE,
+];
+
+/// A service name on the bus it was seen on
+type Service = (BusType, String);
+
+#[derive(Debug, Default)]
+struct MethodInfo {
+    in_signature: String,
+    /// `None` if no successful reply was observed
+    out_signature: Option<String>,
+    no_reply: bool,
+}
+
+#[derive(Debug, Default)]
+struct PropertyInfo {
+    /// `None` if the property was only seen invalidated
+    signature: Option<String>,
+    read: bool,
+    write: bool,
+}
+
+#[derive(Debug, Default)]
+struct InterfaceInfo {
+    paths: BTreeSet<String>,
+    methods: BTreeMap<String, MethodInfo>,
+    signals: BTreeMap<String, String>,
+    properties: BTreeMap<String, PropertyInfo>,
+}
+
+/// Introspection data inferred from the messages that were actually
+/// exchanged, for services that don't publish it
+#[derive(Debug, Default)]
+pub struct Introspection {
+    /// Interfaces keyed by service, and then by interface name
+    services: BTreeMap<Service, BTreeMap<String, InterfaceInfo>>,
+}
+
+impl Introspection {
+    pub fn from_message_list(message_list: &MessageList) -> Result<Self> {
+        let bus_names = message_list.bus_names();
+
+        let mut this = Self::default();
+        for message in message_list.iter::<Message>() {
+            let message = message.context("Message list was mutated while inferring")?;
+
+            match message.message_type() {
+                MessageType::MethodCall => this.handle_call(&message, bus_names),
+                MessageType::Signal => this.handle_signal(&message, bus_names),
+                MessageType::MethodReturn | MessageType::Error => {}
+            }
+        }
+
+        Ok(this)
+    }
+
+    /// Returns the services with at least one inferred interface, along with
+    /// their bus
+    pub fn services(&self) -> impl Iterator<Item = (BusType, &str)> {
+        self.services
+            .keys()
+            .map(|(bus_type, name)| (*bus_type, name.as_str()))
+    }
+
+    /// Saves one `<service name>.xml` file per service in `dir`, or
+    /// `<service name>.<bus>.xml` if services of several buses were seen,
+    /// returning the number of files written
+    pub async fn save_to_dir(&self, dir: &gio::File) -> Result<usize> {
+        let has_multiple_buses = self
+            .services()
+            .any(|(bus_type, _)| self.services().any(|(other, _)| other != bus_type));
+
+        for (bus_type, service_name) in self.services() {
+            let xml = self
+                .to_xml(bus_type, service_name)
+                .context("Unknown service")?;
+            let file = if has_multiple_buses {
+                dir.child(format!(
+                    "{}.{}.xml",
+                    service_name,
+                    bus_type.interface_name()
+                ))
+            } else {
+                dir.child(format!("{}.xml", service_name))
+            };
+            file.replace_contents_future(
+                xml,
+                None,
+                false,
+                gio::FileCreateFlags::REPLACE_DESTINATION,
+            )
+            .await
+            .map_err(|e| e.1)?;
+        }
+
+        Ok(self.services.len())
+    }
+
+    /// Returns the introspection XML of the given service on `bus_type`,
+    /// with the interfaces seen on all of its objects merged in the root
+    /// node, as expected by `zbus-xmlgen`, and one child node per object
+    /// listing the interfaces seen on it.
+    pub fn to_xml(&self, bus_type: BusType, service_name: &str) -> Option<String> {
+        let interfaces = self.services.get(&(bus_type, service_name.to_string()))?;
+
+        let mut xml = String::new();
+        writeln!(
+            xml,
+            r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN""#
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            r#" "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">"#
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            "<!-- Inferred by Bustle from the traffic of {} on the {} bus -->",
+            escape(service_name),
+            bus_type.interface_name()
+        )
+        .unwrap();
+        writeln!(xml, "<node>").unwrap();
+
+        for (interface_name, interface) in interfaces {
+            writeln!(xml, r#"  <interface name="{}">"#, escape(interface_name)).unwrap();
+
+            for (name, method) in &interface.methods {
+                writeln!(xml, r#"    <method name="{}">"#, escape(name)).unwrap();
+                for ty in complete_types(&method.in_signature) {
+                    writeln!(xml, r#"      <arg type="{}" direction="in"/>"#, escape(ty)).unwrap();
+                }
+                match &method.out_signature {
+                    Some(out_signature) => {
+                        for ty in complete_types(out_signature) {
+                            writeln!(xml, r#"      <arg type="{}" direction="out"/>"#, escape(ty))
+                                .unwrap();
+                        }
+                    }
+                    None if method.no_reply => {
+                        writeln!(
+                            xml,
+                            r#"      <annotation name="org.freedesktop.DBus.Method.NoReply" value="true"/>"#
+                        )
+                        .unwrap();
+                    }
+                    None => {
+                        writeln!(xml, "      <!-- No successful reply was observed -->").unwrap();
+                    }
+                }
+                writeln!(xml, "    </method>").unwrap();
+            }
+
+            for (name, signature) in &interface.signals {
+                writeln!(xml, r#"    <signal name="{}">"#, escape(name)).unwrap();
+                for ty in complete_types(signature) {
+                    writeln!(xml, r#"      <arg type="{}"/>"#, escape(ty)).unwrap();
+                }
+                writeln!(xml, "    </signal>").unwrap();
+            }
+
+            for (name, property) in &interface.properties {
+                let Some(signature) = &property.signature else {
+                    writeln!(
+                        xml,
+                        "    <!-- Property {} was invalidated but its type is unknown -->",
+                        escape(name)
+                    )
+                    .unwrap();
+                    continue;
+                };
+                let access = match (property.read, property.write) {
+                    (_, false) => "read",
+                    (false, true) => "write",
+                    (true, true) => "readwrite",
+                };
+                writeln!(
+                    xml,
+                    r#"    <property name="{}" type="{}" access="{}"/>"#,
+                    escape(name),
+                    escape(signature),
+                    access
+                )
+                .unwrap();
+            }
+
+            writeln!(xml, "  </interface>").unwrap();
+        }
+
+        let mut paths = BTreeMap::<&str, Vec<&str>>::new();
+        for (interface_name, interface) in interfaces {
+            for path in &interface.paths {
+                paths.entry(path).or_default().push(interface_name);
+            }
+        }
+        for (path, interface_names) in paths {
+            writeln!(xml, r#"  <node name="{}">"#, escape(path)).unwrap();
+            for interface_name in interface_names {
+                writeln!(xml, r#"    <interface name="{}"/>"#, escape(interface_name)).unwrap();
+            }
+            writeln!(xml, "  </node>").unwrap();
+        }
+
+        writeln!(xml, "</node>").unwrap();
+
+        Some(xml)
+    }
+
+    fn interface_mut(
+        &mut self,
+        service: Service,
+        path: &str,
+        interface: &str,
+    ) -> &mut InterfaceInfo {
+        let info = self
+            .services
+            .entry(service)
+            .or_default()
+            .entry(interface.to_string())
+            .or_default();
+        info.paths.insert(path.to_string());
+        info
+    }
+
+    fn property_mut(
+        &mut self,
+        service: Service,
+        path: &str,
+        interface: &str,
+        property: &str,
+    ) -> &mut PropertyInfo {
+        self.interface_mut(service, path, interface)
+            .properties
+            .entry(property.to_string())
+            .or_default()
+    }
+
+    fn handle_call(&mut self, message: &Message, bus_names: &BusNameList) {
+        let header = message.header();
+
+        let Some(destination) = header.destination() else {
+            return;
+        };
+        let service = (
+            message.bus_type(),
+            service_name(bus_names, destination, message),
+        );
+        if service.1 == BUS_DRIVER_NAME {
+            return;
+        }
+
+        // Calls without an interface can't be attributed to one
+        let (Some(path), Some(interface), Some(member)) =
+            (header.path(), header.interface(), header.member())
+        else {
+            return;
+        };
+
+        if interface.as_str() == PROPERTIES_INTERFACE {
+            if let Err(err) = self.handle_properties_call(
+                message,
+                service,
+                path.as_str(),
+                member.as_str(),
+            ) {
+                tracing::debug!(%message, "Failed to handle properties call: {:?}", err);
+            }
+            return;
+        }
+
+        if STANDARD_INTERFACES.contains(&interface.as_str()) {
+            return;
+        }
+
+        let in_signature = signature_of(message);
+        let out_signature = message
+            .associated_message()
+            .filter(|reply| reply.message_type() == MessageType::MethodReturn)
+            .map(|reply| signature_of(&reply));
+        let no_reply = header
+            .primary()
+            .flags()
+            .contains(zbus::MessageFlags::NoReplyExpected);
+
+        let method = self
+            .interface_mut(service, path.as_str(), interface.as_str())
+            .methods
+            .entry(member.to_string())
+            .or_insert_with(|| MethodInfo {
+                in_signature,
+                ..Default::default()
+            });
+        if method.out_signature.is_none() {
+            method.out_signature = out_signature;
+        }
+        method.no_reply |= no_reply;
+    }
+
+    fn handle_properties_call(
+        &mut self,
+        message: &Message,
+        service: Service,
+        path: &str,
+        member: &str,
+    ) -> Result<()> {
+        let reply = message
+            .associated_message()
+            .filter(|reply| reply.message_type() == MessageType::MethodReturn);
+
+        match member {
+            "Get" => {
+                let (interface, property) = message.body().deserialize::<(String, String)>()?;
+                let signature = reply
+                    .map(|reply| reply.body().deserialize::<OwnedValue>())
+                    .transpose()?
+                    .map(|value| value.value_signature().to_string());
+
+                let info = self.property_mut(service, path, &interface, &property);
+                info.read = true;
+                if info.signature.is_none() {
+                    info.signature = signature;
+                }
+            }
+            "Set" => {
+                let (interface, property, value) = message
+                    .body()
+                    .deserialize::<(String, String, OwnedValue)>()?;
+
+                let info = self.property_mut(service, path, &interface, &property);
+                info.write = true;
+                if info.signature.is_none() {
+                    info.signature = Some(value.value_signature().to_string());
+                }
+            }
+            "GetAll" => {
+                let (interface,) = message.body().deserialize::<(String,)>()?;
+                let Some(reply) = reply else {
+                    return Ok(());
+                };
+                let properties = reply
+                    .body()
+                    .deserialize::<HashMap<String, OwnedValue>>()?;
+
+                self.insert_read_properties(service, path, &interface, properties);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, message: &Message, bus_names: &BusNameList) {
+        let header = message.header();
+
+        let Some(sender) = header.sender() else {
+            return;
+        };
+        let service = (
+            message.bus_type(),
+            service_name(bus_names, &BusName::from(sender.clone()), message),
+        );
+        if service.1 == BUS_DRIVER_NAME {
+            return;
+        }
+
+        let (Some(path), Some(interface), Some(member)) =
+            (header.path(), header.interface(), header.member())
+        else {
+            return;
+        };
+
+        if interface.as_str() == PROPERTIES_INTERFACE && member.as_str() == "PropertiesChanged" {
+            match message
+                .body()
+                .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+            {
+                Ok((interface, changed, invalidated)) => {
+                    for property in invalidated {
+                        self.property_mut(service.clone(), path.as_str(), &interface, &property)
+                            .read = true;
+                    }
+                    self.insert_read_properties(service, path.as_str(), &interface, changed);
+                }
+                Err(err) => {
+                    tracing::debug!(%message, "Failed to handle PropertiesChanged: {:?}", err);
+                }
+            }
+            return;
+        }
+
+        if STANDARD_INTERFACES.contains(&interface.as_str()) {
+            return;
+        }
+
+        self.interface_mut(service, path.as_str(), interface.as_str())
+            .signals
+            .entry(member.to_string())
+            .or_insert_with(|| signature_of(message));
+    }
+
+    fn insert_read_properties(
+        &mut self,
+        service: Service,
+        path: &str,
+        interface: &str,
+        properties: HashMap<String, OwnedValue>,
+    ) {
+        for (property, value) in properties {
+            let info = self.property_mut(service.clone(), path, interface, &property);
+            info.read = true;
+            if info.signature.is_none() {
+                info.signature = Some(value.value_signature().to_string());
+            }
+        }
+    }
+}
+
+/// Returns the first well-known name owned by `name` when `message` was
+/// received, or `name` itself if it had none
+fn service_name(bus_names: &BusNameList, name: &BusName<'_>, message: &Message) -> String {
+    match name {
+        BusName::WellKnown(wk_name) => wk_name.to_string(),
+        BusName::Unique(_) => bus_names
+            .get(message.bus_type(), name)
+            .and_then(|bus_name_item| {
+                bus_name_item
+                    .wk_names(message.receive_index().into())
+                    .first()
+                    .map(|wk_name| wk_name.to_string())
+            })
+            .unwrap_or_else(|| name.to_string()),
+    }
+}
+
+fn signature_of(message: &Message) -> String {
+    message
+        .header()
+        .signature()
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Splits `signature` into its complete types, e.g. `sa{sv}` into `s` and
+/// `a{sv}`
+fn complete_types(signature: &str) -> Vec<&str> {
+    let mut types = Vec::new();
+    let mut start = 0;
+    let mut depth = 0_usize;
+
+    for (i, c) in signature.char_indices() {
+        match c {
+            'a' => continue,
+            '(' | '{' => {
+                depth += 1;
+                continue;
+            }
+            ')' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        if depth == 0 {
+            types.push(&signature[start..=i]);
+            start = i + 1;
+        }
+    }
+
+    types
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_types_split() {
+        assert!(complete_types("").is_empty());
+        assert_eq!(complete_types("su"), ["s", "u"]);
+        assert_eq!(complete_types("sa{sv}as"), ["s", "a{sv}", "as"]);
+        assert_eq!(complete_types("a(sa(ii))v"), ["a(sa(ii))", "v"]);
+    }
+
+    #[test]
+    fn to_xml_nodes() {
+        let mut introspection = Introspection::default();
+        for (path, interface) in [
+            ("/org/example/Foo", "org.example.A"),
+            ("/org/example/Foo", "org.example.B"),
+            ("/org/example/Bar", "org.example.B"),
+        ] {
+            let service = (BusType::Session, "org.example.Foo".to_owned());
+            introspection.interface_mut(service, path, interface);
+        }
+
+        let xml = introspection
+            .to_xml(BusType::Session, "org.example.Foo")
+            .unwrap();
+        assert!(xml.contains(concat!(
+            "  <node name=\"/org/example/Bar\">\n",
+            "    <interface name=\"org.example.B\"/>\n",
+            "  </node>\n",
+            "  <node name=\"/org/example/Foo\">\n",
+            "    <interface name=\"org.example.A\"/>\n",
+            "    <interface name=\"org.example.B\"/>\n",
+            "  </node>\n",
+            "</node>\n",
+        )));
+        assert!(introspection
+            .to_xml(BusType::Session, "org.example.Bar")
+            .is_none());
+        assert!(introspection
+            .to_xml(BusType::System, "org.example.Foo")
+            .is_none());
+    }
+}
@@ -4,23 +4,40 @@ mod address_dialog;
 mod application;
 #[rustfmt::skip]
 mod config;
+mod bus_discovery;
 mod bus_name_item;
 mod bus_name_list;
+mod bus_type;
 mod color_widget;
 mod colors;
+mod command_dialog;
 mod details_view;
 mod diagram;
+mod event_merger;
 mod filter_pane;
 mod filtered_bus_name_model;
 mod filtered_message_model;
 mod i18n;
+mod introspection;
 mod message;
 mod message_list;
+mod message_match;
+mod message_store;
 mod message_tag;
 mod message_type;
+mod mock_service;
 mod monitor;
+mod proxy;
+mod replay;
+mod retention_dialog;
 mod statistics;
+mod stop_conditions;
+mod stop_dialog;
+#[cfg(test)]
+mod test_util;
 mod timestamp;
+mod trigger;
+mod trigger_dialog;
 mod window;
 
 use gettextrs::LocaleCategory;
@@ -1,14 +1,42 @@
+use std::time::Duration;
+
 use gettextrs::gettext;
 use gtk::{glib, prelude::*, subclass::prelude::*};
-use zbus::names::{BusName, UniqueName, WellKnownName};
+use zbus::{
+    names::{BusName, UniqueName, WellKnownName},
+    zvariant,
+};
 
 use crate::{
+    bus_name_item::BusNameItem, bus_type::BusType, message_list::MessageList,
     message_tag::MessageTag, message_type::MessageType, monitor::Event, timestamp::Timestamp,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Time after which callers give up on a reply by default, as used by
+/// libdbus, GDBus and zbus
+pub const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(25);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ReceiveIndex(u32);
 
+impl ReceiveIndex {
+    pub fn new(raw_receive_index: u32) -> Self {
+        Self(raw_receive_index)
+    }
+
+    pub fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+
+    /// Returns how many messages were received between `other` and self, or
+    /// None if self was received before `other`
+    pub fn distance_from(self, other: Self) -> Option<usize> {
+        self.0
+            .checked_sub(other.0)
+            .map(|distance| distance as usize)
+    }
+}
+
 mod imp {
     use std::cell::{Cell, OnceCell};
 
@@ -23,11 +51,14 @@ mod imp {
         pub(super) timestamp: OnceCell<Timestamp>,
         #[property(get, set, builder(MessageTag::default()))]
         pub(super) message_tag: Cell<MessageTag>,
+        #[property(get, set, builder(BusType::default()))]
+        pub(super) bus_type: Cell<BusType>,
 
         pub(super) inner: OnceCell<zbus::Message>,
         pub(super) receive_index: OnceCell<ReceiveIndex>,
 
-        pub(super) associated_message: OnceCell<glib::WeakRef<super::Message>>,
+        /// List that stores this message and its links to other messages
+        pub(super) message_list: glib::WeakRef<MessageList>,
     }
 
     #[glib::object_subclass]
@@ -88,9 +119,7 @@ impl Message {
     /// Return the time it took to receive a response or None if it is a signal
     /// or no message is associated to the current one
     pub fn response_time(&self) -> Option<Timestamp> {
-        let Some(associated_message) = self.associated_message() else {
-            return None;
-        };
+        let associated_message = self.associated_message()?;
         match self.message_type() {
             MessageType::Signal => None,
             MessageType::MethodReturn | MessageType::Error => {
@@ -100,10 +129,27 @@ impl Message {
         }
     }
 
-    pub fn set_receive_index(&self, raw_receive_index: u32) {
+    /// Whether this is a method call whose sender waits for a reply
+    pub fn expects_reply(&self) -> bool {
+        self.message_type().is_method_call()
+            && !self
+                .header()
+                .primary()
+                .flags()
+                .contains(zbus::MessageFlags::NoReplyExpected)
+    }
+
+    /// Whether this is a call or return whose reply arrived after the caller
+    /// most likely timed out already
+    pub fn is_late_reply(&self) -> bool {
+        self.response_time()
+            .is_some_and(|response_time| Duration::from(response_time) > DEFAULT_REPLY_TIMEOUT)
+    }
+
+    pub fn set_receive_index(&self, receive_index: ReceiveIndex) {
         self.imp()
             .receive_index
-            .set(ReceiveIndex(raw_receive_index))
+            .set(receive_index)
             .expect("receive index must only be set once");
     }
 
@@ -111,18 +157,31 @@ impl Message {
         *self.imp().receive_index.get().unwrap()
     }
 
-    pub fn set_associated_message(&self, message: &Self) {
-        self.imp()
-            .associated_message
-            .set(message.downgrade())
-            .expect("associated message must not be set twice");
+    /// This must only be called on `MessageList`
+    pub fn set_message_list(&self, message_list: &MessageList) {
+        self.imp().message_list.set(Some(message_list));
+    }
+
+    /// Returns the list that stores this message, if it still exists
+    pub fn message_list(&self) -> Option<MessageList> {
+        self.imp().message_list.upgrade()
     }
 
+    /// Returns the item of `name` as it was when this message was received,
+    /// if it is still in the message list
+    pub fn bus_name_item(&self, name: &BusName<'_>) -> Option<BusNameItem> {
+        self.message_list()?
+            .bus_names()
+            .lookup(self.bus_type(), name, self.receive_index().into())
+    }
+
+    /// Returns the return of this call or the call of this return, if it is
+    /// still in the message list
     pub fn associated_message(&self) -> Option<Self> {
         self.imp()
-            .associated_message
-            .get()
-            .and_then(|m| m.upgrade())
+            .message_list
+            .upgrade()?
+            .associated_message(self.receive_index())
     }
 
     pub fn sender_display(&self) -> String {
@@ -200,6 +259,30 @@ impl Message {
             .join(" | ")
     }
 
+    /// Returns the error name if this is an error message
+    pub fn error_name(&self) -> Option<String> {
+        self.header().error_name().map(|name| name.to_string())
+    }
+
+    /// Returns the human-readable text of an error message, i.e., its first
+    /// argument if it is a string
+    pub fn error_text(&self) -> Option<String> {
+        if !self.message_type().is_error()
+            || !self
+                .header()
+                .signature()
+                .is_some_and(|signature| signature.as_str().starts_with('s'))
+        {
+            return None;
+        }
+
+        let body = self.body().deserialize::<zvariant::OwnedStructure>().ok()?;
+        match body.0.fields().first()? {
+            zvariant::Value::Str(text) => Some(text.to_string()),
+            _ => None,
+        }
+    }
+
     pub fn member_markup(&self, render_error: bool) -> String {
         let interface = self.interface_display();
         let member = self.member_display();
@@ -257,6 +340,7 @@ impl Message {
         debug_assert!(self.message_type().is_method_return());
 
         other.message_type().is_method_call()
+            && self.bus_type() == other.bus_type()
             && self.header().reply_serial() == Some(other.header().primary().serial_num())
             && self.destination() == other.sender().map(From::from)
     }
@@ -2,20 +2,55 @@ use std::{
     borrow::Cow,
     collections::{hash_map::Entry, HashMap},
     fs::File,
-    io::{BufWriter, Write},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
     path::Path,
+    time::Duration,
 };
 
 use anyhow::{bail, Context, Ok, Result};
 use gtk::{gio, glib, prelude::*, subclass::prelude::*};
-use pcap_file::pcap::{PcapHeader, PcapPacket, PcapReader, PcapWriter};
+use pcap_file::{
+    pcap::{PcapHeader, PcapPacket, PcapReader, PcapWriter},
+    pcapng::{
+        blocks::{
+            enhanced_packet::EnhancedPacketBlock,
+            interface_description::{InterfaceDescriptionBlock, InterfaceDescriptionOption},
+        },
+        Block, PcapNgReader, PcapNgWriter,
+    },
+};
+use zbus::names::{BusName, UniqueName};
 
 use crate::{
-    bus_name_list::BusNameList, message::Message, message_tag::MessageTag, monitor::Event, RUNTIME,
+    bus_name_list::BusNameList,
+    bus_type::BusType,
+    message::{Message, ReceiveIndex, DEFAULT_REPLY_TIMEOUT},
+    message_store::MessageStore,
+    message_tag::MessageTag,
+    monitor::Event,
+    timestamp::Timestamp,
+    RUNTIME,
 };
 
+/// The block type of the section header that starts a PCAPNG file, which
+/// reads the same in both byte orders
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+/// How many of the most recent messages a `MessageList` keeps
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    /// Keep all messages
+    #[default]
+    Unlimited,
+    /// Keep only the given number of messages
+    Count(usize),
+    /// Keep only the messages received within the given duration before the
+    /// most recent one
+    Duration(Duration),
+}
+
 mod imp {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     // Import the items from the parent module (`super`), which in this case is the outer module
     // where `imp` is defined.
@@ -24,10 +59,22 @@ mod imp {
     // Define a new struct named `MessageList`.
     #[derive(Debug, Default)]
     pub struct MessageList {
-        // Define a field named `inner` of type `RefCell<Vec<Message>>`. `RefCell` allows interior
-        // mutability, enabling shared mutable access to the vector of `Message` instances.
-        pub(super) inner: RefCell<Vec<Message>>,
+        /// Raw messages, `Message` objects are only created on demand
+        pub(super) store: RefCell<MessageStore>,
+        /// Created messages, so the same object is returned for a message as
+        /// long as it is alive
+        pub(super) message_cache: RefCell<HashMap<ReceiveIndex, glib::WeakRef<Message>>>,
+        /// Number of live entries in `message_cache` after it was last pruned
+        pub(super) n_cached_messages: Cell<usize>,
         pub(super) bus_names: BusNameList,
+        /// Calls that did not get a return yet
+        pub(super) pending_calls: RefCell<HashMap<CallKey, ReceiveIndex>>,
+        /// Receive index given to the next pushed message. This is not the
+        /// position, as messages may be evicted from the front.
+        pub(super) next_receive_index: Cell<u32>,
+        pub(super) retention: Cell<Retention>,
+        pub(super) latest_timestamp: Cell<Option<Timestamp>>,
+        pub(super) is_live: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -47,23 +94,13 @@ mod imp {
 
         // Define a method to get the number of items in the list model.
         fn n_items(&self) -> u32 {
-            self.inner.borrow().len() as u32
+            self.store.borrow().len() as u32
         }
 
         fn item(&self, position: u32) -> Option<glib::Object> {
-            // Borrow the inner vector, attempt to get the item at the specified position, map
-            // it to an `glib::Object`, and then clone it.
-            self.inner
-                .borrow()
-                .get(position as usize)
-                .map(|item| item.upcast_ref())
-                .cloned()
-        }
-    }
-    impl MessageList {
-        // Public method to access the inner field
-        pub fn inner(&self) -> RefCell<Vec<Message>> {
-            self.inner
+            self.obj()
+                .message_at(position as usize)
+                .map(|message| message.upcast())
         }
     }
 }
@@ -74,45 +111,55 @@ glib::wrapper! {
 }
 
 impl MessageList {
+    /// Whether the messages were recorded on more than one bus, which only
+    /// PCAPNG files can tell apart
+    pub fn has_multiple_buses(&self) -> bool {
+        let store = self.imp().store.borrow();
+        let mut bus_types = store.iter().map(|record| record.bus_type);
+        let first = bus_types.next();
+        bus_types.any(|bus_type| Some(bus_type) != first)
+    }
+
+    /// Returns the extension of the files the messages are saved to
+    pub fn file_extension(&self) -> &'static str {
+        if self.has_multiple_buses() {
+            "pcapng"
+        } else {
+            "pcap"
+        }
+    }
+
+    /// Saves the messages as a PCAP file, or as a PCAPNG file with an
+    /// interface per bus if they were recorded on more than one bus
     pub async fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
-        // Retrieve events from the inner model and convert them to `Event` instances.
-        let events = self
-            .imp()
-            .inner
-            .borrow()
-            .iter()
-            .map(|message| message.to_event())
-            .collect::<Vec<_>>();
+        // Copy the raw messages out of the store, so they can be written in
+        // another thread.
+        let packets = {
+            let store = self.imp().store.borrow();
+            store
+                .iter()
+                .map(|record| {
+                    (
+                        record.timestamp,
+                        record.bus_type,
+                        store.bytes(record).to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
 
         let path = path.as_ref().to_owned();
+        let has_multiple_buses = self.has_multiple_buses();
 
         // Asynchronously spawn a blocking task to write events to the file.
         RUNTIME
             .spawn_blocking(move || {
-                // Create a PCAP header with default values.
-                let header = PcapHeader {
-                    datalink: pcap_file::DataLink::DBUS,
-                    ..Default::default()
-                };
-
-                // Create the file and initialize a PCAP writer
                 let file = File::create(path).context("Failed to create file")?;
-                let mut writer = PcapWriter::with_header(BufWriter::new(file), header)
-                    .context("Failed to create writer")?;
-
-                // Write each event as a PCAP packet to the file.
-                for event in events {
-                    let message_bytes = event.message.data();
-                    writer
-                        .write_packet(&PcapPacket {
-                            timestamp: event.timestamp.into(),
-                            orig_len: message_bytes.len() as u32,
-                            data: Cow::Borrowed(message_bytes),
-                        })
-                        .context("Failed to write packet")?;
+                if has_multiple_buses {
+                    write_pcapng(BufWriter::new(file), packets)
+                } else {
+                    write_pcap(BufWriter::new(file), packets)
                 }
-
-                Ok(())
             })
             .await
             .context("Failed to spawn blocking task")?
@@ -127,8 +174,9 @@ impl MessageList {
 
         // Write the start of the DOT file
         writeln!(&mut buffer, "digraph bustle {{")?;
-        // Iterate over each message in the inner list
-        for message in self.imp().inner.borrow().iter() {
+        // Iterate over each message in the list
+        for message in self.iter::<Message>() {
+            let message = message.context("Message list was mutated while saving")?;
             // Get the sender and destination display names from the message
             let sender = message.sender_display();
             let destination = message.destination_display();
@@ -164,97 +212,883 @@ impl MessageList {
         // Convert the path to an owned PathBuf
         let path = path.as_ref().to_owned();
         // Spawn a blocking task to read the file and parse its contents
-        let events = RUNTIME
+        let store = RUNTIME
             .spawn_blocking(move || {
                 // Open the file
-                let file = File::open(&path)
+                let mut file = File::open(&path)
                     .with_context(|| format!("Failed to open file at `{}`", path.display()))?;
-                // Create a reader for the pcap file
-                let mut reader = PcapReader::new(file).context("Failed to create reader")?;
-
-                // Read the header of the pcap file
-                let header = reader.header();
-                // Check if the datalink type is compatible with dbus
-                if header.datalink != pcap_file::DataLink::DBUS {
-                    bail!("Invalid datalink type `{:?}`", header.datalink)
-                }
 
-                tracing::debug!(?path, ?header, "Loaded PCAP file");
+                let mut magic = [0; 4];
+                file.read_exact(&mut magic)
+                    .context("Failed to read file format")?;
+                file.seek(SeekFrom::Start(0))
+                    .context("Failed to rewind file")?;
 
-                let mut events = Vec::new();
-                // Iterate over each packet in the pcap file
-                while let Some(packet) = reader.next_packet() {
-                    // Parse the packet into an event
-                    let packet = packet.context("Failed to get packet")?;
-                    let event = Event::from_packet(packet)
-                        .context("Failed to construct event from packet")?;
-                    events.push(event);
-                }
+                tracing::debug!(?path, "Loading file");
 
-                Ok(events)
+                // Only keep the raw bytes here, as parsed messages take a lot
+                // more memory and are only needed while indexing them
+                let mut store = MessageStore::default();
+                if magic == PCAPNG_MAGIC {
+                    read_pcapng(file, &mut store)?;
+                } else {
+                    read_pcap(file, &mut store)?;
+                }
+                Ok(store)
             })
             .await
             .context("Failed to join handle")?
             .context("Failed to load from file")?;
 
         let this = Self::default();
+        this.index_loaded(store);
+
+        Ok(this)
+    }
+
+    pub fn push(&self, event: Event) {
+        self.push_batch(vec![event], BusType::default());
+    }
+
+    /// Appends `events` recorded on `bus_type`, notifying about them at once
+    pub fn push_batch(&self, events: Vec<Event>, bus_type: BusType) {
+        let n_added = events.len() as u32;
+        if n_added == 0 {
+            return;
+        }
 
         for event in events {
-            this.push_inner(Message::from_event(event));
+            self.push_inner(event, bus_type);
         }
+        self.items_changed(self.n_items() - n_added, 0, n_added);
 
-        Ok(this)
+        let n_evicted = self.evict();
+        if n_evicted != 0 {
+            self.items_changed(0, n_evicted as u32, 0);
+        }
     }
 
-    pub fn push(&self, message: Message) {
-        self.push_inner(message);
-        self.items_changed(self.n_items() - 1, 0, 1);
+    /// Sets how many messages are kept as new ones are pushed
+    pub fn set_retention(&self, retention: Retention) {
+        self.imp().retention.set(retention);
     }
 
     pub fn bus_names(&self) -> &BusNameList {
         &self.imp().bus_names
     }
 
-    fn push_inner(&self, message: Message) {
-        // Get the current position (index) of the message in the model.
-        let position = self.n_items();
+    /// Returns the calls that expected a reply but did not get one, in the
+    /// order they were received
+    pub fn unanswered_calls(&self) -> Vec<Message> {
+        let mut receive_indices = self
+            .imp()
+            .pending_calls
+            .borrow()
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+        receive_indices.sort();
+
+        receive_indices
+            .into_iter()
+            .filter_map(|receive_index| self.message_by_receive_index(receive_index))
+            .collect()
+    }
+
+    /// Returns whether `call` did not get a return yet
+    pub fn is_call_pending(&self, call: &Message) -> bool {
+        debug_assert!(call.message_type().is_method_call());
+
+        call_key(call).is_some_and(|key| {
+            self.imp().pending_calls.borrow().get(&key) == Some(&call.receive_index())
+        })
+    }
+
+    /// Returns whether `call` expected a reply it did not get, or is
+    /// unlikely to get anymore as it timed out while still recording
+    pub fn is_call_unanswered(&self, call: &Message) -> bool {
+        if !call.expects_reply() || !self.is_call_pending(call) {
+            return false;
+        }
+
+        !self.is_live()
+            || self.latest_timestamp().is_some_and(|latest| {
+                Duration::from(latest.saturating_sub(call.timestamp())) > DEFAULT_REPLY_TIMEOUT
+            })
+    }
+
+    /// Sets whether messages are still being recorded, so that pending calls
+    /// may still get their reply
+    pub fn set_live(&self, is_live: bool) {
+        self.imp().is_live.set(is_live);
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.imp().is_live.get()
+    }
+
+    /// Returns the timestamp of the most recent message ever pushed
+    ///
+    /// This is not necessarily the one of the last message, as messages of
+    /// different buses may be slightly out of order.
+    pub fn latest_timestamp(&self) -> Option<Timestamp> {
+        self.imp().latest_timestamp.get()
+    }
+
+    /// Returns the message at `position`, creating it if it is not alive
+    pub fn message_at(&self, position: usize) -> Option<Message> {
+        let imp = self.imp();
+
+        let record = *imp.store.borrow().get(position)?;
+
+        if let Some(message) = imp
+            .message_cache
+            .borrow()
+            .get(&record.receive_index)
+            .and_then(|message| message.upgrade())
+        {
+            return Some(message);
+        }
+
+        let bytes = imp.store.borrow().bytes(&record).to_vec();
+        let event = Event::from_bytes(bytes, record.timestamp)
+            .expect("stored messages were decoded when pushed");
+
+        let message = Message::from_event(event);
+        message.set_receive_index(record.receive_index);
+        message.set_message_tag(record.message_tag);
+        message.set_bus_type(record.bus_type);
+        message.set_message_list(self);
+        self.cache_message(&message);
+
+        Some(message)
+    }
+
+    /// Returns the message with `receive_index`, or None if it was evicted
+    pub fn message_by_receive_index(&self, receive_index: ReceiveIndex) -> Option<Message> {
+        let position = self.imp().store.borrow().position_of(receive_index)?;
+        self.message_at(position)
+    }
+
+    /// Returns the return of the call with `receive_index`, or the call of
+    /// the return with `receive_index`
+    pub fn associated_message(&self, receive_index: ReceiveIndex) -> Option<Message> {
+        let associated = self
+            .imp()
+            .store
+            .borrow()
+            .get_by_receive_index(receive_index)?
+            .associated?;
+        self.message_by_receive_index(associated)
+    }
+
+    fn cache_message(&self, message: &Message) {
+        let imp = self.imp();
+
+        let mut message_cache = imp.message_cache.borrow_mut();
 
-        // Set the receive index of the message to its position in the model.
-        message.set_receive_index(position);
+        // Prune dead entries once the cache doubled in size, so this stays
+        // amortized constant time
+        if message_cache.len() >= 2 * imp.n_cached_messages.get().max(1024) {
+            message_cache.retain(|_, message| message.upgrade().is_some());
+            imp.n_cached_messages.set(message_cache.len());
+        }
 
+        message_cache.insert(message.receive_index(), message.downgrade());
+    }
+
+    fn push_inner(&self, event: Event, bus_type: BusType) {
         // Get a reference to the implementation of the filtered message model.
         let imp = self.imp();
 
+        let receive_index = ReceiveIndex::new(imp.next_receive_index.get());
+        imp.next_receive_index.set(imp.next_receive_index.get() + 1);
+
+        imp.latest_timestamp
+            .set(imp.latest_timestamp.get().max(Some(event.timestamp)));
+
+        imp.store
+            .borrow_mut()
+            .push(&event.message, receive_index, event.timestamp, bus_type);
+
+        let message = Message::from_event(event);
+        message.set_bus_type(bus_type);
+        message.set_receive_index(receive_index);
+        message.set_message_list(self);
+        self.cache_message(&message);
+
+        self.index_message(&message);
+    }
+
+    /// Takes over the messages of `store` as if they were pushed one by one
+    ///
+    /// This must only be called on a new list.
+    fn index_loaded(&self, store: MessageStore) {
+        let imp = self.imp();
+
+        debug_assert_eq!(imp.store.borrow().len(), 0);
+
+        let n_messages = store.len();
+        imp.next_receive_index.set(n_messages as u32);
+        imp.latest_timestamp
+            .set(store.iter().map(|record| record.timestamp).max());
+        imp.store.replace(store);
+
+        for position in 0..n_messages {
+            if let Some(message) = self.message_at(position) {
+                self.index_message(&message);
+            }
+        }
+    }
+
+    /// Links `message` to the messages received before it, and records the
+    /// names it refers to
+    fn index_message(&self, message: &Message) {
+        let imp = self.imp();
+        let receive_index = message.receive_index();
+
         if message.message_type().is_method_return() {
-            // Reverse so we first look at the most recent call. This speeds up the search
-            // substantially in the common case where the return is close to the call.
-            if let Some(associated_message) = imp
-                .inner
-                .borrow()
-                .iter()
-                .rev()
-                .find(|other_message| message.is_return_of(other_message))
-            {
-                message.set_associated_message(associated_message);
-                associated_message.set_associated_message(&message);
+            let call_receive_index =
+                return_key(message).and_then(|key| imp.pending_calls.borrow_mut().remove(&key));
+            if let Some(call_receive_index) = call_receive_index {
+                debug_assert!(self
+                    .message_by_receive_index(call_receive_index)
+                    .is_some_and(|call| message.is_return_of(&call)));
+
+                let mut store = imp.store.borrow_mut();
+                store
+                    .get_mut_by_receive_index(receive_index)
+                    .unwrap()
+                    .associated = Some(call_receive_index);
+                store
+                    .get_mut_by_receive_index(call_receive_index)
+                    .unwrap()
+                    .associated = Some(receive_index);
             }
         }
 
         // Only handle message when we have its associated message
-        if let Err(err) = imp.bus_names.handle_message(&message) {
+        if let Err(err) = imp.bus_names.handle_message(message) {
             tracing::warn!(%message, "Failed to handle message: {:?}", err);
         }
 
         // Only try to guess the component once we have an associated message
-        let message_tag = MessageTag::guess(&message);
+        let message_tag = MessageTag::guess(message);
         message.set_message_tag(message_tag);
+        imp.store
+            .borrow_mut()
+            .get_mut_by_receive_index(receive_index)
+            .unwrap()
+            .message_tag = message_tag;
+
+        // Calls that expect no reply would never leave the pending calls
+        if message.expects_reply() {
+            if let Some(key) = call_key(message) {
+                imp.pending_calls.borrow_mut().insert(key, receive_index);
+            }
+        }
+    }
+
+    /// Removes the oldest messages that are out of the retention and
+    /// returns how many were removed
+    fn evict(&self) -> usize {
+        let imp = self.imp();
+
+        let n_evicted = {
+            let store = imp.store.borrow();
+            match imp.retention.get() {
+                Retention::Unlimited => 0,
+                Retention::Count(count) => store.len().saturating_sub(count),
+                Retention::Duration(duration) => {
+                    match store
+                        .last()
+                        .and_then(|last| Duration::from(last.timestamp).checked_sub(duration))
+                    {
+                        // Timestamps of different buses may be slightly out
+                        // of order, so stop at the first retained message
+                        // rather than searching for it
+                        Some(oldest) => store
+                            .iter()
+                            .take_while(|record| record.timestamp < Timestamp::from(oldest))
+                            .count(),
+                        None => 0,
+                    }
+                }
+            }
+        };
+
+        if n_evicted == 0 {
+            return 0;
+        }
+
+        // Only pending calls need to be decoded again, to find their key
+        let evicted_pending_calls = (0..n_evicted)
+            .filter(|position| {
+                let store = imp.store.borrow();
+                let record = store.get(*position).unwrap();
+                record.message_type.is_method_call() && record.associated.is_none()
+            })
+            .filter_map(|position| self.message_at(position))
+            .filter_map(|call| Some((call_key(&call)?, call.receive_index())))
+            .collect::<Vec<_>>();
+        let mut pending_calls = imp.pending_calls.borrow_mut();
+        for (key, receive_index) in evicted_pending_calls {
+            // The key may have been reused by a more recent call
+            if pending_calls.get(&key) == Some(&receive_index) {
+                pending_calls.remove(&key);
+            }
+        }
+        drop(pending_calls);
+
+        // Links from retained messages to evicted ones are left as is, as
+        // looking up an evicted receive index gives nothing
+        let first_receive_index = {
+            let mut store = imp.store.borrow_mut();
+            let mut message_cache = imp.message_cache.borrow_mut();
+            for _ in 0..n_evicted {
+                let record = store.pop_front().unwrap();
+                message_cache.remove(&record.receive_index);
+            }
+            store.first().map(|first| first.receive_index)
+        };
 
-        imp.inner.borrow_mut().push(message);
+        // Removing names notifies their handlers, which may look up messages
+        if let Some(first_receive_index) = first_receive_index {
+            imp.bus_names.trim_wk_name_logs(first_receive_index);
+            imp.bus_names.remove_unreferenced(first_receive_index);
+        }
+
+        n_evicted
     }
 }
 
+/// Identifies a call by its bus, sender and serial, which is what returns
+/// refer to
+type CallKey = (BusType, UniqueName<'static>, u32);
+
+/// Returns the key of `call` in the pending calls
+fn call_key(call: &Message) -> Option<CallKey> {
+    let sender = call.sender()?.to_owned();
+    Some((
+        call.bus_type(),
+        sender,
+        call.header().primary().serial_num().get(),
+    ))
+}
+
+/// Returns the key of the call `return_message` replies to in the pending
+/// calls
+fn return_key(return_message: &Message) -> Option<CallKey> {
+    let header = return_message.header();
+    let BusName::Unique(destination) = header.destination()? else {
+        return None;
+    };
+    Some((
+        return_message.bus_type(),
+        destination.to_owned(),
+        header.reply_serial()?.get(),
+    ))
+}
+
 impl Default for MessageList {
     fn default() -> Self {
         glib::Object::new()
     }
 }
+
+/// A raw message as saved in a file
+type Packet = (Timestamp, BusType, Vec<u8>);
+
+fn write_pcap(writer: impl Write, packets: Vec<Packet>) -> Result<()> {
+    // Create a PCAP header with default values.
+    let header = PcapHeader {
+        datalink: pcap_file::DataLink::DBUS,
+        ..Default::default()
+    };
+    let mut writer = PcapWriter::with_header(writer, header).context("Failed to create writer")?;
+
+    // Write each message as a PCAP packet to the file.
+    for (timestamp, _, message_bytes) in packets {
+        writer
+            .write_packet(&PcapPacket {
+                timestamp: timestamp.into(),
+                orig_len: message_bytes.len() as u32,
+                data: Cow::Borrowed(&message_bytes),
+            })
+            .context("Failed to write packet")?;
+    }
+
+    Ok(())
+}
+
+/// Writes `packets` with an interface per bus, named after the bus
+fn write_pcapng(writer: impl Write, packets: Vec<Packet>) -> Result<()> {
+    let mut writer = PcapNgWriter::new(writer).context("Failed to create writer")?;
+
+    let mut interfaces = Vec::new();
+    for (timestamp, bus_type, message_bytes) in packets {
+        let interface_id = match interfaces.iter().position(|known| *known == bus_type) {
+            Some(interface_id) => interface_id,
+            None => {
+                writer
+                    .write_pcapng_block(InterfaceDescriptionBlock {
+                        linktype: pcap_file::DataLink::DBUS,
+                        snaplen: 0,
+                        options: vec![InterfaceDescriptionOption::IfName(Cow::Borrowed(
+                            bus_type.interface_name(),
+                        ))],
+                    })
+                    .context("Failed to write interface")?;
+                interfaces.push(bus_type);
+                interfaces.len() - 1
+            }
+        };
+
+        writer
+            .write_pcapng_block(EnhancedPacketBlock {
+                interface_id: interface_id as u32,
+                timestamp: timestamp.into(),
+                original_len: message_bytes.len() as u32,
+                data: Cow::Borrowed(&message_bytes),
+                options: Vec::new(),
+            })
+            .context("Failed to write packet")?;
+    }
+
+    Ok(())
+}
+
+/// Appends the raw message `bytes` to `store`, checking that it is valid
+fn push_packet(
+    store: &mut MessageStore,
+    timestamp: Timestamp,
+    bus_type: BusType,
+    bytes: Vec<u8>,
+) -> Result<()> {
+    let event =
+        Event::from_bytes(bytes, timestamp).context("Failed to construct event from packet")?;
+    let receive_index = store
+        .last()
+        .map_or(ReceiveIndex::new(0), |last| last.receive_index.next());
+    store.push(&event.message, receive_index, timestamp, bus_type);
+    Ok(())
+}
+
+fn read_pcap(reader: impl Read, store: &mut MessageStore) -> Result<()> {
+    let mut reader = PcapReader::new(reader).context("Failed to create reader")?;
+
+    // Check if the datalink type is compatible with dbus
+    let header = reader.header();
+    if header.datalink != pcap_file::DataLink::DBUS {
+        bail!("Invalid datalink type `{:?}`", header.datalink)
+    }
+
+    while let Some(packet) = reader.next_packet() {
+        let packet = packet.context("Failed to get packet")?;
+        push_packet(
+            store,
+            Timestamp::from(packet.timestamp),
+            BusType::default(),
+            packet.data.into_owned(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Reads the packets of every interface, taking their bus from the
+/// interface name
+fn read_pcapng(reader: impl Read, store: &mut MessageStore) -> Result<()> {
+    let mut reader = PcapNgReader::new(reader).context("Failed to create reader")?;
+
+    let mut interfaces = Vec::new();
+    while let Some(block) = reader.next_block() {
+        match block.context("Failed to get block")? {
+            // Interfaces are numbered per section
+            Block::SectionHeader(_) => interfaces.clear(),
+            Block::InterfaceDescription(interface) => {
+                if interface.linktype != pcap_file::DataLink::DBUS {
+                    bail!("Invalid datalink type `{:?}`", interface.linktype)
+                }
+                let bus_type = interface
+                    .options
+                    .iter()
+                    .find_map(|option| match option {
+                        InterfaceDescriptionOption::IfName(name) => {
+                            BusType::from_interface_name(name)
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                interfaces.push(bus_type);
+            }
+            Block::EnhancedPacket(packet) => {
+                let bus_type = *interfaces
+                    .get(packet.interface_id as usize)
+                    .with_context(|| format!("Unknown interface `{}`", packet.interface_id))?;
+                push_packet(
+                    store,
+                    Timestamp::from(packet.timestamp),
+                    bus_type,
+                    packet.data.into_owned(),
+                )?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bus_name_item::BusNameItem,
+        test_util::{call, event, reply, signal},
+    };
+
+    fn names(message_list: &MessageList) -> Vec<String> {
+        message_list
+            .bus_names()
+            .iter::<BusNameItem>()
+            .map(|bus_name_item| bus_name_item.unwrap().name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn associate_by_bus_and_serial() {
+        let message_list = MessageList::default();
+
+        // The same sender and serial on both buses
+        let call = call(":1.2");
+        message_list.push_batch(vec![event(call.clone(), 0)], BusType::Session);
+        message_list.push_batch(vec![event(call.clone(), 10)], BusType::System);
+        message_list.push_batch(vec![event(reply(&call), 20)], BusType::System);
+
+        let session_call = message_list.message_at(0).unwrap();
+        let system_call = message_list.message_at(1).unwrap();
+        let system_return = message_list.message_at(2).unwrap();
+        assert_eq!(session_call.associated_message(), None);
+        assert_eq!(
+            system_call.associated_message().as_ref(),
+            Some(&system_return)
+        );
+        assert_eq!(
+            system_return.associated_message().as_ref(),
+            Some(&system_call)
+        );
+        assert_eq!(
+            message_list.unanswered_calls(),
+            std::slice::from_ref(&session_call)
+        );
+
+        message_list.push_batch(vec![event(reply(&call), 30)], BusType::Session);
+        let session_return = message_list.message_at(3).unwrap();
+        assert_eq!(
+            session_call.associated_message().as_ref(),
+            Some(&session_return)
+        );
+        assert!(message_list.unanswered_calls().is_empty());
+
+        // A second reply to the same serial is not linked to anything
+        message_list.push_batch(vec![event(reply(&call), 40)], BusType::Session);
+        assert_eq!(
+            message_list.message_at(4).unwrap().associated_message(),
+            None
+        );
+    }
+
+    #[test]
+    fn no_reply_expected() {
+        let message_list = MessageList::default();
+
+        let call = zbus::Message::method("/org/example/Foo", "Bar")
+            .unwrap()
+            .sender(":1.2")
+            .unwrap()
+            .destination(":1.1")
+            .unwrap()
+            .with_flags(zbus::MessageFlags::NoReplyExpected)
+            .unwrap()
+            .build(&())
+            .unwrap();
+        message_list.push_batch(vec![event(call.clone(), 0)], BusType::Session);
+        assert!(message_list.imp().pending_calls.borrow().is_empty());
+        assert!(message_list.unanswered_calls().is_empty());
+
+        // A reply sent anyway is not linked to the call
+        message_list.push_batch(vec![event(reply(&call), 10)], BusType::Session);
+        assert_eq!(
+            message_list.message_at(1).unwrap().associated_message(),
+            None
+        );
+    }
+
+    #[test]
+    fn late_reply_after_eviction() {
+        let message_list = MessageList::default();
+        message_list.set_retention(Retention::Count(2));
+
+        let call = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(call.clone(), 0),
+                event(signal(":1.3"), 10),
+                event(signal(":1.3"), 20),
+            ],
+            BusType::Session,
+        );
+        assert!(message_list.imp().pending_calls.borrow().is_empty());
+
+        message_list.push_batch(vec![event(reply(&call), 30)], BusType::Session);
+        assert_eq!(
+            message_list.message_at(1).unwrap().associated_message(),
+            None
+        );
+    }
+
+    #[test]
+    fn late_reply() {
+        let message_list = MessageList::default();
+
+        let early_call = call(":1.2");
+        let late_call = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(early_call.clone(), 0),
+                event(late_call.clone(), 0),
+                event(reply(&early_call), 10_000),
+                event(reply(&late_call), 30_000),
+            ],
+            BusType::Session,
+        );
+
+        let is_late_reply = message_list
+            .iter::<Message>()
+            .map(|message| message.unwrap().is_late_reply())
+            .collect::<Vec<_>>();
+        assert_eq!(is_late_reply, [false, true, false, true]);
+        assert!(message_list.unanswered_calls().is_empty());
+    }
+
+    #[test]
+    fn unanswered() {
+        let message_list = MessageList::default();
+        message_list.set_live(true);
+
+        let answered = call(":1.2");
+        let unanswered = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(answered.clone(), 0),
+                event(unanswered.clone(), 1000),
+                event(reply(&answered), 2000),
+            ],
+            BusType::Session,
+        );
+        let answered = message_list.message_at(0).unwrap();
+        let unanswered = message_list.message_at(1).unwrap();
+        assert_eq!(
+            message_list.unanswered_calls(),
+            std::slice::from_ref(&unanswered)
+        );
+
+        // Pending calls may still get their reply while recording
+        assert!(!message_list.is_call_unanswered(&unanswered));
+        message_list.push_batch(vec![event(signal(":1.3"), 27_000)], BusType::Session);
+        assert!(message_list.is_call_unanswered(&unanswered));
+        assert!(!message_list.is_call_unanswered(&answered));
+
+        // Messages out of order do not make calls younger
+        message_list.push_batch(vec![event(signal(":1.3"), 5000)], BusType::Session);
+        assert!(message_list.is_call_unanswered(&unanswered));
+
+        let pending = call(":1.2");
+        message_list.push_batch(vec![event(pending, 28_000)], BusType::Session);
+        let pending = message_list.message_at(5).unwrap();
+        assert!(!message_list.is_call_unanswered(&pending));
+        message_list.set_live(false);
+        assert!(message_list.is_call_unanswered(&pending));
+        assert_eq!(message_list.unanswered_calls(), [unanswered, pending]);
+    }
+
+    #[test]
+    fn evict_count() {
+        let message_list = MessageList::default();
+        message_list.set_retention(Retention::Count(2));
+
+        let call = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(call.clone(), 0),
+                event(reply(&call), 10),
+                event(signal(":1.3"), 20),
+            ],
+            BusType::Session,
+        );
+        assert_eq!(message_list.n_items(), 2);
+
+        // The return is no longer linked to its evicted call
+        let return_message = message_list.message_at(0).unwrap();
+        assert!(return_message.message_type().is_method_return());
+        assert_eq!(return_message.associated_message(), None);
+        assert_eq!(names(&message_list), [":1.2", ":1.1", ":1.3"]);
+
+        // Names are removed along with the last message referring to them
+        message_list.push_batch(
+            vec![event(signal(":1.3"), 30), event(signal(":1.3"), 40)],
+            BusType::Session,
+        );
+        assert_eq!(message_list.n_items(), 2);
+        assert_eq!(names(&message_list), [":1.3"]);
+    }
+
+    #[test]
+    fn evict_duration() {
+        let message_list = MessageList::default();
+        message_list.set_retention(Retention::Duration(Duration::from_secs(1)));
+
+        let call = call(":1.2");
+        message_list.push_batch(
+            vec![event(call.clone(), 0), event(signal(":1.3"), 500)],
+            BusType::Session,
+        );
+        assert_eq!(message_list.n_items(), 2);
+
+        message_list.push_batch(vec![event(signal(":1.3"), 1200)], BusType::Session);
+        assert_eq!(message_list.n_items(), 2);
+
+        // The reply of the evicted call is not linked to anything
+        message_list.push_batch(vec![event(reply(&call), 1300)], BusType::Session);
+        assert_eq!(message_list.n_items(), 3);
+        let return_message = message_list.message_at(2).unwrap();
+        assert_eq!(return_message.associated_message(), None);
+        assert!(message_list.unanswered_calls().is_empty());
+
+        // Messages slightly out of order are evicted from the oldest one on
+        message_list.push_batch(
+            vec![event(signal(":1.3"), 1400), event(signal(":1.3"), 1350)],
+            BusType::Session,
+        );
+        message_list.push_batch(vec![event(signal(":1.3"), 2390)], BusType::Session);
+        let timestamps = message_list
+            .iter::<Message>()
+            .map(|message| Duration::from(message.unwrap().timestamp()).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(timestamps, [1400, 1350, 2390]);
+    }
+
+    /// Saves `message_list` and loads it back
+    fn save_and_load(message_list: &MessageList, name: &str) -> MessageList {
+        let path = std::env::temp_dir().join(format!("bustle-test-{}-{name}", std::process::id()));
+        let loaded = glib::MainContext::default().block_on(async {
+            message_list.save_to_file(&path).await?;
+            MessageList::load_from_file(&path).await
+        });
+        let _ = std::fs::remove_file(&path);
+        loaded.unwrap()
+    }
+
+    fn summary(message_list: &MessageList) -> Vec<(u128, BusType, Option<u128>, Vec<u8>)> {
+        let millis = |message: &Message| Duration::from(message.timestamp()).as_millis();
+        message_list
+            .iter::<Message>()
+            .map(|message| {
+                let message = message.unwrap();
+                (
+                    millis(&message),
+                    message.bus_type(),
+                    message.associated_message().as_ref().map(millis),
+                    message_list
+                        .imp()
+                        .store
+                        .borrow()
+                        .get_by_receive_index(message.receive_index())
+                        .map(|record| message_list.imp().store.borrow().bytes(record).to_vec())
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn save_and_load_single_bus() {
+        let message_list = MessageList::default();
+        let call = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(call.clone(), 0),
+                event(signal(":1.3"), 10),
+                event(reply(&call), 20),
+            ],
+            BusType::Session,
+        );
+
+        assert_eq!(message_list.file_extension(), "pcap");
+        let loaded = save_and_load(&message_list, "single.pcap");
+        assert_eq!(summary(&loaded), summary(&message_list));
+        assert!(loaded.unanswered_calls().is_empty());
+        assert_eq!(names(&loaded), names(&message_list));
+    }
+
+    #[test]
+    fn save_and_load_both_buses() {
+        let message_list = MessageList::default();
+        let call = call(":1.2");
+        message_list.push_batch(vec![event(call.clone(), 0)], BusType::Session);
+        message_list.push_batch(vec![event(call.clone(), 10)], BusType::System);
+        message_list.push_batch(vec![event(reply(&call), 20)], BusType::System);
+
+        assert_eq!(message_list.file_extension(), "pcapng");
+        let loaded = save_and_load(&message_list, "both.pcapng");
+        assert_eq!(summary(&loaded), summary(&message_list));
+        assert_eq!(
+            loaded
+                .unanswered_calls()
+                .iter()
+                .map(|call| call.bus_type())
+                .collect::<Vec<_>>(),
+            [BusType::Session]
+        );
+    }
+
+    #[test]
+    fn pcapng_round_trip() {
+        let packets = vec![
+            (
+                Timestamp::from(Duration::from_micros(10)),
+                BusType::Session,
+                signal(":1.1").data().to_vec(),
+            ),
+            (
+                Timestamp::from(Duration::from_micros(20)),
+                BusType::System,
+                signal(":1.2").data().to_vec(),
+            ),
+            (
+                Timestamp::from(Duration::from_micros(30)),
+                BusType::Session,
+                call(":1.3").data().to_vec(),
+            ),
+        ];
+
+        let mut bytes = Vec::new();
+        write_pcapng(&mut bytes, packets.clone()).unwrap();
+        assert_eq!(bytes[..4], PCAPNG_MAGIC);
+
+        let mut store = MessageStore::default();
+        read_pcapng(bytes.as_slice(), &mut store).unwrap();
+        let read = store
+            .iter()
+            .map(|record| {
+                (
+                    record.timestamp,
+                    record.bus_type,
+                    store.bytes(record).to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(read, packets);
+    }
+}
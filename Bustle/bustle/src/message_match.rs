@@ -0,0 +1,188 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::message_type::MessageType;
+
+/// Condition on the header of a message, written like a match rule, e.g.,
+/// `type='error',error_name='org.freedesktop.DBus.Error.NoReply'`
+///
+/// Supported keys are `type`, `sender`, `destination`, `path`, `interface`,
+/// `member` and `error_name`. A message matches if it has all the given
+/// values.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessageMatch {
+    message_type: Option<MessageType>,
+    sender: Option<String>,
+    destination: Option<String>,
+    path: Option<String>,
+    interface: Option<String>,
+    member: Option<String>,
+    error_name: Option<String>,
+}
+
+impl MessageMatch {
+    pub fn matches(&self, message: &zbus::Message) -> bool {
+        let header = message.header();
+
+        self.message_type
+            .is_none_or(|message_type| MessageType::from(message.message_type()) == message_type)
+            && field_matches(&self.sender, header.sender().map(|s| s.as_str()))
+            && field_matches(&self.destination, header.destination().map(|d| d.as_str()))
+            && field_matches(&self.path, header.path().map(|p| p.as_str()))
+            && field_matches(&self.interface, header.interface().map(|i| i.as_str()))
+            && field_matches(&self.member, header.member().map(|m| m.as_str()))
+            && field_matches(&self.error_name, header.error_name().map(|e| e.as_str()))
+    }
+}
+
+fn field_matches(expected: &Option<String>, actual: Option<&str>) -> bool {
+    expected
+        .as_deref()
+        .is_none_or(|expected| actual == Some(expected))
+}
+
+impl FromStr for MessageMatch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ret = Self::default();
+        let mut is_empty = true;
+
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let (key, after_key) = rest.split_once('=').context("Expected `=` after key")?;
+            let after_key = after_key.trim_start();
+
+            let (value, after_value) = match after_key.strip_prefix('\'') {
+                Some(quoted) => quoted
+                    .split_once('\'')
+                    .context("Expected `'` after value")?,
+                None => {
+                    let end = after_key.find(',').unwrap_or(after_key.len());
+                    (after_key[..end].trim_end(), &after_key[end..])
+                }
+            };
+
+            let field = match key.trim() {
+                "type" => {
+                    ret.message_type = Some(match value {
+                        "method_call" => MessageType::MethodCall,
+                        "method_return" => MessageType::MethodReturn,
+                        "error" => MessageType::Error,
+                        "signal" => MessageType::Signal,
+                        other => bail!("Unknown message type `{other}`"),
+                    });
+                    None
+                }
+                "sender" => Some(&mut ret.sender),
+                "destination" => Some(&mut ret.destination),
+                "path" => Some(&mut ret.path),
+                "interface" => Some(&mut ret.interface),
+                "member" => Some(&mut ret.member),
+                "error_name" => Some(&mut ret.error_name),
+                other => bail!("Unknown key `{other}`"),
+            };
+            if let Some(field) = field {
+                *field = Some(value.to_owned());
+            }
+            is_empty = false;
+
+            rest = after_value.trim_start();
+            if !rest.is_empty() {
+                rest = rest
+                    .strip_prefix(',')
+                    .context("Expected `,` between conditions")?
+                    .trim_start();
+            }
+        }
+
+        if is_empty {
+            bail!("Expected at least one condition");
+        }
+
+        Ok(ret)
+    }
+}
+
+impl fmt::Display for MessageMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message_type = self.message_type.map(|message_type| match message_type {
+            MessageType::MethodCall => "method_call",
+            MessageType::MethodReturn => "method_return",
+            MessageType::Error => "error",
+            MessageType::Signal => "signal",
+        });
+        let fields = [
+            ("type", message_type),
+            ("sender", self.sender.as_deref()),
+            ("destination", self.destination.as_deref()),
+            ("path", self.path.as_deref()),
+            ("interface", self.interface.as_deref()),
+            ("member", self.member.as_deref()),
+            ("error_name", self.error_name.as_deref()),
+        ];
+
+        let mut is_first = true;
+        for (key, value) in fields {
+            let Some(value) = value else {
+                continue;
+            };
+            if !is_first {
+                f.write_str(",")?;
+            }
+            write!(f, "{key}='{value}'")?;
+            is_first = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let message_match = "type='error', error_name=org.example.Failed,member='A,B'"
+            .parse::<MessageMatch>()
+            .unwrap();
+        assert_eq!(message_match.message_type, Some(MessageType::Error));
+        assert_eq!(
+            message_match.error_name.as_deref(),
+            Some("org.example.Failed")
+        );
+        assert_eq!(message_match.member.as_deref(), Some("A,B"));
+        assert_eq!(
+            message_match.to_string(),
+            "type='error',member='A,B',error_name='org.example.Failed'"
+        );
+
+        assert!("".parse::<MessageMatch>().is_err());
+        assert!("member".parse::<MessageMatch>().is_err());
+        assert!("member='Ping".parse::<MessageMatch>().is_err());
+        assert!("type='call'".parse::<MessageMatch>().is_err());
+        assert!("color='red'".parse::<MessageMatch>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let message = zbus::Message::method("/org/example", "Ping")
+            .unwrap()
+            .interface("org.example.Pinger")
+            .unwrap()
+            .build(&())
+            .unwrap();
+
+        let matching = "type=method_call,member=Ping,interface=org.example.Pinger"
+            .parse::<MessageMatch>()
+            .unwrap();
+        assert!(matching.matches(&message));
+
+        let not_matching = "member=Ping,sender=':1.42'"
+            .parse::<MessageMatch>()
+            .unwrap();
+        assert!(!not_matching.matches(&message));
+    }
+}
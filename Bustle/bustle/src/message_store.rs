@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use crate::{
+    bus_type::BusType, message::ReceiveIndex, message_tag::MessageTag, message_type::MessageType,
+    timestamp::Timestamp,
+};
+
+/// Size of the chunks the raw message bytes are appended to. Messages that
+/// are larger get a chunk of their own.
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// What is kept of a message without decoding it again
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    chunk: u32,
+    offset: u32,
+    len: u32,
+    pub receive_index: ReceiveIndex,
+    pub timestamp: Timestamp,
+    pub message_type: MessageType,
+    pub message_tag: MessageTag,
+    pub bus_type: BusType,
+    /// Receive index of the call of a return, or of the return of a call
+    pub associated: Option<ReceiveIndex>,
+}
+
+/// Compact storage of raw messages, in the order they were received
+///
+/// Messages are appended to large chunks of bytes instead of being allocated
+/// one by one, and only a fixed size `Record` is kept next to them. Messages
+/// can only be removed from the front, which allows looking them up by
+/// receive index in constant time.
+#[derive(Debug, Default)]
+pub struct MessageStore {
+    chunks: VecDeque<Vec<u8>>,
+    /// Identifier of the front chunk
+    first_chunk: u32,
+    records: VecDeque<Record>,
+}
+
+impl MessageStore {
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Appends the bytes of `message` with the given metadata
+    ///
+    /// Only messages that were already decoded can be pushed, so that the
+    /// stored bytes can always be decoded again. `receive_index` must be the
+    /// one following the last pushed message.
+    pub fn push(
+        &mut self,
+        message: &zbus::Message,
+        receive_index: ReceiveIndex,
+        timestamp: Timestamp,
+        bus_type: BusType,
+    ) {
+        let bytes: &[u8] = message.data();
+
+        debug_assert!(self
+            .records
+            .back()
+            .is_none_or(|last| last.receive_index.next() == receive_index));
+
+        let needs_new_chunk = self
+            .chunks
+            .back()
+            .is_none_or(|chunk| chunk.len() + bytes.len() > chunk.capacity());
+        if needs_new_chunk {
+            self.chunks
+                .push_back(Vec::with_capacity(CHUNK_SIZE.max(bytes.len())));
+        }
+
+        let chunk = self.chunks.back_mut().unwrap();
+        let offset = chunk.len();
+        chunk.extend_from_slice(bytes);
+
+        self.records.push_back(Record {
+            chunk: self.first_chunk + self.chunks.len() as u32 - 1,
+            offset: offset as u32,
+            len: bytes.len() as u32,
+            receive_index,
+            timestamp,
+            message_type: MessageType::from(message.message_type()),
+            message_tag: MessageTag::default(),
+            bus_type,
+            associated: None,
+        });
+    }
+
+    /// Removes the oldest message, freeing its chunk if no other message
+    /// uses it
+    pub fn pop_front(&mut self) -> Option<Record> {
+        let record = self.records.pop_front()?;
+
+        let first_used_chunk = self
+            .records
+            .front()
+            .map_or(self.first_chunk + self.chunks.len() as u32, |front| {
+                front.chunk
+            });
+        while self.first_chunk < first_used_chunk {
+            self.chunks.pop_front();
+            self.first_chunk += 1;
+        }
+
+        Some(record)
+    }
+
+    pub fn get(&self, position: usize) -> Option<&Record> {
+        self.records.get(position)
+    }
+
+    pub fn first(&self) -> Option<&Record> {
+        self.records.front()
+    }
+
+    pub fn last(&self) -> Option<&Record> {
+        self.records.back()
+    }
+
+    /// Returns the position of the message with `receive_index`, if it was
+    /// not removed yet
+    pub fn position_of(&self, receive_index: ReceiveIndex) -> Option<usize> {
+        let first = self.records.front()?.receive_index;
+        let position = receive_index.distance_from(first)?;
+        (position < self.records.len()).then_some(position)
+    }
+
+    pub fn get_by_receive_index(&self, receive_index: ReceiveIndex) -> Option<&Record> {
+        self.get(self.position_of(receive_index)?)
+    }
+
+    pub fn get_mut_by_receive_index(&mut self, receive_index: ReceiveIndex) -> Option<&mut Record> {
+        let position = self.position_of(receive_index)?;
+        self.records.get_mut(position)
+    }
+
+    /// Returns the raw bytes of the message of `record`
+    pub fn bytes(&self, record: &Record) -> &[u8] {
+        let chunk = &self.chunks[(record.chunk - self.first_chunk) as usize];
+        &chunk[record.offset as usize..record.offset as usize + record.len as usize]
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Record> + ExactSizeIterator {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn message(body: &[u8]) -> zbus::Message {
+        zbus::Message::signal("/org/example/Foo", "org.example.Foo", "Bar")
+            .unwrap()
+            .build(&body)
+            .unwrap()
+    }
+
+    /// Pushes a signal carrying `body`, returning its bytes
+    fn push(store: &mut MessageStore, raw_receive_index: u32, body: &[u8]) -> Vec<u8> {
+        let message = message(body);
+        store.push(
+            &message,
+            ReceiveIndex::new(raw_receive_index),
+            Timestamp::from(Duration::from_secs(raw_receive_index.into())),
+            BusType::default(),
+        );
+        message.data().to_vec()
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let mut store = MessageStore::default();
+        push(&mut store, 0, b"first");
+        push(&mut store, 1, &vec![1; CHUNK_SIZE]);
+        let third = push(&mut store, 2, b"third");
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.chunks.len(), 3);
+
+        let record = *store.get_by_receive_index(ReceiveIndex::new(2)).unwrap();
+        assert_eq!(store.get(2).unwrap().message_type, MessageType::Signal);
+        assert_eq!(store.bytes(&record), third);
+
+        assert_eq!(
+            store.pop_front().unwrap().receive_index,
+            ReceiveIndex::new(0)
+        );
+        assert_eq!(store.chunks.len(), 2);
+        assert_eq!(store.position_of(ReceiveIndex::new(0)), None);
+        assert_eq!(store.position_of(ReceiveIndex::new(2)), Some(1));
+        assert_eq!(store.bytes(&record), third);
+
+        store.pop_front();
+        store.pop_front();
+        assert_eq!(store.len(), 0);
+        assert!(store.chunks.is_empty());
+    }
+
+    #[test]
+    fn shared_chunk() {
+        let mut store = MessageStore::default();
+        push(&mut store, 0, b"first");
+        push(&mut store, 1, b"second");
+        let third = push(&mut store, 2, b"third");
+        assert_eq!(store.chunks.len(), 1);
+
+        // The chunk is only freed along with its last message
+        store.pop_front();
+        store.pop_front();
+        assert_eq!(store.chunks.len(), 1);
+        assert_eq!(store.first().unwrap().receive_index, ReceiveIndex::new(2));
+        assert_eq!(store.bytes(store.first().unwrap()), third);
+
+        let fourth = push(&mut store, 3, b"fourth");
+        assert_eq!(store.chunks.len(), 1);
+        assert_eq!(store.bytes(store.last().unwrap()), fourth);
+
+        store.pop_front();
+        store.pop_front();
+        assert!(store.chunks.is_empty());
+        assert_eq!(store.first_chunk, 1);
+
+        // New chunks are still told apart from the freed ones
+        let fifth = push(&mut store, 4, b"fifth");
+        assert_eq!(store.bytes(store.first().unwrap()), fifth);
+    }
+
+    #[test]
+    fn get_mut_by_receive_index() {
+        let mut store = MessageStore::default();
+        push(&mut store, 0, b"call");
+        push(&mut store, 1, b"signal");
+        push(&mut store, 2, b"return");
+
+        store
+            .get_mut_by_receive_index(ReceiveIndex::new(2))
+            .unwrap()
+            .associated = Some(ReceiveIndex::new(0));
+        assert_eq!(store.get(2).unwrap().associated, Some(ReceiveIndex::new(0)));
+
+        store.pop_front();
+        assert!(store
+            .get_mut_by_receive_index(ReceiveIndex::new(0))
+            .is_none());
+        assert!(store
+            .get_mut_by_receive_index(ReceiveIndex::new(3))
+            .is_none());
+
+        let record = store
+            .get_mut_by_receive_index(ReceiveIndex::new(1))
+            .unwrap();
+        record.message_tag = MessageTag::Gtk;
+        assert_eq!(record.receive_index, ReceiveIndex::new(1));
+        assert_eq!(store.get(0).unwrap().message_tag, MessageTag::Gtk);
+    }
+}
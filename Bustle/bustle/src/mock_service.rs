@@ -0,0 +1,745 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use gtk::prelude::*;
+use indexmap::IndexMap;
+use tokio::task::JoinHandle;
+use zbus::{
+    export::futures_util::StreamExt,
+    names::{BusName, WellKnownName},
+    zvariant::{self, Endian},
+};
+
+use crate::{message::Message, message_list::MessageList, message_type::MessageType, RUNTIME};
+
+/// Interfaces that zbus implements on its own and thus must not be generated
+const STANDARD_INTERFACES: &[&str] = &[
+    "org.freedesktop.DBus.Introspectable",
+    "org.freedesktop.DBus.Peer",
+    "org.freedesktop.DBus.Properties",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct MethodKey {
+    path: String,
+    interface: String,
+    member: String,
+}
+
+#[derive(Debug, Clone)]
+enum RecordedReply {
+    Return {
+        signature: String,
+        body: Vec<u8>,
+    },
+    Error {
+        name: String,
+        signature: String,
+        body: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Default)]
+struct MockMethod {
+    in_signature: String,
+    /// Replies in the order they were recorded, keyed by the little-endian
+    /// serialized call arguments
+    replies: IndexMap<Vec<u8>, Vec<RecordedReply>>,
+}
+
+/// Stand-in for a D-Bus service that answers method calls exactly as they
+/// were answered in a recording
+#[derive(Debug)]
+pub struct MockService {
+    name: WellKnownName<'static>,
+    methods: BTreeMap<MethodKey, MockMethod>,
+}
+
+impl MockService {
+    /// Collects the calls to `name` and their replies from `message_list`
+    pub fn from_message_list(message_list: &MessageList, name: &WellKnownName<'_>) -> Result<Self> {
+        let bus_names = message_list.bus_names();
+
+        let mut methods = BTreeMap::<MethodKey, MockMethod>::new();
+
+        for message in message_list.iter::<Message>() {
+            let call = message.context("Message list was mutated while collecting calls")?;
+
+            if !call.message_type().is_method_call() {
+                continue;
+            }
+
+            let Some(reply) = call.associated_message() else {
+                continue;
+            };
+
+            let is_to_name = match call.destination() {
+                Some(BusName::WellKnown(wk_name)) => wk_name == *name,
+                Some(unique_name @ BusName::Unique(_)) => bus_names
+                    .get(call.bus_type(), &unique_name)
+                    .is_some_and(|bus_name_item| {
+                        bus_name_item
+                            .wk_names(call.receive_index().into())
+                            .iter()
+                            .any(|wk_name| wk_name == name)
+                    }),
+                None => false,
+            };
+            if !is_to_name {
+                continue;
+            }
+
+            let header = call.header();
+            let key = MethodKey {
+                path: header.path().map(|p| p.to_string()).unwrap_or_default(),
+                interface: header.interface().map(|i| i.to_string()).unwrap_or_default(),
+                member: header.member().map(|m| m.to_string()).unwrap_or_default(),
+            };
+            let in_signature = signature_of(&call);
+
+            let method = methods.entry(key).or_insert_with(|| MockMethod {
+                in_signature: in_signature.clone(),
+                ..Default::default()
+            });
+            if method.in_signature != in_signature {
+                tracing::debug!(%call, "Skipping call with mismatched signature");
+                continue;
+            }
+
+            let recorded_reply = match reply.message_type() {
+                MessageType::MethodReturn => RecordedReply::Return {
+                    signature: signature_of(&reply),
+                    body: normalized_body(&reply)?,
+                },
+                MessageType::Error => RecordedReply::Error {
+                    name: reply
+                        .header()
+                        .error_name()
+                        .map(|e| e.to_string())
+                        .context("Error message has no error name")?,
+                    signature: signature_of(&reply),
+                    body: normalized_body(&reply)?,
+                },
+                other => bail!("Call is associated with a message of type `{:?}`", other),
+            };
+
+            method
+                .replies
+                .entry(normalized_body(&call)?)
+                .or_default()
+                .push(recorded_reply);
+        }
+
+        if methods.is_empty() {
+            bail!("No answered calls to `{}` were recorded", name);
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            methods,
+        })
+    }
+
+    /// Connects to `address`, owns the mocked name and answers incoming calls
+    /// until the returned handle is dropped.
+    pub async fn serve(self, address: zbus::Address) -> Result<MockServiceHandle> {
+        let name = self.name.clone();
+        let cnx = RUNTIME
+            .spawn(async move {
+                zbus::ConnectionBuilder::address(address)?
+                    .name(name)?
+                    .build()
+                    .await
+            })
+            .await
+            .context("Failed to spawn on runtime")?
+            .context("Failed to own name on bus")?;
+
+        let this = Arc::new(self);
+        let tokio_handle = RUNTIME.spawn(async move {
+            let mut n_calls = HashMap::<(MethodKey, Vec<u8>), usize>::new();
+            let mut stream = zbus::MessageStream::from(&cnx);
+
+            while let Some(res) = stream.next().await {
+                let call = match res {
+                    Ok(message) => message,
+                    Err(err) => {
+                        tracing::warn!("Failed to receive message: {:?}", err);
+                        continue;
+                    }
+                };
+
+                if call.message_type() != zbus::MessageType::MethodCall {
+                    continue;
+                }
+
+                // The caller would not expect the reply, nor would a real
+                // service send it
+                if call
+                    .header()
+                    .primary()
+                    .flags()
+                    .contains(zbus::MessageFlags::NoReplyExpected)
+                {
+                    continue;
+                }
+
+                match this.reply_to(&call, &mut n_calls) {
+                    Ok(reply) => {
+                        if let Err(err) = cnx.send(&reply).await {
+                            tracing::warn!("Failed to send reply: {:?}", err);
+                        }
+                    }
+                    Err(err) => tracing::warn!("Failed to create reply: {:?}", err),
+                }
+            }
+        });
+
+        Ok(MockServiceHandle { tokio_handle })
+    }
+
+    /// Returns the next recorded reply for `call`, or an error reply if the
+    /// call was never recorded with the same arguments
+    fn reply_to(
+        &self,
+        call: &zbus::Message,
+        n_calls: &mut HashMap<(MethodKey, Vec<u8>), usize>,
+    ) -> Result<zbus::Message> {
+        let header = call.header();
+        let key = MethodKey {
+            path: header.path().map(|p| p.to_string()).unwrap_or_default(),
+            interface: header.interface().map(|i| i.to_string()).unwrap_or_default(),
+            member: header.member().map(|m| m.to_string()).unwrap_or_default(),
+        };
+
+        let Some(method) = self.methods.get(&key).or_else(|| {
+            // Callers are allowed to leave out the interface
+            header.interface().is_none().then(|| {
+                self.methods
+                    .iter()
+                    .find(|(other, _)| other.path == key.path && other.member == key.member)
+                    .map(|(_, method)| method)
+            })?
+        }) else {
+            return Ok(zbus::Message::method_error(call, "org.freedesktop.DBus.Error.UnknownMethod")?
+                .build(&(format!("No call to `{}` was recorded", key.member),))?);
+        };
+
+        let args = normalized_zbus_body(call)?;
+        let Some(replies) = method.replies.get(&args) else {
+            return Ok(zbus::Message::method_error(call, "org.freedesktop.DBus.Error.InvalidArgs")?
+                .build(&(format!(
+                    "No call to `{}` was recorded with these arguments",
+                    key.member
+                ),))?);
+        };
+
+        // Answer in the recorded order, then keep repeating the last reply
+        let n_calls = n_calls.entry((key, args)).or_default();
+        let reply = &replies[(*n_calls).min(replies.len() - 1)];
+        *n_calls += 1;
+
+        let message = match reply {
+            RecordedReply::Return { signature, body } => unsafe {
+                // SAFETY: The body was serialized from a message with the same signature
+                zbus::Message::method_reply(call)?.build_raw_body(
+                    body,
+                    signature.as_str(),
+                    Vec::new(),
+                )?
+            },
+            RecordedReply::Error {
+                name,
+                signature,
+                body,
+            } => unsafe {
+                // SAFETY: The body was serialized from a message with the same signature
+                zbus::Message::method_error(call, name.as_str())?.build_raw_body(
+                    body,
+                    signature.as_str(),
+                    Vec::new(),
+                )?
+            },
+        };
+        Ok(message)
+    }
+
+    /// Generates Rust source implementing the recorded responses with
+    /// `#[zbus::interface]`
+    ///
+    /// The standard interfaces implemented by zbus itself, such as
+    /// `org.freedesktop.DBus.Properties`, are left out.
+    pub fn to_rust_source(&self) -> Result<String> {
+        let mut src = String::new();
+
+        writeln!(src, "//! Mock of `{}` generated by Bustle", self.name)?;
+        writeln!(src, "//!")?;
+        writeln!(
+            src,
+            "//! Calls are answered with the replies recorded for the same arguments, in"
+        )?;
+        writeln!(
+            src,
+            "//! the recorded order. Once exhausted, the last reply is repeated."
+        )?;
+        writeln!(src, "//!")?;
+        writeln!(src, "//! Requires the `zbus` (4.x) and `serde` crates.")?;
+        writeln!(src)?;
+        writeln!(src, "use std::sync::atomic::{{AtomicUsize, Ordering}};")?;
+        writeln!(src)?;
+        writeln!(src, "use zbus::zvariant;")?;
+        writeln!(src)?;
+        src.push_str(RUST_SOURCE_PRELUDE);
+
+        let mut objects = BTreeMap::<(&str, &str), Vec<(&str, &MockMethod)>>::new();
+        for (key, method) in &self.methods {
+            if key.interface.is_empty() || STANDARD_INTERFACES.contains(&key.interface.as_str()) {
+                continue;
+            }
+            objects
+                .entry((&key.path, &key.interface))
+                .or_default()
+                .push((&key.member, method));
+        }
+
+        let mut struct_names = Vec::new();
+        for (index, ((path, interface), methods)) in objects.iter().enumerate() {
+            let struct_name = format!(
+                "{}{}",
+                interface.rsplit('.').next().map(to_camel_case).unwrap_or_default(),
+                index
+            );
+
+            let n_keys = methods.iter().map(|(_, m)| m.replies.len()).sum::<usize>();
+
+            writeln!(src)?;
+            writeln!(src, "/// `{}` at `{}`", interface, path)?;
+            writeln!(src, "pub struct {} {{", struct_name)?;
+            writeln!(src, "    n_calls: [AtomicUsize; {}],", n_keys)?;
+            writeln!(src, "}}")?;
+            writeln!(src)?;
+            writeln!(src, "#[zbus::interface(name = \"{}\")]", interface)?;
+            writeln!(src, "impl {} {{", struct_name)?;
+
+            let mut counter_index = 0;
+            for (member, method) in methods {
+                write_rust_method(&mut src, member, method, &mut counter_index)?;
+            }
+
+            writeln!(src, "}}")?;
+
+            struct_names.push((struct_name, *path, n_keys));
+        }
+
+        writeln!(src)?;
+        writeln!(
+            src,
+            "/// Serves the mock on `connection` and requests `{}`",
+            self.name
+        )?;
+        writeln!(
+            src,
+            "pub async fn serve(connection: &zbus::Connection) -> zbus::Result<()> {{"
+        )?;
+        writeln!(src, "    let object_server = connection.object_server();")?;
+        for (struct_name, path, n_keys) in &struct_names {
+            writeln!(src, "    object_server")?;
+            writeln!(src, "        .at(")?;
+            writeln!(src, "            \"{}\",", path)?;
+            writeln!(src, "            {} {{", struct_name)?;
+            writeln!(
+                src,
+                "                n_calls: std::array::from_fn::<_, {}, _>(|_| AtomicUsize::new(0)),",
+                n_keys
+            )?;
+            writeln!(src, "            }},")?;
+            writeln!(src, "        )")?;
+            writeln!(src, "        .await?;")?;
+        }
+        writeln!(src, "    connection.request_name(\"{}\").await?;", self.name)?;
+        writeln!(src, "    Ok(())")?;
+        writeln!(src, "}}")?;
+
+        Ok(src)
+    }
+}
+
+/// Keeps a mock service running until dropped
+#[derive(Debug)]
+pub struct MockServiceHandle {
+    tokio_handle: JoinHandle<()>,
+}
+
+impl Drop for MockServiceHandle {
+    fn drop(&mut self) {
+        self.tokio_handle.abort();
+    }
+}
+
+const RUST_SOURCE_PRELUDE: &str = r#"fn context() -> zvariant::serialized::Context {
+    zvariant::serialized::Context::new_dbus(zvariant::LE, 0)
+}
+
+#[derive(Debug)]
+pub struct RecordedError {
+    name: &'static str,
+    description: String,
+}
+
+impl zbus::DBusError for RecordedError {
+    fn create_reply(&self, call: &zbus::message::Header<'_>) -> zbus::Result<zbus::Message> {
+        #[allow(deprecated)]
+        zbus::message::Builder::error(call, self.name)?.build(&(self.description.as_str(),))
+    }
+
+    fn name(&self) -> zbus::names::ErrorName<'_> {
+        zbus::names::ErrorName::from_static_str_unchecked(self.name)
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some(&self.description)
+    }
+}
+
+impl From<zbus::Error> for RecordedError {
+    fn from(err: zbus::Error) -> Self {
+        Self {
+            name: "org.freedesktop.DBus.Error.Failed",
+            description: err.to_string(),
+        }
+    }
+}
+
+fn args_bytes<T: serde::Serialize + zvariant::DynamicType>(args: &T) -> Vec<u8> {
+    zvariant::to_bytes(context(), args)
+        .expect("arguments must be serializable")
+        .to_vec()
+}
+
+fn reply<T: serde::de::DeserializeOwned + zvariant::Type>(
+    body: &'static [u8],
+) -> Result<T, RecordedError> {
+    let (value, _) = zvariant::serialized::Data::new(body, context()).deserialize()?;
+    Ok(value)
+}
+
+fn error<T>(name: &'static str, description: &str) -> Result<T, RecordedError> {
+    Err(RecordedError {
+        name,
+        description: description.to_owned(),
+    })
+}
+
+fn no_recorded_reply<T>(member: &str) -> Result<T, RecordedError> {
+    Err(RecordedError {
+        name: "org.freedesktop.DBus.Error.InvalidArgs",
+        description: format!("No call to `{member}` was recorded with these arguments"),
+    })
+}
+"#;
+
+fn write_rust_method(
+    src: &mut String,
+    member: &str,
+    method: &MockMethod,
+    counter_index: &mut usize,
+) -> Result<()> {
+    let Some(in_types) = rust_types_for_signature(&method.in_signature) else {
+        writeln!(
+            src,
+            "    // `{}` skipped: unsupported input signature `{}`",
+            member, method.in_signature
+        )?;
+        *counter_index += method.replies.len();
+        return Ok(());
+    };
+
+    let out_signature = method
+        .replies
+        .values()
+        .flatten()
+        .find_map(|reply| match reply {
+            RecordedReply::Return { signature, .. } => Some(signature.as_str()),
+            RecordedReply::Error { .. } => None,
+        })
+        .unwrap_or_default();
+    let Some(out_types) = rust_types_for_signature(out_signature) else {
+        writeln!(
+            src,
+            "    // `{}` skipped: unsupported output signature `{}`",
+            member, out_signature
+        )?;
+        *counter_index += method.replies.len();
+        return Ok(());
+    };
+    let out_type = match out_types.as_slice() {
+        [] => "()".to_owned(),
+        [single] => single.clone(),
+        multiple => format!("({})", multiple.join(", ")),
+    };
+
+    let params = in_types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!(", arg{}: {}", i, ty))
+        .collect::<String>();
+    let args_tuple = (0..in_types.len())
+        .map(|i| format!("&arg{},", i))
+        .collect::<String>();
+
+    writeln!(src)?;
+    writeln!(src, "    #[zbus(name = \"{}\")]", member)?;
+    writeln!(
+        src,
+        "    async fn {}(&self{}) -> Result<{}, RecordedError> {{",
+        to_snake_case(member),
+        params,
+        out_type
+    )?;
+    writeln!(src, "        let args = args_bytes(&({}));", args_tuple)?;
+    writeln!(src, "        match args.as_slice() {{")?;
+
+    for (args, replies) in &method.replies {
+        writeln!(src, "            {} => {{", byte_string_literal(args))?;
+        writeln!(
+            src,
+            "                let n_calls = self.n_calls[{}].fetch_add(1, Ordering::Relaxed);",
+            counter_index
+        )?;
+        writeln!(src, "                match n_calls.min({}) {{", replies.len() - 1)?;
+        for (i, reply) in replies.iter().enumerate() {
+            let pattern = if i == replies.len() - 1 {
+                "_".to_owned()
+            } else {
+                i.to_string()
+            };
+            match reply {
+                RecordedReply::Return { signature, body } if signature == out_signature => {
+                    writeln!(
+                        src,
+                        "                    {} => reply({}),",
+                        pattern,
+                        byte_string_literal(body)
+                    )?;
+                }
+                RecordedReply::Return { signature, .. } => {
+                    writeln!(
+                        src,
+                        "                    {} => error(\"org.freedesktop.DBus.Error.Failed\", \"recorded reply has a different signature `{}`\"),",
+                        pattern, signature
+                    )?;
+                }
+                RecordedReply::Error { name, body, .. } => {
+                    let description = error_description(body).unwrap_or_default();
+                    writeln!(
+                        src,
+                        "                    {} => error({:?}, {:?}),",
+                        pattern, name, description
+                    )?;
+                }
+            }
+        }
+        writeln!(src, "                }}")?;
+        writeln!(src, "            }}")?;
+        *counter_index += 1;
+    }
+
+    writeln!(src, "            _ => no_recorded_reply({:?}),", member)?;
+    writeln!(src, "        }}")?;
+    writeln!(src, "    }}")?;
+
+    Ok(())
+}
+
+fn signature_of(message: &Message) -> String {
+    message
+        .header()
+        .signature()
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+fn normalized_body(message: &Message) -> Result<Vec<u8>> {
+    normalized_zbus_body(&message.to_event().message)
+}
+
+/// Returns the body serialized in little-endian, so that recordings from
+/// big-endian peers can be compared with the calls we receive
+fn normalized_zbus_body(message: &zbus::Message) -> Result<Vec<u8>> {
+    let body = message.body();
+    if body.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let structure = body
+        .deserialize::<zvariant::OwnedStructure>()
+        .context("Failed to deserialize body")?;
+    let ctxt = zvariant::serialized::Context::new_dbus(Endian::Little, 0);
+    let data = zvariant::to_bytes(ctxt, &structure).context("Failed to serialize body")?;
+    Ok(data.bytes().to_vec())
+}
+
+/// Returns the message text of an error body, i.e. its first string argument
+fn error_description(body: &[u8]) -> Option<String> {
+    let ctxt = zvariant::serialized::Context::new_dbus(Endian::Little, 0);
+    zvariant::serialized::Data::new(body, ctxt)
+        .deserialize::<(String,)>()
+        .ok()
+        .map(|((description,), _)| description)
+}
+
+fn byte_string_literal(bytes: &[u8]) -> String {
+    let mut ret = String::from("b\"");
+    for byte in bytes {
+        write!(ret, "\\x{:02x}", byte).unwrap();
+    }
+    ret.push('"');
+    ret
+}
+
+fn to_camel_case(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut ret = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                ret.push('_');
+            }
+            ret.extend(c.to_lowercase());
+        } else {
+            ret.push(c);
+        }
+    }
+    match ret.as_str() {
+        // These cannot be raw identifiers
+        "self" | "super" | "crate" => ret + "_",
+        "as" | "async" | "await" | "break" | "const" | "continue" | "dyn" | "else" | "enum"
+        | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match"
+        | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static" | "struct" | "trait"
+        | "true" | "type" | "unsafe" | "use" | "where" | "while" | "abstract" | "become"
+        | "box" | "do" | "final" | "gen" | "macro" | "override" | "priv" | "try" | "typeof"
+        | "unsized" | "virtual" | "yield" => format!("r#{}", ret),
+        _ => ret,
+    }
+}
+
+/// Maps every complete type in `signature` to a Rust type that zbus can
+/// (de)serialize with the same signature
+///
+/// Returns `None` if the signature is invalid.
+fn rust_types_for_signature(signature: &str) -> Option<Vec<String>> {
+    let mut chars = signature.chars().peekable();
+    let mut types = Vec::new();
+    while chars.peek().is_some() {
+        types.push(next_rust_type(&mut chars)?);
+    }
+    Some(types)
+}
+
+fn next_rust_type(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let ty = match chars.next()? {
+        'y' => "u8".to_owned(),
+        'b' => "bool".to_owned(),
+        'n' => "i16".to_owned(),
+        'q' => "u16".to_owned(),
+        'i' => "i32".to_owned(),
+        'u' => "u32".to_owned(),
+        'x' => "i64".to_owned(),
+        't' => "u64".to_owned(),
+        'd' => "f64".to_owned(),
+        's' => "String".to_owned(),
+        'o' => "zvariant::OwnedObjectPath".to_owned(),
+        'g' => "zvariant::OwnedSignature".to_owned(),
+        'h' => "zvariant::OwnedFd".to_owned(),
+        'v' => "zvariant::OwnedValue".to_owned(),
+        'a' => {
+            if chars.peek() == Some(&'{') {
+                chars.next();
+                // Entries of a `BTreeMap` serialize in the same order as the
+                // recorded dictionaries were normalized in, which needs keys
+                // with a total order
+                if matches!(chars.peek(), Some('d' | 'h')) {
+                    return None;
+                }
+                let key = next_rust_type(chars)?;
+                let value = next_rust_type(chars)?;
+                if chars.next()? != '}' {
+                    return None;
+                }
+                format!("std::collections::BTreeMap<{}, {}>", key, value)
+            } else {
+                format!("Vec<{}>", next_rust_type(chars)?)
+            }
+        }
+        '(' => {
+            let mut fields = Vec::new();
+            while chars.peek() != Some(&')') {
+                fields.push(next_rust_type(chars)?);
+            }
+            chars.next();
+            if fields.is_empty() {
+                return None;
+            }
+            format!("({},)", fields.join(", "))
+        }
+        _ => return None,
+    };
+    Some(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_types_basic() {
+        assert_eq!(rust_types_for_signature(""), Some(vec![]));
+        assert_eq!(
+            rust_types_for_signature("su"),
+            Some(vec!["String".to_owned(), "u32".to_owned()])
+        );
+    }
+
+    #[test]
+    fn rust_types_containers() {
+        assert_eq!(
+            rust_types_for_signature("a{sv}ao"),
+            Some(vec![
+                "std::collections::BTreeMap<String, zvariant::OwnedValue>".to_owned(),
+                "Vec<zvariant::OwnedObjectPath>".to_owned()
+            ])
+        );
+        assert_eq!(
+            rust_types_for_signature("a(sb)"),
+            Some(vec!["Vec<(String, bool,)>".to_owned()])
+        );
+    }
+
+    #[test]
+    fn rust_types_invalid() {
+        assert_eq!(rust_types_for_signature("a"), None);
+        assert_eq!(rust_types_for_signature("a{s"), None);
+        assert_eq!(rust_types_for_signature("a{dv}"), None);
+        assert_eq!(rust_types_for_signature("()"), None);
+        assert_eq!(rust_types_for_signature("z"), None);
+    }
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(to_snake_case("GetDisplayDevice"), "get_display_device");
+        assert_eq!(to_snake_case("Type"), "r#type");
+        assert_eq!(to_snake_case("Self"), "self_");
+        assert_eq!(to_camel_case("login1"), "Login1");
+    }
+}
@@ -1,22 +1,58 @@
-use std::{fmt, path::Path, process::Stdio};
+use std::{
+    ffi::OsString,
+    fmt,
+    future::Future,
+    io, mem,
+    os::{fd::AsFd, unix::fs::MetadataExt},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
-use gtk::{gio, glib};
+use gtk::{
+    glib::{self, closure_local},
+    prelude::*,
+    subclass::prelude::*,
+};
 use once_cell::sync::Lazy;
-use pcap_file::pcap::PcapPacket;
+use pcap_file::{
+    pcap::{PcapPacket, PcapParser},
+    PcapError,
+};
 use tokio::{
-    io::{AsyncReadExt, BufReader},
+    fs::File,
+    io::{AsyncRead, AsyncReadExt},
     process::{Child, Command},
-    sync::{mpsc, oneshot},
+    sync::{mpsc, oneshot, Notify},
     task::JoinHandle,
 };
-use zbus::export::futures_util::StreamExt;
-use zbus::zvariant;
+use zbus::{
+    address::transport::{Transport, Unix, UnixSocket},
+    export::futures_util::{future, StreamExt},
+    zvariant,
+};
 
-use crate::{timestamp::Timestamp, RUNTIME};
+use crate::{bus_type::BusType, proxy, timestamp::Timestamp, RUNTIME};
 
 static IS_RUNNING_IN_FLATPAK: Lazy<bool> = Lazy::new(|| Path::new("/.flatpak-info").exists());
 
+/// Interval at which a followed file is checked for new packets
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of bytes at the start of a followed file that are checked to tell
+/// whether it was rewritten, which covers the first packet and its timestamp
+const FOLLOW_PREFIX_LEN: usize = 1024;
+
+/// Maximum number of events waiting for the UI thread, after which new
+/// events are dropped
+const MAX_QUEUED_EVENTS: usize = 100_000;
+
+#[derive(Debug)]
 pub struct Event {
     pub message: zbus::Message,
     pub timestamp: Timestamp,
@@ -52,252 +88,863 @@ impl Cancelled {
     }
 }
 
+/// Why reading a PCAP stream stopped when its writer closed it after a
+/// whole packet
 #[derive(Debug)]
-pub struct Monitor(Inner);
+struct EndOfStream;
+
+impl fmt::Display for EndOfStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Reached end of PCAP stream")
+    }
+}
+
+/// Counts of the events that could not be delivered to the UI thread as soon
+/// as they were received
+#[derive(Debug, Default)]
+pub struct DeliveryStats {
+    n_delayed: AtomicU64,
+    n_dropped: AtomicU64,
+}
+
+impl DeliveryStats {
+    /// Returns the number of events that waited for the UI thread to handle
+    /// a previous batch
+    pub fn n_delayed(&self) -> u64 {
+        self.n_delayed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of events that were dropped because too many were
+    /// waiting for the UI thread
+    pub fn n_dropped(&self) -> u64 {
+        self.n_dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Events received on the tokio side, waiting to be delivered to the UI
+/// thread in a single batch
+struct EventQueue {
+    events: Mutex<Vec<Event>>,
+    notify: Notify,
+    is_closed: AtomicBool,
+    stats: Arc<DeliveryStats>,
+}
+
+impl EventQueue {
+    fn new(stats: Arc<DeliveryStats>) -> Self {
+        Self {
+            events: Mutex::default(),
+            notify: Notify::new(),
+            is_closed: AtomicBool::new(false),
+            stats,
+        }
+    }
+
+    /// Queues `event`, or drops it if the UI thread is too far behind
+    fn push(&self, event: Event) {
+        let mut events = self.events.lock().unwrap();
+
+        if events.len() >= MAX_QUEUED_EVENTS {
+            self.stats.n_dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        events.push(event);
+        drop(events);
+
+        self.notify.notify_one();
+    }
+
+    /// Signals that no more events will be pushed, so `forward` returns once
+    /// the queued ones are delivered
+    fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+        self.notify.notify_one();
+    }
+
+    /// Runs `read`, which pushes events, while delivering them to `tx`, and
+    /// returns why reading stopped
+    async fn run(
+        &self,
+        read: impl Future<Output = anyhow::Error>,
+        tx: mpsc::Sender<Vec<Event>>,
+    ) -> anyhow::Error {
+        let read = async {
+            let err = read.await;
+            self.close();
+            err
+        };
+        let (err, ()) = future::join(read, self.forward(tx)).await;
+        err
+    }
+
+    /// Delivers the queued events to `tx` in batches, with at most one batch
+    /// waiting to be handled by the UI thread at a time
+    async fn forward(&self, tx: mpsc::Sender<Vec<Event>>) {
+        loop {
+            self.notify.notified().await;
+
+            let permit = match tx.try_reserve() {
+                Ok(permit) => permit,
+                Err(mpsc::error::TrySendError::Full(())) => {
+                    let Ok(permit) = tx.reserve().await else {
+                        break;
+                    };
 
+                    // Everything that got queued while waiting is late
+                    let n_delayed = self.events.lock().unwrap().len();
+                    self.stats
+                        .n_delayed
+                        .fetch_add(n_delayed as u64, Ordering::Relaxed);
+
+                    permit
+                }
+                Err(mpsc::error::TrySendError::Closed(())) => break,
+            };
+
+            let events = mem::take(&mut *self.events.lock().unwrap());
+            if !events.is_empty() {
+                permit.send(events);
+            }
+
+            if self.is_closed.load(Ordering::Relaxed) && self.events.lock().unwrap().is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "BustleMonitorState")]
+pub enum MonitorState {
+    #[default]
+    New,
+    Starting,
+    Running,
+    Stopping,
+    Stopped,
+}
+
+/// What is monitored
 #[derive(Debug)]
-enum Inner {
-    System {
-        command: Command,
-        child: Option<Child>,
-        tokio_handle: Option<JoinHandle<()>>,
-        handle: Option<glib::JoinHandle<()>>,
-    },
-    Session {
-        tokio_handle: Option<JoinHandle<()>>,
-        handle: Option<glib::JoinHandle<()>>,
+enum Source {
+    System,
+    Session,
+    Address(zbus::Address),
+    /// PCAP stream written to the standard input of this process
+    Stdin,
+    /// PCAP stream written to the named pipe at the path
+    Fifo(PathBuf),
+    /// PCAP file at the path, including what is appended to it later
+    File(PathBuf),
+    /// PCAP stream written to the standard output of the command, given as
+    /// its arguments
+    Command(Vec<OsString>),
+    /// Clients connect to the socket at `path`, which forwards their traffic
+    /// to `target`
+    Proxy {
+        path: PathBuf,
+        target: zbus::Address,
     },
 }
 
-impl Drop for Monitor {
-    fn drop(&mut self) {
-        match &mut self.0 {
-            Inner::System {
-                child,
-                tokio_handle,
-                handle,
-                ..
-            } => {
-                if let Some(mut child) = child.take() {
-                    glib::spawn_future(async move {
-                        if let Err(err) = child.kill().await {
-                            tracing::warn!("Failed to kill child process: {:?}", err)
-                        } else {
-                            tracing::debug!("Killed child process")
-                        }
-                    });
+impl Source {
+    /// Returns a connection that became a monitor of the bus, or None if
+    /// `dbus-monitor` has to be run instead
+    ///
+    /// The system bus is monitored in-process if its policy allows us to, so
+    /// that `pkexec` does not prompt for a password. Any failure to connect
+    /// or to become a monitor falls back to `dbus-monitor`, which reports
+    /// whatever is actually wrong with the bus.
+    async fn monitor_connection(&self) -> Result<Option<zbus::Connection>> {
+        match self {
+            Self::Session => become_monitor(zbus::Connection::session()).await.map(Some),
+            Self::System => match become_monitor(zbus::Connection::system()).await {
+                Ok(cnx) => Ok(Some(cnx)),
+                Err(err) => {
+                    tracing::debug!("Falling back to dbus-monitor: {:?}", err);
+                    Ok(None)
                 }
+            },
+            Self::Address(_) | Self::Command(_) => Ok(None),
+            Self::Stdin | Self::Fifo(_) | Self::File(_) | Self::Proxy { .. } => {
+                unreachable!("{self:?} is not a bus")
+            }
+        }
+    }
 
-                if let Some(tokio_handle) = tokio_handle.take() {
-                    tokio_handle.abort();
-                }
+    /// Returns the command writing the PCAP stream to read to its standard
+    /// output
+    fn command(&self) -> Command {
+        let mut command = match self {
+            Self::System => {
+                let mut command = dbus_monitor_command();
+                command.arg("--system");
+                command
+            }
+            Self::Address(address) => {
+                let mut command = dbus_monitor_command();
+                command.arg("--address").arg(address.to_string());
+                command
+            }
+            Self::Command(argv) => host_command(argv),
+            Self::Session => unreachable!("session bus is always monitored in-process"),
+            Self::Stdin | Self::Fifo(_) | Self::File(_) | Self::Proxy { .. } => {
+                unreachable!("{self:?} is not read from a command")
+            }
+        };
+        command.stdout(Stdio::piped());
+        command
+    }
 
-                if let Some(handle) = handle.take() {
-                    handle.abort();
-                }
+    /// Whether the end of its PCAP stream is the normal end of the recording,
+    /// as opposed to `dbus-monitor` going away
+    fn can_end(&self) -> bool {
+        matches!(self, Self::Stdin | Self::Fifo(_) | Self::Command(_))
+    }
+}
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use glib::subclass::Signal;
+
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::Monitor)]
+    pub struct Monitor {
+        #[property(get, builder(MonitorState::default()))]
+        pub(super) state: Cell<MonitorState>,
+
+        pub(super) source: OnceCell<Source>,
+        pub(super) child: RefCell<Option<Child>>,
+        pub(super) tokio_handle: RefCell<Option<JoinHandle<()>>>,
+        pub(super) handle: RefCell<Option<glib::JoinHandle<()>>>,
+        pub(super) delivery_stats: Arc<DeliveryStats>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Monitor {
+        const NAME: &'static str = "BustleMonitor";
+        type Type = super::Monitor;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for Monitor {
+        fn dispose(&self) {
+            if let Some(mut child) = self.child.take() {
+                glib::spawn_future(async move {
+                    if let Err(err) = child.kill().await {
+                        tracing::warn!("Failed to kill child process: {:?}", err)
+                    } else {
+                        tracing::debug!("Killed child process")
+                    }
+                });
             }
-            Inner::Session {
-                tokio_handle,
-                handle,
-                ..
-            } => {
-                if let Some(tokio_handle) = tokio_handle.take() {
-                    tokio_handle.abort();
-                }
 
-                if let Some(handle) = handle.take() {
-                    handle.abort();
-                }
+            if let Some(tokio_handle) = self.tokio_handle.take() {
+                tokio_handle.abort();
             }
+
+            if let Some(handle) = self.handle.take() {
+                handle.abort();
+            }
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("error")
+                        .param_types([String::static_type()])
+                        .build(),
+                    Signal::builder("finished").build(),
+                ]
+            });
+
+            SIGNALS.as_ref()
         }
     }
 }
 
+glib::wrapper! {
+    /// Records the messages of a bus, going from `New` to `Stopped` through
+    /// `Starting`, `Running` and `Stopping`
+    ///
+    /// If monitoring stops while running, without `stop` being called,
+    /// `finished` is emitted when the written PCAP stream was closed after a
+    /// whole packet, and `error` otherwise.
+    pub struct Monitor(ObjectSubclass<imp::Monitor>);
+}
+
 impl Monitor {
+    fn new(source: Source) -> Self {
+        let this = glib::Object::new::<Self>();
+        this.imp().source.set(source).unwrap();
+        this
+    }
+
     pub fn system() -> Self {
-        let mut command = dbus_monitor_command();
-        command
-            .stdout(Stdio::piped())
-            .arg("--binary")
-            .arg("--system");
-
-        Self(Inner::System {
-            command,
-            child: None,
-            tokio_handle: None,
-            handle: None,
-        })
+        Self::new(Source::System)
     }
 
     pub fn session() -> Self {
-        Self(Inner::Session {
-            tokio_handle: None,
-            handle: None,
-        })
+        Self::new(Source::Session)
     }
 
     pub fn address(address: zbus::Address) -> Self {
-        let mut command = dbus_monitor_command();
-        command
-            .stdout(Stdio::piped())
-            .arg("--binary")
-            .arg("--address")
-            .arg(address.to_string());
-
-        Self(Inner::System {
-            command,
-            child: None,
-            tokio_handle: None,
-            handle: None,
+        Self::new(Source::Address(address))
+    }
+
+    /// Reads the PCAP stream written to the standard input of this process
+    pub fn stdin() -> Self {
+        Self::new(Source::Stdin)
+    }
+
+    /// Reads the PCAP stream written to the named pipe at `path`, waiting for
+    /// a writer to open it
+    pub fn fifo(path: PathBuf) -> Self {
+        Self::new(Source::Fifo(path))
+    }
+
+    /// Reads the PCAP file at `path` and keeps reading what is appended to
+    /// it, like `tail -F`
+    pub fn follow(path: PathBuf) -> Self {
+        Self::new(Source::File(path))
+    }
+
+    /// Runs the command given by `argv` and reads the PCAP stream it writes
+    /// to its standard output, e.g., `ssh host dbus-monitor --pcap`
+    pub fn command(argv: Vec<OsString>) -> Self {
+        debug_assert!(!argv.is_empty());
+        Self::new(Source::Command(argv))
+    }
+
+    /// Records the traffic of the clients that connect to `proxy_address`
+    /// instead of `target`, which can be a bus or a peer
+    pub fn proxy(target: zbus::Address) -> Self {
+        Self::new(Source::Proxy {
+            path: proxy::new_socket_path(),
+            target,
         })
     }
 
-    pub async fn start(&mut self, message_cb: impl Fn(Event) + 'static) -> Result<()> {
-        match &mut self.0 {
-            Inner::System {
-                command,
-                child,
-                tokio_handle,
-                handle,
-            } => {
-                let enter_guard = RUNTIME.enter();
-                let mut spawned_child = command.spawn().context("Failed to spawn command")?;
-                drop(enter_guard);
+    /// Returns the bus the monitored messages are from
+    ///
+    /// Only the system bus is told apart, the messages of every other source
+    /// are on the default bus. Hence a recording must not have more than one
+    /// monitor of such sources.
+    pub fn bus_type(&self) -> BusType {
+        match self.imp().source.get().unwrap() {
+            Source::System => BusType::System,
+            _ => BusType::Session,
+        }
+    }
 
-                let stdout = spawned_child.stdout.take().expect("Child must have stdout");
+    /// Returns the address clients must connect to, if this is a proxy
+    pub fn proxy_address(&self) -> Option<zbus::Address> {
+        match self.imp().source.get().unwrap() {
+            Source::Proxy { path, .. } => Some(zbus::Address::new(Transport::Unix(Unix::new(
+                UnixSocket::File(path.clone()),
+            )))),
+            _ => None,
+        }
+    }
 
-                let (tx, mut rx) = mpsc::channel(10);
+    pub fn connect_error<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &str) + 'static,
+    {
+        self.connect_closure(
+            "error",
+            false,
+            closure_local!(|obj: &Self, message: &str| {
+                f(obj, message);
+            }),
+        )
+    }
 
-                let (first_read_tx, first_read_rx) = oneshot::channel();
-                let mut first_read_tx = Some(first_read_tx);
+    pub fn connect_finished<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_closure(
+            "finished",
+            false,
+            closure_local!(|obj: &Self| {
+                f(obj);
+            }),
+        )
+    }
 
-                tokio_handle.replace(RUNTIME.spawn(async move {
-                    let mut reader = BufReader::new(stdout);
+    /// Returns how many events were not delivered as soon as they were
+    /// received so far
+    pub fn delivery_stats(&self) -> &DeliveryStats {
+        &self.imp().delivery_stats
+    }
 
-                    loop {
-                        // Initialize space for the header
-                        let mut message_buf = vec![0u8; 16];
+    /// Starts monitoring, calling `messages_cb` with batches of events in
+    /// the order they were received
+    pub async fn start(&self, messages_cb: impl Fn(Vec<Event>) + 'static) -> Result<()> {
+        debug_assert_eq!(self.state(), MonitorState::New);
 
-                        // Read the header
-                        let ret = reader.read_exact(&mut message_buf).await;
+        self.set_state(MonitorState::Starting);
 
-                        if let Some(first_msg_tx) = first_read_tx.take() {
-                            let _ = first_msg_tx.send(());
-                        }
+        if let Err(err) = self.start_inner(messages_cb).await {
+            self.set_state(MonitorState::Stopped);
+            return Err(err);
+        }
 
-                        if let Err(err) = ret {
-                            tracing::warn!("Failed to read header bytes: {:?}", err);
-                            break;
-                        }
+        self.set_state(MonitorState::Running);
 
-                        let timestamp = Timestamp::now();
+        Ok(())
+    }
 
-                        let bytes_needed = match gio::DBusMessage::bytes_needed(&message_buf) {
-                            Ok(n_needed) => n_needed,
-                            Err(err) => {
-                                tracing::warn!("Failed to get bytes needed: {:?}", err);
-                                break;
-                            }
-                        };
-                        assert!(bytes_needed >= 16);
+    /// Stops monitoring, if it is running
+    pub fn stop(&self) {
+        let imp = self.imp();
 
-                        // Make space for the body
-                        message_buf.resize(bytes_needed as usize, 0);
+        if self.state() != MonitorState::Running {
+            return;
+        }
 
-                        // Read the body
-                        if let Err(err) = reader.read_exact(&mut message_buf[16..]).await {
-                            tracing::warn!("Failed to read body bytes: {:?}", err);
-                            break;
-                        }
+        self.set_state(MonitorState::Stopping);
 
-                        match Event::from_bytes(message_buf, timestamp) {
-                            Ok(event) => {
-                                let _ = tx.send(event).await;
-                            }
-                            Err(err) => {
-                                tracing::warn!("Failed to create event from bytes: {:?}", err);
-                            }
-                        }
-                    }
-                }));
-
-                // Wait for the first read before we check for child status to ensure that the
-                // child already exited on error.
-                first_read_rx.await.expect("rx unexpectedly closed");
-
-                if let Some(exit_status) =
-                    spawned_child.try_wait().context("Failed to wait child")?
-                {
-                    debug_assert!(!exit_status.success(), "child must only exit on error");
-
-                    let err = anyhow!(
-                        "Child exited with status `{:?}` and code `{:?}`",
-                        exit_status,
-                        exit_status.code()
-                    );
-                    match exit_status.code() {
-                        Some(126) => {
-                            return Err(err.context(Cancelled::new(
-                                "User dismissed polkit authorization dialog",
-                            )))
-                        }
-                        _ => return Err(err),
-                    }
+        if let Some(tokio_handle) = imp.tokio_handle.take() {
+            tokio_handle.abort();
+        }
+
+        if let Some(handle) = imp.handle.take() {
+            handle.abort();
+        }
+
+        if let Some(mut child) = imp.child.take() {
+            let this = self.clone();
+            glib::spawn_future_local(async move {
+                if let Err(err) = child.kill().await {
+                    tracing::warn!("Failed to kill child process: {:?}", err)
                 } else {
-                    child.replace(spawned_child);
+                    tracing::debug!("Killed child process")
                 }
 
-                handle.replace(glib::spawn_future_local(async move {
-                    while let Some(event) = rx.recv().await {
-                        message_cb(event);
+                this.set_state(MonitorState::Stopped);
+            });
+        } else {
+            self.set_state(MonitorState::Stopped);
+        }
+    }
+
+    async fn start_inner(&self, messages_cb: impl Fn(Vec<Event>) + 'static) -> Result<()> {
+        let imp = self.imp();
+
+        let queue = Arc::new(EventQueue::new(Arc::clone(&imp.delivery_stats)));
+        let (tx, mut rx) = mpsc::channel(1);
+        let (end_tx, end_rx) = oneshot::channel();
+
+        let source = imp.source.get().unwrap();
+        let tokio_handle = if let Source::Stdin | Source::Fifo(_) = source {
+            let fifo_path = match source {
+                Source::Fifo(path) => Some(path.clone()),
+                _ => None,
+            };
+            RUNTIME.spawn(async move {
+                let read = async {
+                    match open_pcap_input(fifo_path).await {
+                        Ok(input) => read_pcap_stream(input, &queue, None).await,
+                        Err(err) => err,
+                    }
+                };
+                let err = queue.run(read, tx).await;
+                let _ = end_tx.send(err);
+            })
+        } else if let Source::File(path) = source {
+            let path = path.clone();
+            RUNTIME.spawn(async move {
+                let err = queue.run(read_followed_file(path, &queue), tx).await;
+                let _ = end_tx.send(err);
+            })
+        } else if let Source::Proxy { path, target } = source {
+            let enter_guard = RUNTIME.enter();
+            let listener = proxy::Listener::bind(path)?;
+            drop(enter_guard);
+
+            let target = target.clone();
+            RUNTIME.spawn(async move {
+                let pushed_queue = Arc::clone(&queue);
+                let on_event = move |event: Event| pushed_queue.push(event);
+                let err = queue.run(proxy::run(listener, target, on_event), tx).await;
+                let _ = end_tx.send(err);
+            })
+        } else if let Some(cnx) = source.monitor_connection().await? {
+            RUNTIME.spawn(async move {
+                let err = queue.run(read_message_stream(cnx, &queue), tx).await;
+                let _ = end_tx.send(err);
+            })
+        } else {
+            let mut command = source.command();
+
+            let enter_guard = RUNTIME.enter();
+            let mut spawned_child = command.spawn().context("Failed to spawn command")?;
+            drop(enter_guard);
+
+            let stdout = spawned_child.stdout.take().expect("Child must have stdout");
+
+            let (first_read_tx, first_read_rx) = oneshot::channel();
+
+            let tokio_handle = RUNTIME.spawn(async move {
+                let err = queue
+                    .run(read_pcap_stream(stdout, &queue, Some(first_read_tx)), tx)
+                    .await;
+                let _ = end_tx.send(err);
+            });
+
+            // Wait for the first read before we check for child status to ensure that the
+            // child already exited on error.
+            first_read_rx.await.expect("rx unexpectedly closed");
+
+            // A command given by the user may also be done writing already,
+            // in which case what it wrote is still read
+            if let Some(exit_status) = spawned_child
+                .try_wait()
+                .context("Failed to wait child")?
+                .filter(|exit_status| !exit_status.success())
+            {
+                let err = anyhow!(
+                    "Child exited with status `{:?}` and code `{:?}`",
+                    exit_status,
+                    exit_status.code()
+                );
+                match exit_status.code() {
+                    Some(126) if !matches!(source, Source::Command(_)) => {
+                        return Err(err
+                            .context(Cancelled::new("User dismissed polkit authorization dialog")))
                     }
-                }));
+                    _ => return Err(err),
+                }
             }
-            Inner::Session {
-                tokio_handle,
-                handle,
-            } => {
-                let cnx = RUNTIME
-                    .spawn(async {
-                        let cnx = zbus::Connection::session().await?;
-                        let proxy = zbus::fdo::MonitoringProxy::new(&cnx).await?;
-                        proxy.become_monitor(&[], 0).await?;
-                        zbus::Result::Ok(cnx)
-                    })
+
+            imp.child.replace(Some(spawned_child));
+
+            tokio_handle
+        };
+        imp.tokio_handle.replace(Some(tokio_handle));
+
+        let this = self.downgrade();
+        imp.handle
+            .replace(Some(glib::spawn_future_local(async move {
+                while let Some(events) = rx.recv().await {
+                    messages_cb(events);
+                }
+
+                // This is only reached if monitoring stopped on its own
+                let err = end_rx
                     .await
-                    .context("Failed to spawn on runtime")?
-                    .context("Failed to setup monitoring")?;
-
-                let (tx, mut rx) = mpsc::channel(10);
-
-                tokio_handle.replace(RUNTIME.spawn(async move {
-                    let mut stream = zbus::MessageStream::from(cnx);
-
-                    while let Some(res) = stream.next().await {
-                        match res {
-                            Ok(message) => {
-                                let _ = tx
-                                    .send(Event {
-                                        message,
-                                        timestamp: Timestamp::now(),
-                                    })
-                                    .await;
-                            }
-                            Err(err) => tracing::warn!("Failed to receive message: {:?}", err),
-                        }
+                    .unwrap_or_else(|_| anyhow!("Monitoring task was cancelled"));
+                if let Some(this) = this.upgrade() {
+                    if err.is::<EndOfStream>() && this.imp().source.get().unwrap().can_end() {
+                        this.finish();
+                    } else {
+                        this.fail(err);
                     }
-                }));
+                }
+            })));
+
+        Ok(())
+    }
+
+    fn finish(&self) {
+        let imp = self.imp();
+
+        // Don't abort the currently running future
+        imp.handle.take();
 
-                handle.replace(glib::spawn_future_local(async move {
-                    while let Some(event) = rx.recv().await {
-                        message_cb(event);
+        // The command may have closed its output before failing
+        let exit_status = imp
+            .child
+            .borrow_mut()
+            .as_mut()
+            .and_then(|child| child.try_wait().ok().flatten())
+            .filter(|exit_status| !exit_status.success());
+        if let Some(exit_status) = exit_status {
+            self.fail(anyhow!("Child process exited with {exit_status}"));
+            return;
+        }
+
+        tracing::debug!("Monitoring reached the end of the stream");
+
+        self.stop();
+
+        self.emit_by_name::<()>("finished", &[]);
+    }
+
+    fn fail(&self, err: anyhow::Error) {
+        let imp = self.imp();
+
+        // Don't abort the currently running future
+        imp.handle.take();
+
+        let exit_status = imp
+            .child
+            .borrow_mut()
+            .as_mut()
+            .and_then(|child| child.try_wait().ok().flatten());
+        let err = match exit_status {
+            Some(exit_status) => err.context(format!("Child process exited with {exit_status}")),
+            None => err,
+        };
+        tracing::warn!("Monitoring stopped unexpectedly: {:?}", err);
+
+        self.stop();
+
+        self.emit_by_name::<()>("error", &[&format!("{err:#}")]);
+    }
+
+    fn set_state(&self, state: MonitorState) {
+        if state == self.state() {
+            return;
+        }
+
+        self.imp().state.set(state);
+        self.notify_state();
+    }
+}
+
+/// Connects with `connect` and turns the connection into a monitor of all
+/// messages
+async fn become_monitor(
+    connect: impl Future<Output = zbus::Result<zbus::Connection>> + Send + 'static,
+) -> Result<zbus::Connection> {
+    RUNTIME
+        .spawn(async move {
+            let cnx = connect.await?;
+            let proxy = zbus::fdo::MonitoringProxy::new(&cnx).await?;
+            proxy.become_monitor(&[], 0).await?;
+            zbus::Result::Ok(cnx)
+        })
+        .await
+        .context("Failed to spawn on runtime")?
+        .context("Failed to setup monitoring")
+}
+
+/// Incremental parser of a PCAP stream, fed with its bytes as they are read
+#[derive(Default)]
+struct PcapStream {
+    /// Start of the packet that is not complete yet
+    buf: Vec<u8>,
+    parser: Option<PcapParser>,
+}
+
+impl PcapStream {
+    /// Queues the packets completed by `bytes`
+    fn feed(&mut self, bytes: &[u8], queue: &EventQueue) -> Result<()> {
+        self.buf.extend_from_slice(bytes);
+
+        let mut n_consumed = 0;
+
+        if self.parser.is_none() {
+            match PcapParser::new(&self.buf) {
+                Ok((rest, parser)) => {
+                    n_consumed = self.buf.len() - rest.len();
+                    self.parser = Some(parser);
+                }
+                Err(PcapError::IncompleteBuffer) => return Ok(()),
+                Err(err) => return Err(anyhow!(err).context("Failed to parse PCAP header")),
+            }
+        }
+        let parser = self.parser.as_ref().unwrap();
+
+        loop {
+            match parser.next_packet(&self.buf[n_consumed..]) {
+                Ok((rest, packet)) => {
+                    n_consumed = self.buf.len() - rest.len();
+
+                    match Event::from_packet(packet) {
+                        Ok(event) => queue.push(event),
+                        Err(err) => {
+                            tracing::warn!("Failed to create event from packet: {:?}", err);
+                        }
                     }
-                }));
+                }
+                Err(PcapError::IncompleteBuffer) => break,
+                Err(err) => return Err(anyhow!(err).context("Failed to parse PCAP packet")),
             }
         }
 
+        self.buf.drain(..n_consumed);
+
         Ok(())
     }
+
+    /// Whether no partially read header or packet is left
+    fn is_at_packet_boundary(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+/// Opens the named pipe at `fifo_path`, or the standard input if None
+async fn open_pcap_input(fifo_path: Option<PathBuf>) -> Result<File> {
+    match fifo_path {
+        Some(path) => File::open(&path)
+            .await
+            .with_context(|| format!("Failed to open `{}`", path.display())),
+        None => {
+            let stdin = std::io::stdin()
+                .as_fd()
+                .try_clone_to_owned()
+                .context("Failed to duplicate standard input")?;
+            Ok(File::from_std(std::fs::File::from(stdin)))
+        }
+    }
+}
+
+/// Queues the messages of the PCAP stream written by `dbus-monitor --pcap`,
+/// or anything else writing the same format, as they come, keeping the time
+/// at which the bus saw them
+///
+/// `first_read_tx` is notified once the first read is done, whether it
+/// failed or not.
+///
+/// Returns why the stream could not be read further.
+async fn read_pcap_stream(
+    mut input: impl AsyncRead + Unpin,
+    queue: &EventQueue,
+    mut first_read_tx: Option<oneshot::Sender<()>>,
+) -> anyhow::Error {
+    let mut stream = PcapStream::default();
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+        let ret = input.read(&mut chunk).await;
+
+        if let Some(first_read_tx) = first_read_tx.take() {
+            let _ = first_read_tx.send(());
+        }
+
+        let n_read = match ret {
+            Ok(0) if stream.is_at_packet_boundary() => return anyhow!(EndOfStream),
+            Ok(0) => return anyhow!("PCAP stream ended in the middle of a packet"),
+            Ok(n_read) => n_read,
+            Err(err) => return anyhow!(err).context("Failed to read PCAP stream"),
+        };
+
+        if let Err(err) = stream.feed(&chunk[..n_read], queue) {
+            return err;
+        }
+    }
+}
+
+/// Queues the packets of the PCAP file at `path`, then the ones appended to
+/// it as it grows, starting over when the file is replaced or truncated
+///
+/// Returns why the file could not be read further.
+async fn read_followed_file(path: PathBuf, queue: &EventQueue) -> anyhow::Error {
+    let mut chunk = vec![0; 64 * 1024];
+
+    'reopen: loop {
+        let mut file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(err) => {
+                return anyhow!(err).context(format!("Failed to open `{}`", path.display()))
+            }
+        };
+        let identity = match file.metadata().await {
+            Ok(metadata) => (metadata.dev(), metadata.ino()),
+            Err(err) => return anyhow!(err).context("Failed to get file metadata"),
+        };
+        let mut stream = PcapStream::default();
+        let mut n_read_total = 0;
+        let mut prefix = Vec::new();
+
+        loop {
+            let n_read = match file.read(&mut chunk).await {
+                Ok(n_read) => n_read,
+                Err(err) => return anyhow!(err).context("Failed to read file"),
+            };
+
+            if n_read != 0 {
+                n_read_total += n_read as u64;
+                let n_prefix = n_read.min(FOLLOW_PREFIX_LEN - prefix.len());
+                prefix.extend_from_slice(&chunk[..n_prefix]);
+                if let Err(err) = stream.feed(&chunk[..n_read], queue) {
+                    return err;
+                }
+                continue;
+            }
+
+            // Reached the end for now, wait for the file to grow
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+
+            match tokio::fs::metadata(&path).await {
+                Ok(metadata) if (metadata.dev(), metadata.ino()) != identity => {
+                    tracing::debug!(?path, "Followed file was replaced");
+                    continue 'reopen;
+                }
+                Ok(metadata) if metadata.len() < n_read_total => {
+                    tracing::debug!(?path, "Followed file was truncated");
+                    continue 'reopen;
+                }
+                // Rewritten from the start, possibly past where it was read
+                Ok(_) => match starts_with(&path, &prefix).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::debug!(?path, "Followed file was rewritten");
+                        continue 'reopen;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return anyhow!(err).context("Failed to read file"),
+                },
+                // Rotated away and not created again yet, the previous file
+                // may still be written to
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return anyhow!(err).context("Failed to get file metadata"),
+            }
+        }
+    }
+}
+
+/// Returns whether the file at `path` still starts with `prefix`
+async fn starts_with(path: &Path, prefix: &[u8]) -> io::Result<bool> {
+    let mut file = File::open(path).await?;
+    let mut start = vec![0; prefix.len()];
+    match file.read_exact(&mut start).await {
+        Ok(_) => Ok(start == prefix),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Queues the messages received by `cnx`, which must be a monitor
+///
+/// Returns why no more messages could be received.
+async fn read_message_stream(cnx: zbus::Connection, queue: &EventQueue) -> anyhow::Error {
+    let mut stream = zbus::MessageStream::from(cnx);
+
+    while let Some(res) = stream.next().await {
+        match res {
+            Ok(message) => queue.push(Event {
+                message,
+                timestamp: Timestamp::now(),
+            }),
+            Err(err) => tracing::warn!("Failed to receive message: {:?}", err),
+        }
+    }
+
+    anyhow!("Connection to the bus was closed")
+}
+
+/// Returns the command running `argv` outside of the sandbox, if any
+fn host_command(argv: &[OsString]) -> Command {
+    let mut command = if *IS_RUNNING_IN_FLATPAK {
+        let mut command = Command::new("flatpak-spawn");
+        command.arg("--host").arg(&argv[0]);
+        command
+    } else {
+        Command::new(&argv[0])
+    };
+
+    command.args(&argv[1..]);
+
+    command
 }
 
 fn dbus_monitor_command() -> Command {
@@ -311,7 +958,288 @@ fn dbus_monitor_command() -> Command {
         command.arg("--host").arg("pkexec");
     }
 
-    command.arg("dbus-monitor");
+    command.arg("dbus-monitor").arg("--pcap");
 
     command
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, io::Write};
+
+    use pcap_file::pcap::{PcapHeader, PcapReader, PcapWriter};
+
+    use super::*;
+    use crate::test_util::{event, member_signal};
+
+    /// Returns a PCAP stream with a signal per member of `members`
+    fn pcap_stream(members: &[&str]) -> Vec<u8> {
+        let header = PcapHeader {
+            datalink: pcap_file::DataLink::DBUS,
+            ..Default::default()
+        };
+        let mut writer = PcapWriter::with_header(Vec::new(), header).unwrap();
+        for (i, member) in members.iter().enumerate() {
+            let bytes = member_signal(":1.1", member).data().to_vec();
+            writer
+                .write_packet(&PcapPacket {
+                    timestamp: Duration::from_millis(i as u64),
+                    orig_len: bytes.len() as u32,
+                    data: Cow::Owned(bytes),
+                })
+                .unwrap();
+        }
+        writer.into_writer()
+    }
+
+    fn new_queue() -> EventQueue {
+        EventQueue::new(Arc::default())
+    }
+
+    fn queued_members(queue: &EventQueue) -> Vec<String> {
+        members(&queue.events.lock().unwrap())
+    }
+
+    /// Returns the `member` signal, as received when the recording started
+    fn member_event(member: &str) -> Event {
+        event(member_signal(":1.1", member), 0)
+    }
+
+    fn members(events: &[Event]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| event.message.header().member().unwrap().to_string())
+            .collect()
+    }
+
+    /// Runs `future` on a runtime whose clock only advances once every task
+    /// waits for it, so that the outcome does not depend on actual timing
+    fn block_on_paused<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Runs `read` on `queue` and returns the delivered batches, which are
+    /// only received once `receive_delay` elapsed
+    fn run_queue(
+        queue: &EventQueue,
+        read: impl Future<Output = anyhow::Error>,
+        receive_delay: Duration,
+    ) -> Vec<Vec<String>> {
+        let (tx, mut rx) = mpsc::channel::<Vec<Event>>(1);
+        let receive = async {
+            tokio::time::sleep(receive_delay).await;
+
+            let mut batches = Vec::new();
+            while let Some(events) = rx.recv().await {
+                batches.push(members(&events));
+            }
+            batches
+        };
+        let (_, batches) = block_on_paused(future::join(queue.run(read, tx), receive));
+        batches
+    }
+
+    #[test]
+    fn single_batch() {
+        let queue = new_queue();
+        let read = async {
+            queue.push(member_event("Foo"));
+            queue.push(member_event("Bar"));
+            queue.push(member_event("Baz"));
+            anyhow!("Done")
+        };
+
+        // Everything queued before delivery goes in the same batch
+        let batches = run_queue(&queue, read, Duration::ZERO);
+        assert_eq!(batches, [["Foo", "Bar", "Baz"]]);
+        assert_eq!(queue.stats.n_delayed(), 0);
+        assert_eq!(queue.stats.n_dropped(), 0);
+    }
+
+    #[test]
+    fn delayed_batch() {
+        let queue = new_queue();
+        let read = async {
+            queue.push(member_event("Foo"));
+            tokio::task::yield_now().await;
+            queue.push(member_event("Bar"));
+            queue.push(member_event("Baz"));
+            anyhow!("Done")
+        };
+
+        // The first batch is not handled yet when the others are queued, as
+        // the paused clock only gets to the receive delay once the queue
+        // waits for it
+        let batches = run_queue(&queue, read, Duration::from_millis(50));
+        assert_eq!(batches, [vec!["Foo"], vec!["Bar", "Baz"]]);
+        assert_eq!(queue.stats.n_delayed(), 2);
+        assert_eq!(queue.stats.n_dropped(), 0);
+    }
+
+    #[test]
+    fn overflow() {
+        let queue = new_queue();
+        let event = member_event("Foo");
+        for _ in 0..MAX_QUEUED_EVENTS + 2 {
+            queue.push(Event {
+                message: event.message.clone(),
+                timestamp: event.timestamp,
+            });
+        }
+
+        assert_eq!(queue.events.lock().unwrap().len(), MAX_QUEUED_EVENTS);
+        assert_eq!(queue.stats.n_dropped(), 2);
+    }
+
+    /// Feeds `bytes` to a `PcapStream` in chunks of the given sizes, cycling
+    /// through them, and returns the queued events
+    fn feed_in_chunks(bytes: &[u8], chunk_sizes: &[usize]) -> Vec<String> {
+        let queue = new_queue();
+        let mut stream = PcapStream::default();
+
+        let mut rest = bytes;
+        for chunk_size in chunk_sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, next) = rest.split_at((*chunk_size).min(rest.len()));
+            stream.feed(chunk, &queue).unwrap();
+            rest = next;
+        }
+        assert!(stream.is_at_packet_boundary());
+
+        queued_members(&queue)
+    }
+
+    #[test]
+    fn feed_in_chunks_like_reader() {
+        let bytes = pcap_stream(&["Foo", "Bar", "Baz", "Qux"]);
+
+        let mut reader = PcapReader::new(bytes.as_slice()).unwrap();
+        let mut read = Vec::new();
+        while let Some(packet) = reader.next_packet() {
+            read.push(Event::from_packet(packet.unwrap()).unwrap());
+        }
+        let read = members(&read);
+        assert_eq!(read, ["Foo", "Bar", "Baz", "Qux"]);
+
+        // Splits the global header, the record headers and the records
+        assert_eq!(feed_in_chunks(&bytes, &[1]), read);
+        assert_eq!(feed_in_chunks(&bytes, &[7, 13, 3, 29, 1, 64, 5]), read);
+        assert_eq!(feed_in_chunks(&bytes, &[23, 2]), read);
+        assert_eq!(feed_in_chunks(&bytes, &[bytes.len()]), read);
+
+        // Pseudo-random sizes, from a xorshift generator so failures can be
+        // reproduced
+        let mut state = 0x2545_f491_u32;
+        for _ in 0..100 {
+            let chunk_sizes = (0..16)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    (state % 40) as usize + 1
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(feed_in_chunks(&bytes, &chunk_sizes), read);
+        }
+    }
+
+    /// Waits until `queue` holds `n_events`, while the followed file is read
+    async fn wait_for_events(queue: &EventQueue, n_events: usize) {
+        for _ in 0..100 {
+            if queue.events.lock().unwrap().len() >= n_events {
+                return;
+            }
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+        }
+        panic!("timed out waiting for {n_events} events");
+    }
+
+    #[test]
+    fn follow_file() {
+        let path =
+            std::env::temp_dir().join(format!("bustle-test-{}-follow.pcap", std::process::id()));
+        let replacement = path.with_extension("new");
+
+        // Records of the same member have the same length, whatever their
+        // serial is
+        let foo_bar = pcap_stream(&["Foo", "Bar"]);
+        let n_foo = pcap_stream(&["Foo"]).len();
+        std::fs::write(&path, &foo_bar[..n_foo]).unwrap();
+
+        let queue = new_queue();
+        let follow = async {
+            // Changes happen halfway between two polls of the reader
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL / 2).await;
+            wait_for_events(&queue, 1).await;
+
+            // Appended in two writes, splitting the record
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            let n_half = n_foo + (foo_bar.len() - n_foo) / 2;
+            file.write_all(&foo_bar[n_foo..n_half]).unwrap();
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL * 2).await;
+            file.write_all(&foo_bar[n_half..]).unwrap();
+            drop(file);
+            wait_for_events(&queue, 2).await;
+
+            // Truncated in place, then rewritten from the start
+            std::fs::write(&path, pcap_stream(&["Baz"])).unwrap();
+            wait_for_events(&queue, 3).await;
+
+            // Truncated and rewritten past its previous length between two
+            // polls, so only its content tells
+            std::fs::write(&path, pcap_stream(&["Grault", "Garply"])).unwrap();
+            wait_for_events(&queue, 5).await;
+
+            // Replaced by another file, even a larger one
+            std::fs::write(&replacement, pcap_stream(&["Qux", "Quux", "Corge"])).unwrap();
+            std::fs::rename(&replacement, &path).unwrap();
+            wait_for_events(&queue, 8).await;
+        };
+
+        let res = block_on_paused(future::select(
+            Box::pin(read_followed_file(path.clone(), &queue)),
+            Box::pin(follow),
+        ));
+        if let future::Either::Left((err, _)) = res {
+            panic!("stopped following file: {err:?}");
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            queued_members(&queue),
+            ["Foo", "Bar", "Baz", "Grault", "Garply", "Qux", "Quux", "Corge"]
+        );
+    }
+
+    #[test]
+    fn end_of_stream() {
+        let bytes = pcap_stream(&["Foo", "Bar"]);
+
+        let queue = new_queue();
+        let err = RUNTIME.block_on(read_pcap_stream(bytes.as_slice(), &queue, None));
+        assert!(err.is::<EndOfStream>());
+        assert_eq!(queued_members(&queue), ["Foo", "Bar"]);
+
+        // Nothing was ever written
+        let queue = new_queue();
+        let err = RUNTIME.block_on(read_pcap_stream(&[][..], &queue, None));
+        assert!(err.is::<EndOfStream>());
+        assert!(queued_members(&queue).is_empty());
+
+        // The writer went away in the middle of the last packet
+        let queue = new_queue();
+        let err = RUNTIME.block_on(read_pcap_stream(&bytes[..bytes.len() - 1], &queue, None));
+        assert!(!err.is::<EndOfStream>());
+        assert_eq!(queued_members(&queue), ["Foo"]);
+    }
+}
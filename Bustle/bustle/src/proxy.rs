@@ -0,0 +1,449 @@
+use std::{
+    fs,
+    os::{linux::net::SocketAddrExt, unix::net::SocketAddr},
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use gtk::glib;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpStream, UnixListener, UnixStream},
+    task::JoinSet,
+};
+use zbus::{
+    address::transport::{Transport, UnixSocket},
+    export::futures_util::future::{self, Either},
+};
+
+use crate::{monitor::Event, timestamp::Timestamp};
+
+/// Maximum length of a message allowed by the specification
+const MAX_MESSAGE_LEN: usize = 128 * 1024 * 1024;
+
+/// Maximum length of a line exchanged during authentication
+const MAX_AUTH_LINE_LEN: usize = 16 * 1024;
+
+/// Length of the fixed part of a message header
+const FIXED_HEADER_LEN: usize = 16;
+
+/// Code of the `SENDER` header field
+const SENDER_FIELD_CODE: u8 = 7;
+
+static N_PROXIES: AtomicU32 = AtomicU32::new(0);
+
+/// Returns a path for the socket of a new proxy, unique in this process
+pub fn new_socket_path() -> PathBuf {
+    let n = N_PROXIES.fetch_add(1, Ordering::Relaxed);
+    glib::user_runtime_dir().join(format!("bustle-proxy-{}-{n}", std::process::id()))
+}
+
+/// Socket clients connect to instead of the bus or peer, removed once
+/// dropped
+pub struct Listener {
+    inner: UnixListener,
+    path: PathBuf,
+}
+
+impl Listener {
+    /// This must be called within the runtime
+    pub fn bind(path: &Path) -> Result<Self> {
+        // Left behind by an instance that had the same pid
+        let _ = fs::remove_file(path);
+
+        let inner = UnixListener::bind(path)
+            .with_context(|| format!("Failed to bind to `{}`", path.display()))?;
+
+        Ok(Self {
+            inner,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove proxy socket: {:?}", err);
+        }
+    }
+}
+
+/// Connects every client accepted on `listener` to `target`, forwarding
+/// their traffic untouched and calling `on_event` with every message
+/// exchanged in both directions
+///
+/// Returns why no more clients could be accepted.
+pub async fn run(
+    listener: Listener,
+    target: zbus::Address,
+    on_event: impl Fn(Event) + Send + Sync + 'static,
+) -> anyhow::Error {
+    let on_event = Arc::new(on_event);
+    let mut connections = JoinSet::new();
+    let mut n_accepted = 0;
+
+    loop {
+        let accepted = if connections.is_empty() {
+            listener.inner.accept().await
+        } else {
+            match future::select(pin!(listener.inner.accept()), pin!(connections.join_next())).await
+            {
+                Either::Left((accepted, _)) => accepted,
+                Either::Right((res, _)) => {
+                    match res {
+                        Some(Ok(Ok(()))) | None => tracing::debug!("Proxied client disconnected"),
+                        Some(Ok(Err(err))) => {
+                            tracing::warn!("Proxied connection failed: {:?}", err)
+                        }
+                        Some(Err(err)) => tracing::warn!("Failed to join connection: {:?}", err),
+                    }
+                    continue;
+                }
+            }
+        };
+
+        let client = match accepted {
+            Ok((client, _)) => client,
+            Err(err) => return anyhow!(err).context("Failed to accept client"),
+        };
+        tracing::debug!(id = n_accepted, "Accepted proxied client");
+
+        let target = target.clone();
+        let on_event = Arc::clone(&on_event);
+        let names = ConnectionNames::new(n_accepted);
+        connections.spawn(async move {
+            let peer = connect(&target)
+                .await
+                .context("Failed to connect to target")?;
+            relay(client, peer, names, &*on_event).await
+        });
+
+        n_accepted += 1;
+    }
+}
+
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+async fn connect(address: &zbus::Address) -> Result<Box<dyn Socket>> {
+    match address.transport() {
+        Transport::Unix(unix) => {
+            let socket_addr = match unix.path() {
+                UnixSocket::File(path) => SocketAddr::from_pathname(path)?,
+                UnixSocket::Abstract(name) => {
+                    SocketAddr::from_abstract_name(name.as_encoded_bytes())?
+                }
+                _ => bail!("Cannot connect to a listenable address"),
+            };
+            let stream = tokio::task::spawn_blocking(move || {
+                let stream = std::os::unix::net::UnixStream::connect_addr(&socket_addr)?;
+                stream.set_nonblocking(true)?;
+                std::io::Result::Ok(stream)
+            })
+            .await??;
+            Ok(Box::new(UnixStream::from_std(stream)?))
+        }
+        Transport::Tcp(tcp) => {
+            if tcp.nonce_file().is_some() {
+                bail!("Nonce TCP addresses are not supported");
+            }
+            Ok(Box::new(
+                TcpStream::connect((tcp.host(), tcp.port())).await?,
+            ))
+        }
+        _ => bail!("Unsupported transport"),
+    }
+}
+
+/// Names put in the `SENDER` header field of the messages that do not have
+/// one, as the bus would, so they can be shown on the diagram
+struct ConnectionNames {
+    /// Serial of the `Hello` call of the client, if it is connected to a bus
+    hello_serial: Option<u32>,
+    /// Unique name the bus assigned to the client, or a made up one
+    client: String,
+    /// Made up unique name of the peer the client talks to directly
+    peer: String,
+}
+
+impl ConnectionNames {
+    fn new(id: u32) -> Self {
+        Self {
+            hello_serial: None,
+            client: format!(":client.{id}"),
+            peer: format!(":peer.{id}"),
+        }
+    }
+}
+
+/// Forwards the traffic between `client` and `peer` until either of them
+/// disconnects
+async fn relay(
+    mut client: UnixStream,
+    mut peer: Box<dyn Socket>,
+    names: ConnectionNames,
+    on_event: &(dyn Fn(Event) + Send + Sync),
+) -> Result<()> {
+    let client_buf = authenticate(&mut client, &mut peer).await?;
+
+    let names = Mutex::new(names);
+    let (client_read, client_write) = client.split();
+    let (peer_read, peer_write) = tokio::io::split(peer);
+
+    let outgoing = forward_messages(client_read, peer_write, client_buf, |bytes| {
+        let mut names = names.lock().unwrap();
+        let Some(event) = parse_message(bytes, &names.client) else {
+            return;
+        };
+        let header = event.message.header();
+        if header
+            .interface()
+            .is_some_and(|interface| interface.as_str() == "org.freedesktop.DBus")
+            && header
+                .member()
+                .is_some_and(|member| member.as_str() == "Hello")
+        {
+            names.hello_serial = Some(header.primary().serial_num().get());
+        }
+        drop(names);
+        on_event(event);
+    });
+    let incoming = forward_messages(peer_read, client_write, Vec::new(), |bytes| {
+        let mut names = names.lock().unwrap();
+        let Some(event) = parse_message(bytes, &names.peer) else {
+            return;
+        };
+        let header = event.message.header();
+        if names.hello_serial.is_some()
+            && header.reply_serial().map(|serial| serial.get()) == names.hello_serial
+        {
+            match event.message.body().deserialize::<String>() {
+                Ok(unique_name) => names.client = unique_name,
+                Err(err) => tracing::warn!("Failed to read unique name: {:?}", err),
+            }
+        }
+        drop(names);
+        on_event(event);
+    });
+
+    // Pinned as locals, temporaries of the final expression would outlive `names`
+    let outgoing = pin!(outgoing);
+    let incoming = pin!(incoming);
+    match future::select(outgoing, incoming).await {
+        Either::Left((res, _)) | Either::Right((res, _)) => res,
+    }
+}
+
+/// Relays the authentication of `client` to `peer`, returning the bytes the
+/// client sent after `BEGIN`
+///
+/// Passing file descriptors is refused, as they cannot be forwarded.
+async fn authenticate(
+    client: &mut UnixStream,
+    peer: &mut (impl AsyncRead + AsyncWrite + Unpin),
+) -> Result<Vec<u8>> {
+    let mut nul = [0; 1];
+    client.read_exact(&mut nul).await?;
+    peer.write_all(&nul).await?;
+
+    let mut client_buf = Vec::new();
+    let mut peer_buf = Vec::new();
+
+    loop {
+        let line = read_auth_line(client, &mut client_buf).await?;
+
+        if line.starts_with(b"NEGOTIATE_UNIX_FD") {
+            client.write_all(b"ERROR\r\n").await?;
+            continue;
+        }
+
+        peer.write_all(&line).await?;
+
+        if line.starts_with(b"BEGIN") {
+            peer.write_all(&client_buf).await?;
+            return Ok(client_buf);
+        }
+
+        // Every other command is answered with exactly one line
+        let reply = read_auth_line(peer, &mut peer_buf).await?;
+        client.write_all(&reply).await?;
+    }
+}
+
+/// Returns the next line read from `stream`, including its `\r\n`, keeping
+/// what was read after it in `buf`
+async fn read_auth_line(
+    stream: &mut (impl AsyncRead + Unpin),
+    buf: &mut Vec<u8>,
+) -> Result<Vec<u8>> {
+    let mut chunk = [0; 1024];
+
+    loop {
+        if let Some(position) = buf.windows(2).position(|window| window == b"\r\n") {
+            return Ok(buf.drain(..position + 2).collect());
+        }
+
+        if buf.len() > MAX_AUTH_LINE_LEN {
+            bail!("Authentication line is too long");
+        }
+
+        let n_read = stream.read(&mut chunk).await?;
+        if n_read == 0 {
+            bail!("Connection closed during authentication");
+        }
+        buf.extend_from_slice(&chunk[..n_read]);
+    }
+}
+
+/// Forwards what is read from `from` to `to` as it comes, calling `on_message`
+/// with every complete message
+///
+/// `buf` holds the start of the stream that was already forwarded.
+async fn forward_messages(
+    mut from: impl AsyncRead + Unpin,
+    mut to: impl AsyncWrite + Unpin,
+    mut buf: Vec<u8>,
+    mut on_message: impl FnMut(Vec<u8>),
+) -> Result<()> {
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+        while let Some(len) = message_len(&buf)? {
+            if buf.len() < len {
+                break;
+            }
+            on_message(buf.drain(..len).collect());
+        }
+
+        let n_read = from.read(&mut chunk).await?;
+        if n_read == 0 {
+            return Ok(());
+        }
+
+        to.write_all(&chunk[..n_read]).await?;
+        buf.extend_from_slice(&chunk[..n_read]);
+    }
+}
+
+/// Parses `bytes`, setting `fallback_sender` as sender if it has none
+fn parse_message(bytes: Vec<u8>, fallback_sender: &str) -> Option<Event> {
+    let res = Event::from_bytes(bytes.clone(), Timestamp::now()).and_then(|event| {
+        if event.message.header().sender().is_some() {
+            Ok(event)
+        } else {
+            Event::from_bytes(with_sender(&bytes, fallback_sender), event.timestamp)
+        }
+    });
+
+    match res {
+        Ok(event) => Some(event),
+        Err(err) => {
+            tracing::warn!("Failed to parse proxied message: {:?}", err);
+            None
+        }
+    }
+}
+
+fn read_u32(message: &[u8], offset: usize) -> u32 {
+    let bytes = message[offset..offset + 4].try_into().unwrap();
+    if message[0] == b'B' {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+fn write_u32(message: &mut [u8], offset: usize, value: u32) {
+    let bytes = if message[0] == b'B' {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    message[offset..offset + 4].copy_from_slice(&bytes);
+}
+
+/// Returns the length of the message at the start of `buf`, or None if its
+/// fixed header was not read completely yet
+fn message_len(buf: &[u8]) -> Result<Option<usize>> {
+    if buf.len() < FIXED_HEADER_LEN {
+        return Ok(None);
+    }
+
+    if !matches!(buf[0], b'l' | b'B') {
+        bail!("Invalid endianness `{}`", buf[0]);
+    }
+
+    let body_len = read_u32(buf, 4) as usize;
+    let fields_len = read_u32(buf, 12) as usize;
+    let len = FIXED_HEADER_LEN + fields_len.next_multiple_of(8) + body_len;
+    if len > MAX_MESSAGE_LEN {
+        bail!("Message length {len} exceeds the maximum");
+    }
+
+    Ok(Some(len))
+}
+
+/// Returns a copy of `message`, which must not have a `SENDER` header field,
+/// with `sender` added as one
+fn with_sender(message: &[u8], sender: &str) -> Vec<u8> {
+    let fields_len = read_u32(message, 12) as usize;
+    let body_start = FIXED_HEADER_LEN + fields_len.next_multiple_of(8);
+
+    let mut ret = Vec::with_capacity(message.len() + sender.len() + 16);
+    ret.extend_from_slice(&message[..FIXED_HEADER_LEN + fields_len]);
+
+    // Header fields are structs, which are aligned to 8 bytes, holding the
+    // code, the signature and the string
+    ret.resize(ret.len().next_multiple_of(8), 0);
+    ret.extend_from_slice(&[SENDER_FIELD_CODE, 1, b's', 0]);
+    let len_offset = ret.len();
+    ret.extend_from_slice(&[0; 4]);
+    write_u32(&mut ret, len_offset, sender.len() as u32);
+    ret.extend_from_slice(sender.as_bytes());
+    ret.push(0);
+
+    let new_fields_len = ret.len() - FIXED_HEADER_LEN;
+    write_u32(&mut ret, 12, new_fields_len as u32);
+
+    ret.resize(ret.len().next_multiple_of(8), 0);
+    ret.extend_from_slice(&message[body_start..]);
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_sender() {
+        let message = zbus::Message::method("/org/example", "Ping")
+            .unwrap()
+            .destination("org.example.Service")
+            .unwrap()
+            .build(&("pong", 42u32))
+            .unwrap();
+        let bytes = message.data().to_vec();
+        assert_eq!(message_len(&bytes).unwrap(), Some(bytes.len()));
+        assert_eq!(message_len(&bytes[..FIXED_HEADER_LEN - 1]).unwrap(), None);
+
+        let bytes = with_sender(&bytes, ":1.42");
+        assert_eq!(message_len(&bytes).unwrap(), Some(bytes.len()));
+
+        let event = Event::from_bytes(bytes, Timestamp::now()).unwrap();
+        let header = event.message.header();
+        assert_eq!(header.sender().unwrap().as_str(), ":1.42");
+        assert_eq!(header.member().unwrap().as_str(), "Ping");
+        assert_eq!(
+            event.message.body().deserialize::<(String, u32)>().unwrap(),
+            ("pong".to_owned(), 42)
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use gtk::{gio, prelude::*};
+use tokio::{sync::mpsc, time::Instant};
+use zbus::{export::futures_util::StreamExt, names::UniqueName};
+
+use crate::{
+    bus_type::BusType,
+    message::{Message, DEFAULT_REPLY_TIMEOUT},
+    message_list::MessageList,
+    monitor::Event,
+    timestamp::Timestamp,
+    RUNTIME,
+};
+
+/// How the delays between the original calls are reproduced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Reissue the calls with the same delays as they were recorded with
+    Original,
+    /// Multiply the recorded delays by the given factor, e.g. `0.5` replays
+    /// twice as fast
+    Scaled(f64),
+}
+
+impl Timing {
+    fn apply(self, offset: Duration) -> Duration {
+        match self {
+            Self::Original => offset,
+            Self::Scaled(factor) => offset.mul_f64(factor.max(0.0)),
+        }
+    }
+}
+
+/// A method call to reissue, relative to the first replayed call
+struct ReplayCall {
+    offset: Duration,
+    message: zbus::Message,
+}
+
+/// Reissues the method calls of one sender from a capture against another bus
+pub struct Replay {
+    calls: Vec<ReplayCall>,
+    n_skipped_calls: usize,
+}
+
+impl Replay {
+    /// Collects the method calls sent by `sender` on `bus_type` from `model`,
+    /// which can be a whole `MessageList` or a filtered subset of it.
+    pub fn new(
+        model: &impl IsA<gio::ListModel>,
+        bus_type: BusType,
+        sender: &UniqueName<'_>,
+    ) -> Result<Self> {
+        let mut first_timestamp = None;
+        let mut calls = Vec::new();
+        let mut n_skipped_calls = 0;
+
+        for message in model.as_ref().iter::<Message>() {
+            let message = message.context("Model was mutated while collecting calls")?;
+
+            // Unique names are only unique on their own bus
+            if !message.message_type().is_method_call()
+                || message.bus_type() != bus_type
+                || message.sender().as_ref() != Some(sender)
+            {
+                continue;
+            }
+
+            // The file descriptors a call passed are not recorded, so it
+            // cannot be reissued
+            if passes_fds(&message) {
+                n_skipped_calls += 1;
+                continue;
+            }
+
+            let timestamp = message.timestamp();
+            let first_timestamp = *first_timestamp.get_or_insert(timestamp);
+
+            calls.push(ReplayCall {
+                offset: (timestamp - first_timestamp).into(),
+                message: message.to_event().message,
+            });
+        }
+
+        Ok(Self {
+            calls,
+            n_skipped_calls,
+        })
+    }
+
+    /// Returns the number of calls that will be reissued
+    pub fn n_calls(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns the number of calls that cannot be reissued, as they pass file
+    /// descriptors
+    pub fn n_skipped_calls(&self) -> usize {
+        self.n_skipped_calls
+    }
+
+    /// Connects to `address`, reissues the calls with the delays given by
+    /// `timing` and records them with their replies into `message_list`, so
+    /// that the new session can be compared with the original one, even while
+    /// the replay is still running.
+    ///
+    /// The replay stops early, without failing, once `cancellable` is
+    /// cancelled.
+    pub async fn run(
+        self,
+        address: zbus::Address,
+        timing: Timing,
+        message_list: &MessageList,
+        cancellable: &gio::Cancellable,
+    ) -> Result<()> {
+        let Self { calls, .. } = self;
+
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let handle = RUNTIME.spawn(async move {
+            let cnx = zbus::ConnectionBuilder::address(address)?
+                .build()
+                .await
+                .context("Failed to connect to address")?;
+            replay_calls(&cnx, calls, timing, &tx).await
+        });
+
+        // Aborting the task drops the sender, which ends the loop below
+        let abort_handle = handle.abort_handle();
+        let cancelled_id = cancellable.connect_cancelled_local(move |_| abort_handle.abort());
+
+        while let Some(event) = rx.recv().await {
+            message_list.push(event);
+        }
+
+        let res = handle.await;
+        if let Some(cancelled_id) = cancelled_id {
+            cancellable.disconnect_cancelled(cancelled_id);
+        }
+        match res {
+            Err(err) if err.is_cancelled() => Ok(()),
+            res => res
+                .context("Failed to join replay task")?
+                .context("Failed to replay calls"),
+        }
+    }
+}
+
+/// Sends `calls` on `cnx` with the delays given by `timing`, and forwards
+/// them to `tx` along with their replies
+async fn replay_calls(
+    cnx: &zbus::Connection,
+    calls: Vec<ReplayCall>,
+    timing: Timing,
+    tx: &mpsc::Sender<Event>,
+) -> Result<()> {
+    let unique_name = cnx
+        .unique_name()
+        .context("Connection has no unique name")?
+        .to_owned();
+    let mut stream = zbus::MessageStream::from(cnx);
+
+    // When to give up on the reply to each call, by serial. Serials only
+    // increase, so the first deadline is also the earliest one.
+    let mut pending_replies = BTreeMap::new();
+    let mut n_timed_out = 0;
+
+    let mut calls = calls.into_iter().peekable();
+    let start = Instant::now();
+    loop {
+        let next_call_at = calls.peek().map(|call| start + timing.apply(call.offset));
+        let next_deadline = pending_replies.values().next().copied();
+        let Some(wake_at) = next_call_at.into_iter().chain(next_deadline).min() else {
+            break;
+        };
+
+        let message = match tokio::time::timeout_at(wake_at, stream.next()).await {
+            Ok(Some(res)) => res.context("Failed to receive reply")?,
+            Ok(None) => bail!("Connection was closed before the replay ended"),
+            Err(_) => {
+                let now = Instant::now();
+
+                if next_call_at.is_some_and(|call_at| call_at <= now) {
+                    let call = calls.next().unwrap();
+                    let message = rebuild_call(&call.message, &unique_name)
+                        .context("Failed to rebuild call")?;
+                    cnx.send(&message).await.context("Failed to send call")?;
+
+                    if expects_reply(&message) {
+                        let serial = message.header().primary().serial_num();
+                        pending_replies.insert(serial, now + DEFAULT_REPLY_TIMEOUT);
+                    }
+
+                    let _ = tx
+                        .send(Event {
+                            message,
+                            timestamp: Timestamp::now(),
+                        })
+                        .await;
+                }
+
+                // Give up on replies like the original caller would have
+                let n_pending_replies = pending_replies.len();
+                pending_replies.retain(|_, deadline| *deadline > now);
+                n_timed_out += n_pending_replies - pending_replies.len();
+                continue;
+            }
+        };
+
+        // Anything else received on this connection is not part of the
+        // replayed session
+        let is_pending_reply = zbus_message_is_reply(&message)
+            && message
+                .header()
+                .reply_serial()
+                .is_some_and(|serial| pending_replies.remove(&serial).is_some());
+        if is_pending_reply {
+            let _ = tx
+                .send(Event {
+                    message,
+                    timestamp: Timestamp::now(),
+                })
+                .await;
+        }
+    }
+
+    tracing::debug!(n_timed_out, "Replayed calls");
+
+    Ok(())
+}
+
+/// Creates a copy of `call` with a fresh serial, sent from `sender`
+fn rebuild_call(call: &zbus::Message, sender: &UniqueName<'_>) -> Result<zbus::Message> {
+    let header = call.header();
+
+    let path = header.path().context("Call has no path")?;
+    let member = header.member().context("Call has no member")?;
+
+    let mut builder = zbus::Message::method(path, member)?.sender(sender)?;
+    for flag in header.primary().flags().iter() {
+        builder = builder.with_flags(flag)?;
+    }
+    if let Some(interface) = header.interface() {
+        builder = builder.interface(interface)?;
+    }
+    if let Some(destination) = header.destination() {
+        builder = builder.destination(destination)?;
+    }
+
+    let body = call.body();
+    let signature = header
+        .signature()
+        .cloned()
+        .unwrap_or_else(|| zbus::zvariant::Signature::from_static_str_unchecked(""));
+
+    debug_assert!(
+        !signature.as_str().contains('h'),
+        "file descriptors cannot be replayed"
+    );
+
+    // SAFETY: The body bytes and signature come from a message that was
+    // already successfully parsed, and passes no file descriptors.
+    let message = unsafe { builder.build_raw_body(body.data().bytes(), signature, Vec::new())? };
+    Ok(message)
+}
+
+/// Whether the arguments of `message` include file descriptors
+fn passes_fds(message: &Message) -> bool {
+    message
+        .header()
+        .signature()
+        .is_some_and(|signature| signature.as_str().contains('h'))
+}
+
+fn expects_reply(message: &zbus::Message) -> bool {
+    message.message_type() == zbus::MessageType::MethodCall
+        && !message
+            .header()
+            .primary()
+            .flags()
+            .contains(zbus::MessageFlags::NoReplyExpected)
+}
+
+fn zbus_message_is_reply(message: &zbus::Message) -> bool {
+    matches!(
+        message.message_type(),
+        zbus::MessageType::MethodReturn | zbus::MessageType::Error
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{call, event, reply, signal};
+
+    fn offsets(replay: &Replay) -> Vec<Duration> {
+        replay.calls.iter().map(|call| call.offset).collect()
+    }
+
+    #[test]
+    fn offsets_from_first_call() {
+        let message_list = MessageList::default();
+        let first_call = call(":1.2");
+        message_list.push_batch(
+            vec![
+                event(signal(":1.2"), 50),
+                event(first_call.clone(), 100),
+                event(reply(&first_call), 110),
+                event(call(":1.3"), 120),
+                event(call(":1.2"), 150),
+                event(call(":1.2"), 400),
+            ],
+            BusType::Session,
+        );
+        message_list.push_batch(vec![event(call(":1.2"), 500)], BusType::System);
+
+        let sender = UniqueName::from_static_str(":1.2").unwrap();
+
+        // Only the calls of the sender on the bus count, relative to the
+        // first of them
+        let replay = Replay::new(&message_list, BusType::Session, &sender).unwrap();
+        assert_eq!(replay.n_calls(), 3);
+        assert_eq!(
+            offsets(&replay),
+            [
+                Duration::ZERO,
+                Duration::from_millis(50),
+                Duration::from_millis(300)
+            ]
+        );
+
+        let replay = Replay::new(&message_list, BusType::System, &sender).unwrap();
+        assert_eq!(offsets(&replay), [Duration::ZERO]);
+
+        let sender = UniqueName::from_static_str(":1.4").unwrap();
+        let replay = Replay::new(&message_list, BusType::Session, &sender).unwrap();
+        assert_eq!(replay.n_calls(), 0);
+    }
+
+    #[test]
+    fn timing() {
+        let offset = Duration::from_millis(300);
+        assert_eq!(Timing::Original.apply(offset), offset);
+        assert_eq!(
+            Timing::Scaled(0.5).apply(offset),
+            Duration::from_millis(150)
+        );
+        assert_eq!(Timing::Scaled(0.0).apply(offset), Duration::ZERO);
+        assert_eq!(Timing::Scaled(-1.0).apply(offset), Duration::ZERO);
+    }
+}
@@ -0,0 +1,75 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::{gio, glib};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/retention_dialog.ui")]
+    pub struct RetentionDialog {
+        #[template_child]
+        pub(super) count_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) duration_row: TemplateChild<adw::SpinRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RetentionDialog {
+        const NAME: &'static str = "BustleRetentionDialog";
+        type Type = super::RetentionDialog;
+        type ParentType = adw::AlertDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for RetentionDialog {}
+    impl WidgetImpl for RetentionDialog {}
+    impl AdwDialogImpl for RetentionDialog {}
+    impl AdwAlertDialogImpl for RetentionDialog {}
+}
+
+glib::wrapper! {
+    /// Edits how many of their most recent messages new recordings keep
+    pub struct RetentionDialog(ObjectSubclass<imp::RetentionDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::AlertDialog;
+}
+
+impl RetentionDialog {
+    /// Shows the retention limits stored in `settings`, and stores the
+    /// edited ones unless cancelled
+    pub async fn edit(parent: &impl IsA<gtk::Widget>, settings: &gio::Settings) {
+        let this = glib::Object::new::<Self>();
+        let imp = this.imp();
+
+        imp.count_row
+            .set_value(settings.uint("retention-count").into());
+        // The duration is stored in seconds but only edited in minutes
+        imp.duration_row
+            .set_value((settings.uint("retention-duration") / 60).max(1).into());
+
+        match this.clone().choose_future(parent).await.as_str() {
+            "cancel" => {}
+            "save" => {
+                if let Err(err) = this.save(settings) {
+                    tracing::error!("Failed to save retention limits: {:?}", err);
+                }
+            }
+            response_id => unreachable!("unexpected response id `{}`", response_id),
+        }
+    }
+
+    fn save(&self, settings: &gio::Settings) -> Result<(), glib::BoolError> {
+        let imp = self.imp();
+
+        settings.set_uint("retention-count", imp.count_row.value() as u32)?;
+        settings.set_uint("retention-duration", imp.duration_row.value() as u32 * 60)?;
+
+        Ok(())
+    }
+}
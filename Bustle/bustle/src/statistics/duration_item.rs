@@ -12,11 +12,11 @@ mod imp {
     pub struct DurationItem {
         #[property(get, set, construct_only)]
         pub(super) method: OnceCell<String>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) total: Cell<Timestamp>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) calls: Cell<u32>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) total_calls: Cell<u32>,
     }
 
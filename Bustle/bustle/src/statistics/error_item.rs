@@ -0,0 +1,69 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use crate::message::Message;
+
+mod imp {
+    use std::cell::{Cell, OnceCell};
+
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties)]
+    #[properties(wrapper_type = super::ErrorItem)]
+    pub struct ErrorItem {
+        /// Markup of the error name or method the errors are grouped by
+        #[property(get, set, construct_only)]
+        pub(super) group: OnceCell<String>,
+        /// Markup of the methods or error names seen in this group
+        #[property(get, set, construct_only)]
+        pub(super) related: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) count: Cell<u32>,
+        #[property(get, set, construct_only)]
+        pub(super) total: Cell<u32>,
+        #[property(get, set, construct_only)]
+        pub(super) senders: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) text: OnceCell<String>,
+
+        pub(super) messages: OnceCell<Vec<Message>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ErrorItem {
+        const NAME: &'static str = "BustleErrorItem";
+        type Type = super::ErrorItem;
+    }
+    #[glib::derived_properties]
+    impl ObjectImpl for ErrorItem {}
+}
+
+glib::wrapper! {
+    pub struct ErrorItem(ObjectSubclass<imp::ErrorItem>);
+}
+
+impl ErrorItem {
+    pub fn new(
+        group: &str,
+        related: &str,
+        total: u32,
+        senders: &str,
+        text: &str,
+        messages: Vec<Message>,
+    ) -> Self {
+        let this = glib::Object::builder::<Self>()
+            .property("group", group)
+            .property("related", related)
+            .property("count", messages.len() as u32)
+            .property("total", total)
+            .property("senders", senders)
+            .property("text", text)
+            .build();
+        this.imp().messages.set(messages).unwrap();
+        this
+    }
+
+    /// The error messages in this group
+    pub fn messages(&self) -> &[Message] {
+        self.imp().messages.get().unwrap()
+    }
+}
@@ -12,9 +12,9 @@ mod imp {
     pub struct FrequencyItem {
         #[property(get, set, construct_only)]
         pub(super) member: OnceCell<String>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) count: Cell<u32>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) total: Cell<u32>,
         #[property(get, set, construct_only, builder(MessageType::default()))]
         pub(super) message_type: Cell<MessageType>,
@@ -0,0 +1,94 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use crate::{bus_type::BusType, timestamp::Timestamp};
+
+mod imp {
+    use std::{
+        cell::{Cell, OnceCell},
+        marker::PhantomData,
+    };
+
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties)]
+    #[properties(wrapper_type = super::MatrixItem)]
+    pub struct MatrixItem {
+        /// Bus of both the sender and the destination
+        #[property(get, set, construct_only, builder(BusType::default()))]
+        pub(super) bus_type: Cell<BusType>,
+        #[property(get, set, construct_only)]
+        pub(super) sender: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) destination: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) count: Cell<u32>,
+        /// Highest count of all pairs
+        #[property(get, set, construct_only)]
+        pub(super) max_count: Cell<u32>,
+        #[property(get, set, construct_only)]
+        pub(super) bytes: Cell<u64>,
+        /// Sum of the response times of the calls from sender to destination
+        #[property(get, set, construct_only)]
+        pub(super) latency: Cell<Timestamp>,
+        /// Whether the labels tell the bus, as the same names may be on
+        /// several buses
+        #[property(get, set)]
+        pub(super) shows_bus_type: Cell<bool>,
+        #[property(get = Self::sender_label)]
+        pub(super) sender_label: PhantomData<String>,
+        #[property(get = Self::destination_label)]
+        pub(super) destination_label: PhantomData<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MatrixItem {
+        const NAME: &'static str = "BustleMatrixItem";
+        type Type = super::MatrixItem;
+    }
+    #[glib::derived_properties]
+    impl ObjectImpl for MatrixItem {}
+
+    impl MatrixItem {
+        fn sender_label(&self) -> String {
+            self.label(self.sender.get().unwrap())
+        }
+
+        fn destination_label(&self) -> String {
+            self.label(self.destination.get().unwrap())
+        }
+
+        fn label(&self, name: &str) -> String {
+            if self.shows_bus_type.get() {
+                format!("{} ({})", name, self.bus_type.get().interface_name())
+            } else {
+                name.to_string()
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MatrixItem(ObjectSubclass<imp::MatrixItem>);
+}
+
+impl MatrixItem {
+    pub fn new(
+        bus_type: BusType,
+        sender: &str,
+        destination: &str,
+        count: u32,
+        max_count: u32,
+        bytes: u64,
+        latency: Timestamp,
+    ) -> Self {
+        glib::Object::builder()
+            .property("bus-type", bus_type)
+            .property("sender", sender)
+            .property("destination", destination)
+            .property("count", count)
+            .property("max-count", max_count)
+            .property("bytes", bytes)
+            .property("latency", latency)
+            .build()
+    }
+}
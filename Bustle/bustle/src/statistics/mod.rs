@@ -1,11 +1,20 @@
 mod duration_item;
+mod error_item;
 mod frequency_item;
+mod matrix_item;
 mod pages;
 mod size_item;
+mod unanswered_item;
 mod window;
 
 pub use duration_item::DurationItem;
+pub use error_item::ErrorItem;
 pub use frequency_item::FrequencyItem;
-pub use pages::{DurationsPage, FrequenciesPage, SizesPage};
+pub use matrix_item::MatrixItem;
+pub use pages::{
+    DurationsPage, ErrorsPage, FrequenciesPage, MatrixPage, OwnershipPage, SizesPage,
+    UnansweredPage,
+};
 pub use size_item::SizeItem;
+pub use unanswered_item::UnansweredItem;
 pub use window::StatisticsWindow;
@@ -1,14 +1,29 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+};
+
 use adw::subclass::prelude::*;
-use gtk::{gio, glib, prelude::*};
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
 
-use crate::{filtered_message_model::FilteredMessageModel, statistics::DurationItem};
+use crate::{
+    filtered_message_model::FilteredMessageModel,
+    message::{Message, ReceiveIndex},
+    message_type::MessageType,
+    statistics::DurationItem,
+    timestamp::Timestamp,
+};
 
 mod imp {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     use super::*;
 
-    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[derive(Debug, glib::Properties, gtk::CompositeTemplate)]
     #[properties(wrapper_type = super::DurationsPage)]
     #[template(resource = "/org/freedesktop/Bustle/ui/durations_page.ui")]
     pub struct DurationsPage {
@@ -24,6 +39,30 @@ mod imp {
         pub(super) calls_column: TemplateChild<gtk::ColumnViewColumn>,
         #[template_child]
         pub(super) mean_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        pub(super) items: gio::ListStore,
+        pub(super) items_map: RefCell<HashMap<String, DurationItem>>,
+        pub(super) total_calls: Cell<u32>,
+        /// Item and duration of each accounted call, by the receive index of
+        /// the call
+        pub(super) accounted: RefCell<BTreeMap<ReceiveIndex, (DurationItem, Timestamp)>>,
+    }
+
+    impl Default for DurationsPage {
+        fn default() -> Self {
+            Self {
+                model: Default::default(),
+                column_view: Default::default(),
+                method_column: Default::default(),
+                total_column: Default::default(),
+                calls_column: Default::default(),
+                mean_column: Default::default(),
+                items: gio::ListStore::new::<DurationItem>(),
+                items_map: Default::default(),
+                total_calls: Default::default(),
+                accounted: Default::default(),
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -82,50 +121,35 @@ mod imp {
 
     impl DurationsPage {
         fn set_model(&self, model: &FilteredMessageModel) {
-            let mut durations_map = std::collections::HashMap::new();
-            let durations = gio::ListStore::new::<DurationItem>();
-            for message in model.iter() {
-                if !message.message_type().is_method_call() {
-                    continue;
-                }
-
-                let Some(return_message) = message.associated_message() else {
-                    continue;
-                };
-
-                // Ignore errors when computing the time it took for a method to receive a reply
-                // This is mostly done to be 100% compatible with Hustle
-                // TODO: investigate if it makes sense to keep this or not
-                if return_message.message_type().is_error() {
-                    continue;
-                }
-
-                let new_duration = return_message.timestamp() - message.timestamp();
-                let member_name = message.member_markup(true);
-                durations_map
-                    .entry(member_name)
-                    .and_modify(|(calls, duration)| {
-                        *calls += 1;
-                        *duration += new_duration;
-                    })
-                    .or_insert((1, new_duration));
-            }
+            let obj = self.obj();
 
-            let total_calls = durations_map.iter().map(|(_, (call, _))| call).sum();
-            for (member, (calls, total_duration)) in durations_map.into_iter() {
-                durations.append(&DurationItem::new(
-                    &member,
-                    calls,
-                    total_calls,
-                    total_duration,
-                ))
-            }
             self.column_view
                 .sort_by_column(Some(&self.calls_column), gtk::SortType::Descending);
             let sorter = self.column_view.sorter();
-            let sorted_model = gtk::SortListModel::new(Some(durations), sorter);
+            let sorted_model = gtk::SortListModel::new(Some(self.items.clone()), sorter);
             let selection_model = gtk::NoSelection::new(Some(sorted_model));
             self.column_view.set_model(Some(&selection_model));
+
+            model.connect_items_changed(
+                clone!(@weak obj => move |model, position, removed, added| {
+                    // Messages are only appended while recording and evicted
+                    // from the front, anything else means the filters changed
+                    if removed == 0 && position + added == model.n_items() {
+                        obj.add_messages(model, (position..model.n_items()).map(|i| {
+                            model.item(i).and_downcast::<Message>().unwrap()
+                        }));
+                    } else if position == 0 && added == 0 {
+                        match model.item(0).and_downcast::<Message>() {
+                            Some(first) => obj.remove_messages_before(first.receive_index()),
+                            None => obj.reset(model),
+                        }
+                    } else {
+                        obj.reset(model);
+                    }
+                }),
+            );
+
+            obj.reset(model);
             self.model.set(Some(model.clone()));
         }
     }
@@ -136,6 +160,108 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Bin;
 }
 
+impl DurationsPage {
+    /// Recomputes the durations from all the messages of `model`
+    fn reset(&self, model: &FilteredMessageModel) {
+        let imp = self.imp();
+
+        imp.items.remove_all();
+        imp.items_map.borrow_mut().clear();
+        imp.total_calls.set(0);
+        imp.accounted.borrow_mut().clear();
+
+        self.add_messages(model, model.iter());
+    }
+
+    /// Accounts the calls answered by `messages` in the durations, updating
+    /// the existing items in place
+    fn add_messages(&self, model: &FilteredMessageModel, messages: impl Iterator<Item = Message>) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let mut new_items = Vec::new();
+        let mut total_calls = imp.total_calls.get();
+        for return_message in messages {
+            // Ignore errors when computing the time it took for a method to receive a reply
+            // This is mostly done to be 100% compatible with Hustle
+            // TODO: investigate if it makes sense to keep this or not
+            if return_message.message_type() != MessageType::MethodReturn {
+                continue;
+            }
+
+            // Calls are accounted once their reply arrives, as only then their
+            // duration is known
+            let Some(message) = return_message.associated_message() else {
+                continue;
+            };
+            if model.get_index_of(&message).is_none() {
+                continue;
+            }
+            total_calls += 1;
+
+            let new_duration = return_message.timestamp() - message.timestamp();
+            let member_name = message.member_markup(true);
+            let item = match items_map.get(&member_name) {
+                Some(item) => {
+                    let mut total = item.total();
+                    total += new_duration;
+                    item.set_calls(item.calls() + 1);
+                    item.set_total(total);
+                    item.clone()
+                }
+                None => {
+                    let item = DurationItem::new(&member_name, 1, total_calls, new_duration);
+                    items_map.insert(member_name, item.clone());
+                    new_items.push(item.clone());
+                    item
+                }
+            };
+            accounted.insert(message.receive_index(), (item, new_duration));
+        }
+
+        if total_calls != imp.total_calls.get() {
+            imp.total_calls.set(total_calls);
+            for item in items_map.values() {
+                item.set_total_calls(total_calls);
+            }
+        }
+        imp.items.extend_from_slice(&new_items);
+    }
+
+    /// Takes back the calls received before `receive_index` from the
+    /// durations, once they were evicted
+    fn remove_messages_before(&self, receive_index: ReceiveIndex) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let retained = accounted.split_off(&receive_index);
+        let evicted = mem::replace(&mut *accounted, retained);
+        if evicted.is_empty() {
+            return;
+        }
+
+        for (item, duration) in evicted.values() {
+            item.set_calls(item.calls() - 1);
+            item.set_total(item.total() - *duration);
+
+            if item.calls() == 0 {
+                items_map.remove(&item.method());
+                if let Some(position) = imp.items.find(item) {
+                    imp.items.remove(position);
+                }
+            }
+        }
+
+        let total_calls = imp.total_calls.get() - evicted.len() as u32;
+        imp.total_calls.set(total_calls);
+        for item in items_map.values() {
+            item.set_total_calls(total_calls);
+        }
+    }
+}
+
 #[gtk::template_callbacks]
 impl DurationsPage {
     #[template_callback]
@@ -0,0 +1,230 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gio, glib};
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+    filtered_message_model::FilteredMessageModel, message::Message, statistics::ErrorItem,
+};
+
+/// Number of distinct error texts shown per group
+const N_SHOWN_TEXTS: usize = 3;
+
+#[derive(Default)]
+struct ErrorGroup {
+    related: IndexSet<String>,
+    senders: IndexSet<String>,
+    texts: IndexMap<String, u32>,
+    messages: Vec<Message>,
+}
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::statistics::pages::ProgressCell;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::ErrorsPage)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/errors_page.ui")]
+    pub struct ErrorsPage {
+        #[property(get, set = Self::set_model)]
+        pub(super) model: RefCell<Option<FilteredMessageModel>>,
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) by_member_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub(super) column_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        pub(super) group_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) related_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) count_column: TemplateChild<gtk::ColumnViewColumn>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ErrorsPage {
+        const NAME: &'static str = "BustleErrorsPage";
+        type Type = super::ErrorsPage;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            ProgressCell::ensure_type();
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ErrorsPage {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.group_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &ErrorItem::this_expression("group"),
+                ))));
+            self.count_column
+                .set_sorter(Some(&gtk::NumericSorter::new(Some(
+                    &ErrorItem::this_expression("count"),
+                ))));
+        }
+    }
+
+    impl WidgetImpl for ErrorsPage {}
+
+    impl BinImpl for ErrorsPage {}
+
+    impl ErrorsPage {
+        fn set_model(&self, model: &FilteredMessageModel) {
+            self.model.replace(Some(model.clone()));
+            self.obj().update_items();
+        }
+    }
+}
+
+glib::wrapper! {
+     pub struct ErrorsPage(ObjectSubclass<imp::ErrorsPage>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl ErrorsPage {
+    fn update_items(&self) {
+        let imp = self.imp();
+
+        let Some(model) = self.model() else {
+            return;
+        };
+
+        let by_member = imp.by_member_button.is_active();
+        if by_member {
+            imp.group_column.set_title(Some(&gettext("Method")));
+            imp.related_column.set_title(Some(&gettext("Errors")));
+        } else {
+            imp.group_column.set_title(Some(&gettext("Error")));
+            imp.related_column.set_title(Some(&gettext("Methods")));
+        }
+
+        let mut groups = IndexMap::<String, ErrorGroup>::new();
+        for message in model.iter() {
+            if !message.message_type().is_error() {
+                continue;
+            }
+
+            let error_name = glib::markup_escape_text(
+                &message
+                    .error_name()
+                    .unwrap_or_else(|| "(no error name)".to_owned()),
+            )
+            .to_string();
+            // Errors are displayed with the member of the call they reply to
+            let member = message.member_markup(false);
+            let (key, related) = if by_member {
+                (member, error_name)
+            } else {
+                (error_name, member)
+            };
+
+            let group = groups.entry(key).or_default();
+            group.related.insert(related);
+            // The sender of the call that triggered the error
+            group.senders.insert(message.destination_display());
+            if let Some(text) = message.error_text() {
+                *group.texts.entry(text).or_default() += 1;
+            }
+            group.messages.push(message);
+        }
+
+        let total = groups
+            .values()
+            .map(|group| group.messages.len() as u32)
+            .sum();
+        let items = gio::ListStore::new::<ErrorItem>();
+        for (key, mut group) in groups {
+            group.texts.sort_by(|_, a, _, b| b.cmp(a));
+            let mut text = group
+                .texts
+                .keys()
+                .take(N_SHOWN_TEXTS)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("; ");
+            if group.texts.len() > N_SHOWN_TEXTS {
+                text.push_str("; …");
+            }
+
+            items.append(&ErrorItem::new(
+                &key,
+                &group
+                    .related
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                total,
+                &group
+                    .senders
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                &text,
+                group.messages,
+            ));
+        }
+
+        if items.n_items() == 0 {
+            imp.stack.set_visible_child_name("empty");
+        } else {
+            imp.stack.set_visible_child_name("list");
+        }
+
+        imp.column_view
+            .sort_by_column(Some(&imp.count_column), gtk::SortType::Descending);
+        let sorter = imp.column_view.sorter();
+        let sorted_model = gtk::SortListModel::new(Some(items), sorter);
+        let selection_model = gtk::NoSelection::new(Some(sorted_model));
+        imp.column_view.set_model(Some(&selection_model));
+    }
+}
+
+#[gtk::template_callbacks]
+impl ErrorsPage {
+    #[template_callback]
+    fn count_fraction(_: &glib::Object, entry: Option<&ErrorItem>) -> f64 {
+        entry.map_or(0.0, |e| e.count() as f64 / e.total() as f64)
+    }
+
+    #[template_callback]
+    fn grouping_toggled(&self) {
+        self.update_items();
+    }
+
+    /// Filters the diagram to the errors of the activated group, along with
+    /// the calls they reply to
+    #[template_callback]
+    fn column_view_activate(&self, position: u32) {
+        let Some(model) = self.model() else {
+            return;
+        };
+
+        let Some(item) = self
+            .imp()
+            .column_view
+            .model()
+            .and_then(|selection_model| selection_model.item(position))
+            .and_downcast::<ErrorItem>()
+        else {
+            return;
+        };
+
+        model.set_message_subset_filter(item.messages().iter().cloned());
+
+        if let Some(dialog) = self.ancestor(adw::Dialog::static_type()) {
+            dialog.downcast::<adw::Dialog>().unwrap().close();
+        }
+    }
+}
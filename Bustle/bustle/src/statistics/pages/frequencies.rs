@@ -1,15 +1,29 @@
-use adw::subclass::prelude::*;
-use gtk::{gio, glib, prelude::*};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+};
 
-use crate::{filtered_message_model::FilteredMessageModel, statistics::FrequencyItem};
+use adw::subclass::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
+
+use crate::{
+    filtered_message_model::FilteredMessageModel,
+    message::{Message, ReceiveIndex},
+    message_type::MessageType,
+    statistics::FrequencyItem,
+};
 
 mod imp {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     use super::*;
     use crate::statistics::pages::ProgressCell;
 
-    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[derive(Debug, glib::Properties, gtk::CompositeTemplate)]
     #[properties(wrapper_type = super::FrequenciesPage)]
     #[template(resource = "/org/freedesktop/Bustle/ui/frequencies_page.ui")]
     pub struct FrequenciesPage {
@@ -23,6 +37,29 @@ mod imp {
         pub(super) member_column: TemplateChild<gtk::ColumnViewColumn>,
         #[template_child]
         pub(super) frequency_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        pub(super) items: gio::ListStore,
+        pub(super) items_map: RefCell<HashMap<(String, MessageType), FrequencyItem>>,
+        /// Number of messages that are not method returns
+        pub(super) total: Cell<u32>,
+        /// Item of each accounted message, by its receive index
+        pub(super) accounted: RefCell<BTreeMap<ReceiveIndex, FrequencyItem>>,
+    }
+
+    impl Default for FrequenciesPage {
+        fn default() -> Self {
+            Self {
+                model: Default::default(),
+                column_view: Default::default(),
+                message_type_column: Default::default(),
+                member_column: Default::default(),
+                frequency_column: Default::default(),
+                items: gio::ListStore::new::<FrequencyItem>(),
+                items_map: Default::default(),
+                total: Default::default(),
+                accounted: Default::default(),
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -70,34 +107,35 @@ mod imp {
 
     impl FrequenciesPage {
         fn set_model(&self, model: &FilteredMessageModel) {
-            let mut n_items = model.n_items();
-
-            let frequencies = gio::ListStore::new::<FrequencyItem>();
-            let mut frequencies_map = std::collections::HashMap::new();
-            for message in model.iter() {
-                let message_type = message.message_type();
-                if message_type.is_method_return() {
-                    n_items -= 1;
-                    continue;
-                }
-                let member_name = message.member_markup(true);
-
-                frequencies_map
-                    .entry((member_name, message_type))
-                    .and_modify(|count| *count += 1)
-                    .or_insert(1);
-            }
-
-            for ((member, message_type), count) in frequencies_map.iter() {
-                frequencies.append(&FrequencyItem::new(member, *count, n_items, *message_type))
-            }
+            let obj = self.obj();
 
             self.column_view
                 .sort_by_column(Some(&self.frequency_column), gtk::SortType::Descending);
             let sorter = self.column_view.sorter();
-            let sorted_model = gtk::SortListModel::new(Some(frequencies), sorter);
+            let sorted_model = gtk::SortListModel::new(Some(self.items.clone()), sorter);
             let selection_model = gtk::NoSelection::new(Some(sorted_model));
             self.column_view.set_model(Some(&selection_model));
+
+            model.connect_items_changed(
+                clone!(@weak obj => move |model, position, removed, added| {
+                    // Messages are only appended while recording and evicted
+                    // from the front, anything else means the filters changed
+                    if removed == 0 && position + added == model.n_items() {
+                        obj.add_messages((position..model.n_items()).map(|i| {
+                            model.item(i).and_downcast::<Message>().unwrap()
+                        }));
+                    } else if position == 0 && added == 0 {
+                        match model.item(0).and_downcast::<Message>() {
+                            Some(first) => obj.remove_messages_before(first.receive_index()),
+                            None => obj.reset(model),
+                        }
+                    } else {
+                        obj.reset(model);
+                    }
+                }),
+            );
+
+            obj.reset(model);
             self.model.set(Some(model.clone()));
         }
     }
@@ -108,6 +146,92 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Bin;
 }
 
+impl FrequenciesPage {
+    /// Recomputes the frequencies from all the messages of `model`
+    fn reset(&self, model: &FilteredMessageModel) {
+        let imp = self.imp();
+
+        imp.items.remove_all();
+        imp.items_map.borrow_mut().clear();
+        imp.total.set(0);
+        imp.accounted.borrow_mut().clear();
+
+        self.add_messages(model.iter());
+    }
+
+    /// Accounts `messages` in the frequencies, updating the existing items in
+    /// place
+    fn add_messages(&self, messages: impl Iterator<Item = Message>) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let mut new_items = Vec::new();
+        let mut total = imp.total.get();
+        for message in messages {
+            let message_type = message.message_type();
+            if message_type.is_method_return() {
+                continue;
+            }
+            total += 1;
+
+            let member_name = message.member_markup(true);
+            let item = match items_map.get(&(member_name.clone(), message_type)) {
+                Some(item) => {
+                    item.set_count(item.count() + 1);
+                    item.clone()
+                }
+                None => {
+                    let item = FrequencyItem::new(&member_name, 1, total, message_type);
+                    items_map.insert((member_name, message_type), item.clone());
+                    new_items.push(item.clone());
+                    item
+                }
+            };
+            accounted.insert(message.receive_index(), item);
+        }
+
+        if total != imp.total.get() {
+            imp.total.set(total);
+            for item in items_map.values() {
+                item.set_total(total);
+            }
+        }
+        imp.items.extend_from_slice(&new_items);
+    }
+
+    /// Takes back the messages received before `receive_index` from the
+    /// frequencies, once they were evicted
+    fn remove_messages_before(&self, receive_index: ReceiveIndex) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let retained = accounted.split_off(&receive_index);
+        let evicted = mem::replace(&mut *accounted, retained);
+        if evicted.is_empty() {
+            return;
+        }
+
+        for item in evicted.values() {
+            item.set_count(item.count() - 1);
+
+            if item.count() == 0 {
+                items_map.remove(&(item.member(), item.message_type()));
+                if let Some(position) = imp.items.find(item) {
+                    imp.items.remove(position);
+                }
+            }
+        }
+
+        let total = imp.total.get() - evicted.len() as u32;
+        imp.total.set(total);
+        for item in items_map.values() {
+            item.set_total(total);
+        }
+    }
+}
+
 #[gtk::template_callbacks]
 impl FrequenciesPage {
     #[template_callback]
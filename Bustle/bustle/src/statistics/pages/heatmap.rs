@@ -0,0 +1,240 @@
+use gtk::{
+    gdk, glib,
+    graphene::{Point, Rect},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use crate::statistics::MatrixItem;
+
+const CELL_SIZE: f32 = 28.0;
+const LABEL_PADDING: f32 = 6.0;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct Heatmap {
+        pub(super) senders: RefCell<Vec<String>>,
+        pub(super) destinations: RefCell<Vec<String>>,
+        /// Counts indexed by sender, then destination
+        pub(super) counts: RefCell<Vec<Vec<u32>>>,
+        pub(super) max_count: RefCell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Heatmap {
+        const NAME: &'static str = "BustleHeatmap";
+        type Type = super::Heatmap;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("heatmap");
+            klass.set_accessible_role(gtk::AccessibleRole::Img);
+        }
+    }
+
+    impl ObjectImpl for Heatmap {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_has_tooltip(true);
+            obj.connect_query_tooltip(|obj, x, y, _, tooltip| {
+                let Some(text) = obj.tooltip_text_at(x as f32, y as f32) else {
+                    return false;
+                };
+                tooltip.set_text(Some(&text));
+                true
+            });
+        }
+    }
+
+    impl WidgetImpl for Heatmap {
+        fn measure(&self, orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            let obj = self.obj();
+            let (row_labels_width, column_labels_height) = obj.labels_size();
+
+            let size = match orientation {
+                gtk::Orientation::Horizontal => {
+                    row_labels_width + self.destinations.borrow().len() as f32 * CELL_SIZE
+                }
+                _ => column_labels_height + self.senders.borrow().len() as f32 * CELL_SIZE,
+            };
+
+            (size as i32, size as i32, -1, -1)
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            let obj = self.obj();
+            let (row_labels_width, column_labels_height) = obj.labels_size();
+
+            let color = obj.color();
+            let max_count = *self.max_count.borrow();
+            let layout = obj.create_pango_layout(None);
+
+            // Row labels, right-aligned against the cells
+            for (i, sender) in self.senders.borrow().iter().enumerate() {
+                layout.set_text(sender);
+                let (_, logical) = layout.pixel_extents();
+                snapshot.save();
+                snapshot.translate(&Point::new(
+                    row_labels_width - LABEL_PADDING - logical.width() as f32,
+                    column_labels_height + i as f32 * CELL_SIZE
+                        + (CELL_SIZE - logical.height() as f32) / 2.0,
+                ));
+                snapshot.append_layout(&layout, &color);
+                snapshot.restore();
+            }
+
+            // Column labels, rotated to read bottom to top
+            for (j, destination) in self.destinations.borrow().iter().enumerate() {
+                layout.set_text(destination);
+                let (_, logical) = layout.pixel_extents();
+                snapshot.save();
+                snapshot.translate(&Point::new(
+                    row_labels_width
+                        + j as f32 * CELL_SIZE
+                        + (CELL_SIZE - logical.height() as f32) / 2.0,
+                    column_labels_height - LABEL_PADDING,
+                ));
+                snapshot.rotate(-90.0);
+                snapshot.append_layout(&layout, &color);
+                snapshot.restore();
+            }
+
+            let cell_color = obj.cell_color();
+            for (i, row) in self.counts.borrow().iter().enumerate() {
+                for (j, count) in row.iter().enumerate() {
+                    let bounds = Rect::new(
+                        row_labels_width + j as f32 * CELL_SIZE + 1.0,
+                        column_labels_height + i as f32 * CELL_SIZE + 1.0,
+                        CELL_SIZE - 2.0,
+                        CELL_SIZE - 2.0,
+                    );
+
+                    if *count == 0 || max_count == 0 {
+                        snapshot.append_color(&color.with_alpha(color.alpha() * 0.05), &bounds);
+                        continue;
+                    }
+
+                    let fraction = *count as f32 / max_count as f32;
+                    snapshot.append_color(
+                        &cell_color.with_alpha(0.15 + 0.85 * fraction),
+                        &bounds,
+                    );
+                }
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct Heatmap(ObjectSubclass<imp::Heatmap>)
+        @extends gtk::Widget;
+}
+
+impl Heatmap {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Replaces the displayed counts with the ones of `items`
+    pub fn set_items(&self, items: &[MatrixItem]) {
+        let imp = self.imp();
+
+        let mut senders = items
+            .iter()
+            .map(|item| item.sender_label())
+            .collect::<Vec<_>>();
+        senders.sort();
+        senders.dedup();
+        let mut destinations = items
+            .iter()
+            .map(|item| item.destination_label())
+            .collect::<Vec<_>>();
+        destinations.sort();
+        destinations.dedup();
+
+        let mut counts = vec![vec![0; destinations.len()]; senders.len()];
+        for item in items {
+            let i = senders.binary_search(&item.sender_label()).unwrap();
+            let j = destinations
+                .binary_search(&item.destination_label())
+                .unwrap();
+            counts[i][j] = item.count();
+        }
+
+        imp.max_count
+            .replace(items.iter().map(|item| item.count()).max().unwrap_or(0));
+        imp.senders.replace(senders);
+        imp.destinations.replace(destinations);
+        imp.counts.replace(counts);
+
+        self.queue_resize();
+    }
+
+    fn cell_color(&self) -> gdk::RGBA {
+        match adw::StyleManager::default().color_scheme() {
+            adw::ColorScheme::Default
+            | adw::ColorScheme::ForceLight
+            | adw::ColorScheme::PreferLight => crate::colors::BLUE_3,
+            adw::ColorScheme::PreferDark | adw::ColorScheme::ForceDark => crate::colors::BLUE_2,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the width of the row labels and the height of the column labels
+    fn labels_size(&self) -> (f32, f32) {
+        let imp = self.imp();
+
+        let layout = self.create_pango_layout(None);
+        let max_width = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| {
+                    layout.set_text(name);
+                    layout.pixel_size().0
+                })
+                .max()
+                .unwrap_or(0) as f32
+                + 2.0 * LABEL_PADDING
+        };
+
+        (
+            max_width(&imp.senders.borrow()),
+            max_width(&imp.destinations.borrow()),
+        )
+    }
+
+    fn tooltip_text_at(&self, x: f32, y: f32) -> Option<String> {
+        let imp = self.imp();
+        let (row_labels_width, column_labels_height) = self.labels_size();
+
+        if x < row_labels_width || y < column_labels_height {
+            return None;
+        }
+
+        let i = ((y - column_labels_height) / CELL_SIZE) as usize;
+        let j = ((x - row_labels_width) / CELL_SIZE) as usize;
+
+        let senders = imp.senders.borrow();
+        let destinations = imp.destinations.borrow();
+        let count = *imp.counts.borrow().get(i)?.get(j)?;
+
+        Some(format!(
+            "{} → {}: {}",
+            senders.get(i)?,
+            destinations.get(j)?,
+            count
+        ))
+    }
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+
+use adw::subclass::prelude::*;
+use anyhow::{Context, Result};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
+use zbus::names::BusName;
+
+use crate::{
+    bus_name_item::LookupPoint,
+    bus_type::BusType,
+    filtered_message_model::FilteredMessageModel,
+    i18n::gettext_f,
+    message::Message,
+    statistics::MatrixItem,
+    timestamp::Timestamp,
+};
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::statistics::pages::{Heatmap, ProgressCell};
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::MatrixPage)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/matrix_page.ui")]
+    pub struct MatrixPage {
+        #[property(get, set = Self::set_model)]
+        pub(super) model: RefCell<Option<FilteredMessageModel>>,
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) heatmap_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub(super) export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub(super) heatmap: TemplateChild<Heatmap>,
+        #[template_child]
+        pub(super) column_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        pub(super) sender_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) destination_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) count_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) bytes_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) latency_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        pub(super) items: RefCell<Vec<MatrixItem>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MatrixPage {
+        const NAME: &'static str = "BustleMatrixPage";
+        type Type = super::MatrixPage;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Heatmap::ensure_type();
+            ProgressCell::ensure_type();
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MatrixPage {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.sender_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &MatrixItem::this_expression("sender-label"),
+                ))));
+            self.destination_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &MatrixItem::this_expression("destination-label"),
+                ))));
+            self.count_column
+                .set_sorter(Some(&gtk::NumericSorter::new(Some(
+                    &MatrixItem::this_expression("count"),
+                ))));
+            self.bytes_column
+                .set_sorter(Some(&gtk::NumericSorter::new(Some(
+                    &MatrixItem::this_expression("bytes"),
+                ))));
+            self.latency_column
+                .set_sorter(Some(&gtk::CustomSorter::new(|a, b| {
+                    let a = a.downcast_ref::<MatrixItem>().unwrap();
+                    let b = b.downcast_ref::<MatrixItem>().unwrap();
+                    a.latency().cmp(&b.latency()).into()
+                })));
+        }
+    }
+
+    impl WidgetImpl for MatrixPage {}
+
+    impl BinImpl for MatrixPage {}
+
+    impl MatrixPage {
+        fn set_model(&self, model: &FilteredMessageModel) {
+            let items = matrix_items(model);
+
+            let store = gio::ListStore::new::<MatrixItem>();
+            store.extend_from_slice(&items);
+            self.heatmap.set_items(&items);
+            self.export_button.set_sensitive(!items.is_empty());
+            self.items.replace(items);
+
+            self.column_view
+                .sort_by_column(Some(&self.count_column), gtk::SortType::Descending);
+            let sorter = self.column_view.sorter();
+            let sorted_model = gtk::SortListModel::new(Some(store), sorter);
+            let selection_model = gtk::NoSelection::new(Some(sorted_model));
+            self.column_view.set_model(Some(&selection_model));
+            self.model.set(Some(model.clone()));
+
+            self.obj().update_visible_child();
+        }
+    }
+}
+
+glib::wrapper! {
+     pub struct MatrixPage(ObjectSubclass<imp::MatrixPage>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+#[gtk::template_callbacks]
+impl MatrixPage {
+    #[template_callback]
+    fn count_fraction(_: &glib::Object, entry: Option<&MatrixItem>) -> f64 {
+        entry.map_or(0.0, |e| e.count() as f64 / e.max_count().max(1) as f64)
+    }
+
+    #[template_callback]
+    fn bytes(_: &glib::Object, entry: Option<&MatrixItem>) -> glib::GString {
+        entry
+            .map(|e| glib::format_size(e.bytes()))
+            .unwrap_or_default()
+    }
+
+    #[template_callback]
+    fn latency(_: &glib::Object, entry: Option<&MatrixItem>) -> String {
+        entry
+            .map(|e| {
+                gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "{milliseconds} ms",
+                    &[(
+                        "milliseconds",
+                        &format!("{:.2}", e.latency().as_millis_f64()),
+                    )],
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    #[template_callback]
+    fn view_toggled(&self) {
+        self.update_visible_child();
+    }
+
+    #[template_callback]
+    fn export_clicked(&self) {
+        glib::spawn_future_local(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.export_csv().await {
+                tracing::error!("Failed to export matrix as CSV: {:?}", err);
+            }
+        }));
+    }
+
+    fn update_visible_child(&self) {
+        let imp = self.imp();
+
+        if imp.items.borrow().is_empty() {
+            imp.stack.set_visible_child_name("empty");
+        } else if imp.heatmap_button.is_active() {
+            imp.stack.set_visible_child_name("heatmap");
+        } else {
+            imp.stack.set_visible_child_name("table");
+        }
+    }
+
+    async fn export_csv(&self) -> Result<()> {
+        let csv = to_csv(&self.imp().items.borrow());
+
+        let filter = gtk::FileFilter::new();
+        filter.set_property("name", gettext("CSV Files"));
+        filter.add_mime_type("text/csv");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Export Matrix"))
+            .filters(&filters)
+            .initial_name("matrix.csv")
+            .modal(true)
+            .build();
+
+        let root = self.root().and_downcast::<gtk::Window>();
+        let file = match chooser.save_future(root.as_ref()).await {
+            Err(err) if err.matches(gtk::DialogError::Dismissed) => return Ok(()),
+            res => res?,
+        };
+        file.replace_contents_future(
+            csv,
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|e| e.1)
+        .context("Failed to write CSV")?;
+
+        Ok(())
+    }
+}
+
+/// Name used for the destination of signals that are not sent to a
+/// specific peer
+const BROADCAST: &str = "(broadcast)";
+
+fn matrix_items(model: &FilteredMessageModel) -> Vec<MatrixItem> {
+    #[derive(Default)]
+    struct Cell {
+        count: u32,
+        bytes: u64,
+        latency: Timestamp,
+    }
+
+    let mut cells: HashMap<(BusType, String, String), Cell> = HashMap::new();
+
+    for message in model.iter() {
+        let Some(sender) = message
+            .sender()
+            .and_then(|sender| resolve_name(model, &sender.into(), &message))
+        else {
+            continue;
+        };
+
+        let destination = match message.header().destination() {
+            Some(destination) => match resolve_name(model, destination, &message) {
+                Some(destination) => destination,
+                None => continue,
+            },
+            None if message.message_type().is_signal() => BROADCAST.to_string(),
+            None => continue,
+        };
+
+        let cell = cells
+            .entry((message.bus_type(), sender, destination))
+            .or_default();
+        cell.count += 1;
+        cell.bytes += message.len() as u64;
+        cell.latency += call_latency(&message);
+    }
+
+    let max_count = cells.values().map(|cell| cell.count).max().unwrap_or(0);
+    let shows_bus_type = cells
+        .keys()
+        .map(|(bus_type, _, _)| bus_type)
+        .collect::<HashSet<_>>()
+        .len()
+        > 1;
+
+    cells
+        .into_iter()
+        .map(|((bus_type, sender, destination), cell)| {
+            let item = MatrixItem::new(
+                bus_type,
+                &sender,
+                &destination,
+                cell.count,
+                max_count,
+                cell.bytes,
+                cell.latency,
+            );
+            item.set_shows_bus_type(shows_bus_type);
+            item
+        })
+        .collect()
+}
+
+/// Returns the time a call waited for its reply, so that latency is
+/// accounted to the caller and callee pair only once
+fn call_latency(message: &Message) -> Timestamp {
+    if message.message_type().is_method_call() {
+        message.response_time().unwrap_or_default()
+    } else {
+        Timestamp::default()
+    }
+}
+
+/// Resolves `name` to the first well-known name it owned when `message` was
+/// received, or None if it is filtered out
+fn resolve_name(
+    model: &FilteredMessageModel,
+    name: &BusName<'_>,
+    message: &Message,
+) -> Option<String> {
+    let bus_name_item = model.filtered_bus_names().get(message.bus_type(), name)?;
+    let wk_names = bus_name_item.wk_names(LookupPoint::from(message.receive_index()));
+    Some(
+        wk_names
+            .first()
+            .map_or_else(|| name.to_string(), |wk_name| wk_name.to_string()),
+    )
+}
+
+fn to_csv(items: &[MatrixItem]) -> String {
+    let mut csv = String::from("bus,sender,destination,messages,bytes,total_latency_ms\n");
+    for item in items {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{:.3}\n",
+            item.bus_type().interface_name(),
+            csv_field(&item.sender()),
+            csv_field(&item.destination()),
+            item.count(),
+            item.bytes(),
+            item.latency().as_millis_f64()
+        ));
+    }
+    csv
+}
+
+/// Quotes `field` if it contains characters that are special in CSV
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("org.freedesktop.DBus"), "org.freedesktop.DBus");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn bus_type_labels() {
+        let item = MatrixItem::new(
+            BusType::System,
+            "org.freedesktop.login1",
+            BROADCAST,
+            2,
+            3,
+            100,
+            Timestamp::default(),
+        );
+        assert_eq!(item.sender_label(), "org.freedesktop.login1");
+
+        item.set_shows_bus_type(true);
+        assert_eq!(item.sender_label(), "org.freedesktop.login1 (system)");
+        assert_eq!(item.destination_label(), "(broadcast) (system)");
+        assert_eq!(
+            to_csv(&[item]),
+            "bus,sender,destination,messages,bytes,total_latency_ms\n\
+             system,org.freedesktop.login1,(broadcast),2,100,0.000\n"
+        );
+    }
+}
@@ -1,9 +1,21 @@
 mod durations;
+mod errors;
 mod frequencies;
+mod heatmap;
+mod matrix;
+mod ownership;
+mod ownership_timeline;
 mod progress_cell;
 mod sizes;
+mod unanswered;
 
 pub use durations::DurationsPage;
+pub use errors::ErrorsPage;
 pub use frequencies::FrequenciesPage;
+pub use heatmap::Heatmap;
+pub use matrix::MatrixPage;
+pub use ownership::OwnershipPage;
+pub use ownership_timeline::OwnershipTimeline;
 pub use progress_cell::ProgressCell;
 pub use sizes::SizesPage;
+pub use unanswered::UnansweredPage;
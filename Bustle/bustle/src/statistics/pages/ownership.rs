@@ -0,0 +1,353 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use adw::subclass::prelude::*;
+use gtk::{
+    glib::{self, closure_local},
+    prelude::*,
+};
+use indexmap::IndexSet;
+use once_cell::sync::Lazy;
+use zbus::{
+    fdo::DBusProxy,
+    names::{BusName, WellKnownName},
+    ProxyDefault,
+};
+
+use crate::{
+    bus_name_item::BusNameItem,
+    bus_type::BusType,
+    filtered_message_model::FilteredMessageModel,
+    message::{Message, ReceiveIndex},
+    message_list::MessageList,
+    statistics::pages::ownership_timeline::{Lane, Mark, Ownership},
+};
+
+/// Reply of `RequestName` when the caller was put in the queue of the name
+const REQUEST_NAME_REPLY_IN_QUEUE: u32 = 2;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use glib::subclass::Signal;
+
+    use super::*;
+    use crate::statistics::pages::OwnershipTimeline;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::OwnershipPage)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/ownership_page.ui")]
+    pub struct OwnershipPage {
+        #[property(get, set = Self::set_model)]
+        pub(super) model: RefCell<Option<FilteredMessageModel>>,
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) timeline: TemplateChild<OwnershipTimeline>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwnershipPage {
+        const NAME: &'static str = "BustleOwnershipPage";
+        type Type = super::OwnershipPage;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            OwnershipTimeline::ensure_type();
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for OwnershipPage {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("show-message-request")
+                    .param_types([Message::static_type()])
+                    .build()]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for OwnershipPage {}
+
+    impl BinImpl for OwnershipPage {}
+
+    impl OwnershipPage {
+        fn set_model(&self, model: &FilteredMessageModel) {
+            let (lanes, duration) = model
+                .message_list()
+                .map(|message_list| timeline_lanes(&message_list))
+                .unwrap_or_default();
+
+            if lanes.is_empty() {
+                self.stack.set_visible_child_name("empty");
+            } else {
+                self.stack.set_visible_child_name("timeline");
+            }
+            self.timeline.set_lanes(lanes, duration);
+
+            self.model.set(Some(model.clone()));
+        }
+    }
+}
+
+glib::wrapper! {
+     pub struct OwnershipPage(ObjectSubclass<imp::OwnershipPage>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+#[gtk::template_callbacks]
+impl OwnershipPage {
+    pub fn connect_show_message_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &Message) + 'static,
+    {
+        self.connect_closure(
+            "show-message-request",
+            false,
+            closure_local!(|obj: &Self, message: &Message| {
+                f(obj, message);
+            }),
+        )
+    }
+
+    #[template_callback]
+    fn timeline_show_message_request(&self, message: &Message) {
+        self.emit_by_name::<()>("show-message-request", &[message]);
+    }
+}
+
+/// A well-known name owned by `owner` from `start` until `end`, or until
+/// the end of the recording if None
+#[derive(Debug, PartialEq, Eq)]
+struct Span {
+    owner: String,
+    start: ReceiveIndex,
+    end: Option<ReceiveIndex>,
+}
+
+/// Returns the lanes of every well-known name in `message_list`, and the
+/// time covered by the messages in milliseconds
+fn timeline_lanes(message_list: &MessageList) -> (Vec<Lane>, f64) {
+    let start = message_list
+        .message_at(0)
+        .map_or(Duration::ZERO, |first| first.timestamp().into());
+    let elapsed = |message: &Message| {
+        Duration::from(message.timestamp())
+            .saturating_sub(start)
+            .as_secs_f64()
+            * 1000.0
+    };
+    let mark = |receive_index: ReceiveIndex| {
+        let message = message_list.message_by_receive_index(receive_index);
+        Mark {
+            time: message.as_ref().map_or(0.0, elapsed),
+            message,
+        }
+    };
+
+    let mut logs: BTreeMap<BusType, Vec<_>> = BTreeMap::new();
+    for bus_name_item in message_list
+        .bus_names()
+        .iter::<BusNameItem>()
+        .filter_map(Result::ok)
+        .filter(|bus_name_item| matches!(bus_name_item.name(), BusName::Unique(_)))
+    {
+        logs.entry(bus_name_item.bus_type()).or_default().push((
+            bus_name_item.name().to_string(),
+            bus_name_item.wk_name_log(),
+        ));
+    }
+
+    let mut lanes = BTreeMap::new();
+    for (bus_type, logs) in logs {
+        for (name, spans) in ownership_spans(logs) {
+            let ownerships = spans
+                .into_iter()
+                .map(|span| Ownership {
+                    owner: span.owner,
+                    start: mark(span.start),
+                    end: span.end.map(mark),
+                })
+                .collect();
+            lanes.insert(
+                (bus_type, name.clone()),
+                Lane {
+                    name,
+                    ownerships,
+                    queued: Vec::new(),
+                },
+            );
+        }
+    }
+
+    for message in message_list.iter::<Message>().filter_map(Result::ok) {
+        let Some((name, owner)) = queued_name(&message) else {
+            continue;
+        };
+        lanes
+            .entry((message.bus_type(), name.clone()))
+            .or_insert_with(|| Lane {
+                name,
+                ownerships: Vec::new(),
+                queued: Vec::new(),
+            })
+            .queued
+            .push((
+                owner,
+                Mark {
+                    time: elapsed(&message),
+                    message: Some(message),
+                },
+            ));
+    }
+
+    let duration = message_list.latest_timestamp().map_or(0.0, |last| {
+        Duration::from(last).saturating_sub(start).as_secs_f64() * 1000.0
+    });
+
+    (lanes.into_values().collect(), duration)
+}
+
+/// Returns the requested name and the caller if `message` is the reply to a
+/// `RequestName` call that put the caller in the queue of the name
+fn queued_name(message: &Message) -> Option<(String, String)> {
+    if !message.message_type().is_method_return() {
+        return None;
+    }
+
+    let call = message.associated_message()?;
+    let call_header = call.header();
+    if call_header.interface().cloned().as_deref() != DBusProxy::INTERFACE
+        || call_header.member().cloned().as_deref() != Some("RequestName")
+    {
+        return None;
+    }
+
+    let reply = message.body().deserialize::<u32>().ok()?;
+    if reply != REQUEST_NAME_REPLY_IN_QUEUE {
+        return None;
+    }
+
+    let (name, _flags) = call.body().deserialize::<(String, u32)>().ok()?;
+    Some((name, call.sender()?.to_string()))
+}
+
+/// Returns the spans of ownership of every well-known name, given the logs
+/// of the well-known names owned by each unique name
+fn ownership_spans(
+    logs: impl IntoIterator<
+        Item = (
+            String,
+            Vec<(ReceiveIndex, IndexSet<WellKnownName<'static>>)>,
+        ),
+    >,
+) -> BTreeMap<String, Vec<Span>> {
+    let mut spans: BTreeMap<String, Vec<Span>> = BTreeMap::new();
+
+    for (owner, log) in logs {
+        let mut owned: HashMap<WellKnownName<'static>, ReceiveIndex> = HashMap::new();
+
+        for (receive_index, wk_names) in log {
+            owned.retain(|wk_name, start| {
+                let is_still_owned = wk_names.contains(wk_name);
+                if !is_still_owned {
+                    spans.entry(wk_name.to_string()).or_default().push(Span {
+                        owner: owner.clone(),
+                        start: *start,
+                        end: Some(receive_index),
+                    });
+                }
+                is_still_owned
+            });
+
+            for wk_name in wk_names {
+                owned.entry(wk_name).or_insert(receive_index);
+            }
+        }
+
+        for (wk_name, start) in owned {
+            spans.entry(wk_name.to_string()).or_default().push(Span {
+                owner: owner.clone(),
+                start,
+                end: None,
+            });
+        }
+    }
+
+    for spans in spans.values_mut() {
+        spans.sort_by_key(|span| span.start);
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_entry(
+        index: u32,
+        wk_names: &[&str],
+    ) -> (ReceiveIndex, IndexSet<WellKnownName<'static>>) {
+        (
+            ReceiveIndex::new(index),
+            wk_names
+                .iter()
+                .map(|wk_name| WellKnownName::try_from(wk_name.to_string()).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn spans() {
+        let spans = ownership_spans([
+            (
+                ":1.1".to_string(),
+                vec![
+                    log_entry(1, &["org.Foo"]),
+                    log_entry(3, &["org.Foo", "org.Bar"]),
+                    log_entry(5, &["org.Bar"]),
+                ],
+            ),
+            (
+                ":1.2".to_string(),
+                vec![log_entry(5, &["org.Foo"]), log_entry(8, &[])],
+            ),
+        ]);
+
+        assert_eq!(
+            spans["org.Foo"],
+            [
+                Span {
+                    owner: ":1.1".to_string(),
+                    start: ReceiveIndex::new(1),
+                    end: Some(ReceiveIndex::new(5)),
+                },
+                Span {
+                    owner: ":1.2".to_string(),
+                    start: ReceiveIndex::new(5),
+                    end: Some(ReceiveIndex::new(8)),
+                },
+            ]
+        );
+        assert_eq!(
+            spans["org.Bar"],
+            [Span {
+                owner: ":1.1".to_string(),
+                start: ReceiveIndex::new(3),
+                end: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use gettextrs::gettext;
+use gtk::{
+    gdk,
+    glib::{self, clone, closure_local},
+    graphene::{Point, Rect},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::sync::Lazy;
+
+use crate::{i18n::gettext_f, message::Message};
+
+const LANE_HEIGHT: f32 = 28.0;
+const LABEL_PADDING: f32 = 6.0;
+const MIN_TIMELINE_WIDTH: f32 = 480.0;
+const MARKER_SIZE: f32 = 6.0;
+
+/// A point of the timeline, in milliseconds since the first message
+#[derive(Debug, Clone)]
+pub struct Mark {
+    pub time: f64,
+    /// The message that caused the change, if it is still in the list
+    pub message: Option<Message>,
+}
+
+/// A period during which a well-known name had the same owner
+#[derive(Debug, Clone)]
+pub struct Ownership {
+    pub owner: String,
+    pub start: Mark,
+    /// None if the name was still owned at the end of the recording
+    pub end: Option<Mark>,
+}
+
+/// The history of a single well-known name
+#[derive(Debug, Clone)]
+pub struct Lane {
+    pub name: String,
+    /// Ownerships in chronological order, with gaps where it was unowned
+    pub ownerships: Vec<Ownership>,
+    /// Unique names that were put in the queue of the name
+    pub queued: Vec<(String, Mark)>,
+}
+
+enum Hit<'a> {
+    Replacement(&'a Ownership, &'a Ownership),
+    Queued(&'a str, &'a Mark),
+    Ownership(&'a Ownership),
+    /// Unowned, after the given ownership ended
+    Gap(Option<&'a Ownership>),
+}
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use glib::subclass::Signal;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct OwnershipTimeline {
+        pub(super) lanes: RefCell<Vec<Lane>>,
+        /// Time of the last message, in milliseconds since the first one
+        pub(super) duration: Cell<f64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwnershipTimeline {
+        const NAME: &'static str = "BustleOwnershipTimeline";
+        type Type = super::OwnershipTimeline;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("ownershiptimeline");
+            klass.set_accessible_role(gtk::AccessibleRole::Img);
+        }
+    }
+
+    impl ObjectImpl for OwnershipTimeline {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_has_tooltip(true);
+            obj.connect_query_tooltip(|obj, x, y, _, tooltip| {
+                let Some(text) = obj.tooltip_text_at(x as f32, y as f32) else {
+                    return false;
+                };
+                tooltip.set_text(Some(&text));
+                true
+            });
+
+            let gesture = gtk::GestureClick::new();
+            gesture.connect_released(clone!(@weak obj => move |_, _, x, y| {
+                if let Some(message) = obj.message_at(x as f32, y as f32) {
+                    obj.emit_by_name::<()>("show-message-request", &[&message]);
+                }
+            }));
+            obj.add_controller(gesture);
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("show-message-request")
+                    .param_types([Message::static_type()])
+                    .build()]
+            });
+
+            SIGNALS.as_ref()
+        }
+    }
+
+    impl WidgetImpl for OwnershipTimeline {
+        fn measure(&self, orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            let size = match orientation {
+                gtk::Orientation::Horizontal => self.obj().labels_width() + MIN_TIMELINE_WIDTH,
+                _ => self.lanes.borrow().len() as f32 * LANE_HEIGHT,
+            };
+
+            (size as i32, size as i32, -1, -1)
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            let obj = self.obj();
+            let labels_width = obj.labels_width();
+
+            let color = obj.color();
+            let bar_colors = obj.bar_colors();
+            let layout = obj.create_pango_layout(None);
+
+            for (i, lane) in self.lanes.borrow().iter().enumerate() {
+                let lane_y = i as f32 * LANE_HEIGHT;
+
+                // Name labels, right-aligned against the lanes
+                layout.set_text(&lane.name);
+                let (_, logical) = layout.pixel_extents();
+                snapshot.save();
+                snapshot.translate(&Point::new(
+                    labels_width - LABEL_PADDING - logical.width() as f32,
+                    lane_y + (LANE_HEIGHT - logical.height() as f32) / 2.0,
+                ));
+                snapshot.append_layout(&layout, &color);
+                snapshot.restore();
+
+                // Background showing through where the name was unowned
+                snapshot.append_color(
+                    &color.with_alpha(color.alpha() * 0.05),
+                    &Rect::new(
+                        labels_width,
+                        lane_y + 1.0,
+                        obj.width() as f32 - labels_width,
+                        LANE_HEIGHT - 2.0,
+                    ),
+                );
+
+                for (j, ownership) in lane.ownerships.iter().enumerate() {
+                    let start_x = obj.time_to_x(labels_width, ownership.start.time);
+                    let end_x = obj.time_to_x(
+                        labels_width,
+                        ownership
+                            .end
+                            .as_ref()
+                            .map_or(self.duration.get(), |end| end.time),
+                    );
+                    let bounds = Rect::new(
+                        start_x,
+                        lane_y + 1.0,
+                        (end_x - start_x).max(1.0),
+                        LANE_HEIGHT - 2.0,
+                    );
+                    snapshot.append_color(&bar_colors[j % bar_colors.len()], &bounds);
+
+                    layout.set_text(&ownership.owner);
+                    let (_, logical) = layout.pixel_extents();
+                    if (logical.width() as f32) < bounds.width() - 2.0 * LABEL_PADDING {
+                        snapshot.save();
+                        snapshot.translate(&Point::new(
+                            start_x + LABEL_PADDING,
+                            lane_y + (LANE_HEIGHT - logical.height() as f32) / 2.0,
+                        ));
+                        snapshot.append_layout(&layout, &crate::colors::LIGHT_1);
+                        snapshot.restore();
+                    }
+                }
+
+                // Owners replaced by another one without the name being
+                // released in between
+                for (_, next) in replacements(lane) {
+                    snapshot.append_color(
+                        &color,
+                        &Rect::new(
+                            obj.time_to_x(labels_width, next.start.time) - 1.0,
+                            lane_y,
+                            2.0,
+                            LANE_HEIGHT,
+                        ),
+                    );
+                }
+
+                for (_, mark) in &lane.queued {
+                    snapshot.append_color(
+                        &color,
+                        &Rect::new(
+                            obj.time_to_x(labels_width, mark.time) - MARKER_SIZE / 2.0,
+                            lane_y + LANE_HEIGHT - MARKER_SIZE - 1.0,
+                            MARKER_SIZE,
+                            MARKER_SIZE,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Shows which unique name owned each well-known name over time
+    pub struct OwnershipTimeline(ObjectSubclass<imp::OwnershipTimeline>)
+        @extends gtk::Widget;
+}
+
+impl OwnershipTimeline {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn connect_show_message_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &Message) + 'static,
+    {
+        self.connect_closure(
+            "show-message-request",
+            false,
+            closure_local!(|obj: &Self, message: &Message| {
+                f(obj, message);
+            }),
+        )
+    }
+
+    /// Replaces the displayed lanes, spanning `duration` milliseconds
+    pub fn set_lanes(&self, lanes: Vec<Lane>, duration: f64) {
+        let imp = self.imp();
+
+        imp.lanes.replace(lanes);
+        imp.duration.set(duration);
+
+        self.queue_resize();
+    }
+
+    fn bar_colors(&self) -> [gdk::RGBA; 2] {
+        match adw::StyleManager::default().color_scheme() {
+            adw::ColorScheme::Default
+            | adw::ColorScheme::ForceLight
+            | adw::ColorScheme::PreferLight => [crate::colors::BLUE_3, crate::colors::BLUE_5],
+            adw::ColorScheme::PreferDark | adw::ColorScheme::ForceDark => {
+                [crate::colors::BLUE_2, crate::colors::BLUE_4]
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn labels_width(&self) -> f32 {
+        let layout = self.create_pango_layout(None);
+        self.imp()
+            .lanes
+            .borrow()
+            .iter()
+            .map(|lane| {
+                layout.set_text(&lane.name);
+                layout.pixel_size().0
+            })
+            .max()
+            .unwrap_or(0) as f32
+            + 2.0 * LABEL_PADDING
+    }
+
+    fn time_to_x(&self, labels_width: f32, time: f64) -> f32 {
+        let timeline_width = (self.width() as f32 - labels_width - LABEL_PADDING).max(0.0);
+        let duration = self.imp().duration.get();
+
+        if duration <= 0.0 {
+            return labels_width;
+        }
+
+        labels_width + (time / duration) as f32 * timeline_width
+    }
+
+    /// Calls `f` with what is drawn at the given coordinates
+    fn with_hit_at<T>(&self, x: f32, y: f32, f: impl FnOnce(Hit<'_>) -> T) -> Option<T> {
+        let lanes = self.imp().lanes.borrow();
+        let labels_width = self.labels_width();
+        if x < labels_width || y < 0.0 {
+            return None;
+        }
+        let lane = lanes.get((y / LANE_HEIGHT) as usize)?;
+
+        let is_near =
+            |mark: &Mark| (self.time_to_x(labels_width, mark.time) - x).abs() <= MARKER_SIZE / 2.0;
+
+        if let Some((previous, next)) = replacements(lane).find(|(_, next)| is_near(&next.start)) {
+            return Some(f(Hit::Replacement(previous, next)));
+        }
+        if let Some((owner, mark)) = lane.queued.iter().find(|(_, mark)| is_near(mark)) {
+            return Some(f(Hit::Queued(owner, mark)));
+        }
+
+        let previous = lane
+            .ownerships
+            .iter()
+            .take_while(|ownership| self.time_to_x(labels_width, ownership.start.time) <= x)
+            .last();
+        let hit = match previous {
+            Some(ownership)
+                if ownership
+                    .end
+                    .as_ref()
+                    .is_none_or(|end| x <= self.time_to_x(labels_width, end.time)) =>
+            {
+                Hit::Ownership(ownership)
+            }
+            previous => Hit::Gap(previous),
+        };
+        Some(f(hit))
+    }
+
+    /// Returns the message that changed the ownership drawn at the given
+    /// coordinates
+    fn message_at(&self, x: f32, y: f32) -> Option<Message> {
+        self.with_hit_at(x, y, |hit| match hit {
+            Hit::Replacement(_, next) => next.start.message.clone(),
+            Hit::Queued(_, mark) => mark.message.clone(),
+            Hit::Ownership(ownership) => ownership.start.message.clone(),
+            Hit::Gap(previous) => previous?.end.as_ref()?.message.clone(),
+        })
+        .flatten()
+    }
+
+    fn tooltip_text_at(&self, x: f32, y: f32) -> Option<String> {
+        self.with_hit_at(x, y, |hit| match hit {
+            Hit::Replacement(previous, next) => Some(gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "{previous} replaced by {next} at {time}",
+                &[
+                    ("previous", &previous.owner),
+                    ("next", &next.owner),
+                    ("time", &time_display(next.start.time)),
+                ],
+            )),
+            Hit::Queued(owner, mark) => Some(gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "{owner} queued at {time}",
+                &[("owner", owner), ("time", &time_display(mark.time))],
+            )),
+            Hit::Ownership(ownership) => Some(match &ownership.end {
+                Some(end) => gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "Owned by {owner} from {start} to {end}",
+                    &[
+                        ("owner", &ownership.owner),
+                        ("start", &time_display(ownership.start.time)),
+                        ("end", &time_display(end.time)),
+                    ],
+                ),
+                None => gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "Owned by {owner} from {start}",
+                    &[
+                        ("owner", &ownership.owner),
+                        ("start", &time_display(ownership.start.time)),
+                    ],
+                ),
+            }),
+            Hit::Gap(_) => Some(gettext("Unowned")),
+        })
+        .flatten()
+    }
+}
+
+impl Default for OwnershipTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the consecutive ownerships of `lane` where the name went
+/// straight from one owner to the next
+fn replacements(lane: &Lane) -> impl Iterator<Item = (&Ownership, &Ownership)> {
+    lane.ownerships
+        .windows(2)
+        .map(|pair| (&pair[0], &pair[1]))
+        .filter(|(previous, next)| {
+            previous
+                .end
+                .as_ref()
+                .is_some_and(|end| end.message.is_some() && end.message == next.start.message)
+        })
+}
+
+fn time_display(time: f64) -> String {
+    gettext_f(
+        // Translators: Do NOT translate the contents between '{' and '}', this
+        // is a variable name.
+        "{milliseconds} ms",
+        &[("milliseconds", &time.round().to_string())],
+    )
+}
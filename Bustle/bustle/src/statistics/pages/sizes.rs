@@ -1,18 +1,83 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+};
 
 use adw::subclass::prelude::*;
-use gtk::{gio, glib, prelude::*};
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
 
 use crate::{
-    filtered_message_model::FilteredMessageModel, message_type::MessageType, statistics::SizeItem,
+    filtered_message_model::FilteredMessageModel,
+    message::{Message, ReceiveIndex},
+    message_type::MessageType,
+    statistics::SizeItem,
 };
 
+/// Sizes of the messages accounted so far for an item, so that it can be
+/// updated without going through all the messages again
+#[derive(Debug, Default)]
+struct RunningSize {
+    count: usize,
+    sum: usize,
+    /// Number of messages of each length
+    lengths: BTreeMap<usize, usize>,
+}
+
+impl RunningSize {
+    fn push(&mut self, length: usize) {
+        self.count += 1;
+        self.sum += length;
+        *self.lengths.entry(length).or_default() += 1;
+    }
+
+    /// Takes back a message of `length` that was pushed before
+    fn remove(&mut self, length: usize) {
+        let Some(n_messages) = self.lengths.get_mut(&length) else {
+            debug_assert!(false, "no message of length {length} was pushed");
+            return;
+        };
+        *n_messages -= 1;
+        if *n_messages == 0 {
+            self.lengths.remove(&length);
+        }
+        self.count -= 1;
+        self.sum -= length;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn smallest(&self) -> usize {
+        self.lengths.keys().next().copied().unwrap_or_default()
+    }
+
+    fn largest(&self) -> usize {
+        self.lengths.keys().next_back().copied().unwrap_or_default()
+    }
+
+    fn mean(&self) -> usize {
+        self.sum / self.count.max(1)
+    }
+
+    /// Shows the sizes on `item`
+    fn update(&self, item: &SizeItem) {
+        item.set_smallest(self.smallest() as u32);
+        item.set_mean(self.mean() as u32);
+        item.set_largest(self.largest() as u32);
+    }
+}
+
 mod imp {
     use std::cell::RefCell;
 
     use super::*;
 
-    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[derive(Debug, glib::Properties, gtk::CompositeTemplate)]
     #[properties(wrapper_type = super::SizesPage)]
     #[template(resource = "/org/freedesktop/Bustle/ui/sizes_page.ui")]
     pub struct SizesPage {
@@ -30,6 +95,28 @@ mod imp {
         pub(super) mean_column: TemplateChild<gtk::ColumnViewColumn>,
         #[template_child]
         pub(super) smallest_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        pub(super) items: gio::ListStore,
+        pub(super) items_map: RefCell<HashMap<(MessageType, String), (SizeItem, RunningSize)>>,
+        /// Item and length of each accounted message, by its receive index
+        pub(super) accounted: RefCell<BTreeMap<ReceiveIndex, (SizeItem, usize)>>,
+    }
+
+    impl Default for SizesPage {
+        fn default() -> Self {
+            Self {
+                model: Default::default(),
+                column_view: Default::default(),
+                message_type_column: Default::default(),
+                member_column: Default::default(),
+                largest_column: Default::default(),
+                mean_column: Default::default(),
+                smallest_column: Default::default(),
+                items: gio::ListStore::new::<SizeItem>(),
+                items_map: Default::default(),
+                accounted: Default::default(),
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -91,40 +178,35 @@ mod imp {
 
     impl SizesPage {
         fn set_model(&self, model: &FilteredMessageModel) {
-            let mut sizes_map: HashMap<(MessageType, String), (usize, Vec<usize>)> =
-                std::collections::HashMap::new();
-            let sizes = gio::ListStore::new::<SizeItem>();
-            for message in model.iter() {
-                let length = message.len();
-                let message_type = message.message_type();
-                let member_name = message.member_markup(true);
-
-                sizes_map
-                    .entry((message_type, member_name))
-                    .and_modify(|(calls, bytes_vec)| {
-                        *calls += 1;
-                        bytes_vec.push(length);
-                    })
-                    .or_insert((1, vec![length]));
-            }
-            for ((message_type, member), (calls, mut bytes)) in sizes_map.into_iter() {
-                bytes.sort();
-                let mean = bytes.iter().copied().sum::<usize>() / calls;
-                sizes.append(&SizeItem::new(
-                    &member,
-                    *bytes.first().unwrap_or(&0) as u32,
-                    mean as u32,
-                    *bytes.last().unwrap_or(&0) as u32,
-                    message_type,
-                ))
-            }
+            let obj = self.obj();
 
             self.column_view
                 .sort_by_column(Some(&self.mean_column), gtk::SortType::Ascending);
             let sorter = self.column_view.sorter();
-            let sorted_model = gtk::SortListModel::new(Some(sizes), sorter);
+            let sorted_model = gtk::SortListModel::new(Some(self.items.clone()), sorter);
             let selection_model = gtk::NoSelection::new(Some(sorted_model));
             self.column_view.set_model(Some(&selection_model));
+
+            model.connect_items_changed(
+                clone!(@weak obj => move |model, position, removed, added| {
+                    // Messages are only appended while recording and evicted
+                    // from the front, anything else means the filters changed
+                    if removed == 0 && position + added == model.n_items() {
+                        obj.add_messages((position..model.n_items()).map(|i| {
+                            model.item(i).and_downcast::<Message>().unwrap()
+                        }));
+                    } else if position == 0 && added == 0 {
+                        match model.item(0).and_downcast::<Message>() {
+                            Some(first) => obj.remove_messages_before(first.receive_index()),
+                            None => obj.reset(model),
+                        }
+                    } else {
+                        obj.reset(model);
+                    }
+                }),
+            );
+
+            obj.reset(model);
             self.model.set(Some(model.clone()));
         }
     }
@@ -135,6 +217,75 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Bin;
 }
 
+impl SizesPage {
+    /// Recomputes the sizes from all the messages of `model`
+    fn reset(&self, model: &FilteredMessageModel) {
+        let imp = self.imp();
+
+        imp.items.remove_all();
+        imp.items_map.borrow_mut().clear();
+        imp.accounted.borrow_mut().clear();
+
+        self.add_messages(model.iter());
+    }
+
+    /// Accounts `messages` in the sizes, updating the existing items in place
+    fn add_messages(&self, messages: impl Iterator<Item = Message>) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let mut new_items = Vec::new();
+        for message in messages {
+            let length = message.len();
+            let message_type = message.message_type();
+            let member_name = message.member_markup(true);
+
+            let (item, running_size) = items_map
+                .entry((message_type, member_name))
+                .or_insert_with_key(|(message_type, member_name)| {
+                    let item = SizeItem::new(member_name, 0, 0, 0, *message_type);
+                    new_items.push(item.clone());
+                    (item, RunningSize::default())
+                });
+            running_size.push(length);
+            running_size.update(item);
+
+            accounted.insert(message.receive_index(), (item.clone(), length));
+        }
+
+        imp.items.extend_from_slice(&new_items);
+    }
+
+    /// Takes back the messages received before `receive_index` from the
+    /// sizes, once they were evicted
+    fn remove_messages_before(&self, receive_index: ReceiveIndex) {
+        let imp = self.imp();
+
+        let mut items_map = imp.items_map.borrow_mut();
+        let mut accounted = imp.accounted.borrow_mut();
+        let retained = accounted.split_off(&receive_index);
+        let evicted = mem::replace(&mut *accounted, retained);
+
+        for (item, length) in evicted.into_values() {
+            let key = (item.message_type(), item.member());
+            let Some((_, running_size)) = items_map.get_mut(&key) else {
+                continue;
+            };
+            running_size.remove(length);
+
+            if running_size.is_empty() {
+                items_map.remove(&key);
+                if let Some(position) = imp.items.find(&item) {
+                    imp.items.remove(position);
+                }
+            } else {
+                running_size.update(&item);
+            }
+        }
+    }
+}
+
 #[gtk::template_callbacks]
 impl SizesPage {
     #[template_callback]
@@ -163,3 +314,31 @@ impl SizesPage {
             .unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_size() {
+        let mut running_size = RunningSize::default();
+        for length in [40, 10, 40, 30] {
+            running_size.push(length);
+        }
+        assert_eq!(running_size.smallest(), 10);
+        assert_eq!(running_size.mean(), 30);
+        assert_eq!(running_size.largest(), 40);
+
+        // Another message still has the same length
+        running_size.remove(40);
+        assert_eq!(running_size.largest(), 40);
+
+        running_size.remove(10);
+        assert_eq!(running_size.smallest(), 30);
+        assert_eq!(running_size.mean(), 35);
+
+        running_size.remove(40);
+        running_size.remove(30);
+        assert!(running_size.is_empty());
+    }
+}
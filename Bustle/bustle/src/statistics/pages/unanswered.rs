@@ -0,0 +1,180 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{gio, glib, prelude::*};
+
+use crate::{
+    filtered_message_model::FilteredMessageModel, i18n::gettext_f, statistics::UnansweredItem,
+};
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::UnansweredPage)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/unanswered_page.ui")]
+    pub struct UnansweredPage {
+        #[property(get, set = Self::set_model)]
+        pub(super) model: RefCell<Option<FilteredMessageModel>>,
+        #[template_child]
+        pub(super) stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub(super) column_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        pub(super) member_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) sender_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) destination_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) status_column: TemplateChild<gtk::ColumnViewColumn>,
+        #[template_child]
+        pub(super) age_column: TemplateChild<gtk::ColumnViewColumn>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for UnansweredPage {
+        const NAME: &'static str = "BustleUnansweredPage";
+        type Type = super::UnansweredPage;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for UnansweredPage {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.member_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &UnansweredItem::this_expression("member"),
+                ))));
+            self.sender_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &UnansweredItem::this_expression("sender"),
+                ))));
+            self.destination_column
+                .set_sorter(Some(&gtk::StringSorter::new(Some(
+                    &UnansweredItem::this_expression("destination"),
+                ))));
+            self.status_column
+                .set_sorter(Some(&gtk::NumericSorter::new(Some(
+                    &UnansweredItem::this_expression("is-late"),
+                ))));
+            self.age_column
+                .set_sorter(Some(&gtk::CustomSorter::new(|a, b| {
+                    let a = a.downcast_ref::<UnansweredItem>().unwrap();
+                    let b = b.downcast_ref::<UnansweredItem>().unwrap();
+                    a.age().cmp(&b.age()).into()
+                })));
+        }
+    }
+
+    impl WidgetImpl for UnansweredPage {}
+
+    impl BinImpl for UnansweredPage {}
+
+    impl UnansweredPage {
+        fn set_model(&self, model: &FilteredMessageModel) {
+            let items = gio::ListStore::new::<UnansweredItem>();
+
+            if let Some(message_list) = model.message_list() {
+                // Messages of different buses may be slightly out of order,
+                // so the last one is not necessarily the latest
+                let end_timestamp = message_list.latest_timestamp().unwrap_or_default();
+
+                for call in message_list.unanswered_calls() {
+                    // Only list calls that are not filtered out, and that
+                    // cannot get their reply anymore, like the diagram does
+                    if model.get_index_of(&call).is_none()
+                        || !message_list.is_call_unanswered(&call)
+                    {
+                        continue;
+                    }
+
+                    items.append(&UnansweredItem::new(
+                        &call.member_markup(false),
+                        &call.sender_display(),
+                        &call.destination_display(),
+                        end_timestamp.saturating_sub(call.timestamp()),
+                        false,
+                    ));
+                }
+            }
+
+            for call in model.iter() {
+                if !call.message_type().is_method_call() || !call.is_late_reply() {
+                    continue;
+                }
+
+                items.append(&UnansweredItem::new(
+                    &call.member_markup(false),
+                    &call.sender_display(),
+                    &call.destination_display(),
+                    call.response_time().unwrap_or_default(),
+                    true,
+                ));
+            }
+
+            if items.n_items() == 0 {
+                self.stack.set_visible_child_name("empty");
+            } else {
+                self.stack.set_visible_child_name("list");
+            }
+
+            self.column_view
+                .sort_by_column(Some(&self.age_column), gtk::SortType::Descending);
+            let sorter = self.column_view.sorter();
+            let sorted_model = gtk::SortListModel::new(Some(items), sorter);
+            let selection_model = gtk::NoSelection::new(Some(sorted_model));
+            self.column_view.set_model(Some(&selection_model));
+            self.model.set(Some(model.clone()));
+        }
+    }
+}
+
+glib::wrapper! {
+     pub struct UnansweredPage(ObjectSubclass<imp::UnansweredPage>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+#[gtk::template_callbacks]
+impl UnansweredPage {
+    #[template_callback]
+    fn status(_: &glib::Object, entry: Option<&UnansweredItem>) -> String {
+        entry
+            .map(|e| {
+                if e.is_late() {
+                    gettext("Late Reply")
+                } else {
+                    gettext("No Reply")
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    #[template_callback]
+    fn age(_: &glib::Object, entry: Option<&UnansweredItem>) -> String {
+        entry
+            .map(|e| {
+                gettext_f(
+                    // Translators: Do NOT translate the contents between '{' and '}', this
+                    // is a variable name.
+                    "{seconds} s",
+                    &[(
+                        "seconds",
+                        &format!("{:.2}", e.age().as_millis_f64() / 1_000.0),
+                    )],
+                )
+            })
+            .unwrap_or_default()
+    }
+}
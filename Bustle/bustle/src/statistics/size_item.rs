@@ -12,11 +12,11 @@ mod imp {
     pub struct SizeItem {
         #[property(get, set, construct_only)]
         pub(super) member: OnceCell<String>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) smallest: Cell<u32>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) mean: Cell<u32>,
-        #[property(get, set, construct_only)]
+        #[property(get, set)]
         pub(super) largest: Cell<u32>,
         #[property(get, set, construct_only, builder(MessageType::default()))]
         pub(super) message_type: OnceCell<MessageType>,
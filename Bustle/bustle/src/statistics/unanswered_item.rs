@@ -0,0 +1,56 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use crate::timestamp::Timestamp;
+
+mod imp {
+    use std::cell::{Cell, OnceCell};
+
+    use super::*;
+
+    #[derive(Debug, Default, glib::Properties)]
+    #[properties(wrapper_type = super::UnansweredItem)]
+    pub struct UnansweredItem {
+        #[property(get, set, construct_only)]
+        pub(super) member: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) sender: OnceCell<String>,
+        #[property(get, set, construct_only)]
+        pub(super) destination: OnceCell<String>,
+        /// Time from the call to the end of the capture, or to the late reply
+        #[property(get, set, construct_only)]
+        pub(super) age: Cell<Timestamp>,
+        /// Whether a reply did arrive, but after the default timeout
+        #[property(get, set, construct_only)]
+        pub(super) is_late: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for UnansweredItem {
+        const NAME: &'static str = "BustleUnansweredItem";
+        type Type = super::UnansweredItem;
+    }
+    #[glib::derived_properties]
+    impl ObjectImpl for UnansweredItem {}
+}
+
+glib::wrapper! {
+    pub struct UnansweredItem(ObjectSubclass<imp::UnansweredItem>);
+}
+
+impl UnansweredItem {
+    pub fn new(
+        member: &str,
+        sender: &str,
+        destination: &str,
+        age: Timestamp,
+        is_late: bool,
+    ) -> Self {
+        glib::Object::builder()
+            .property("member", member)
+            .property("sender", sender)
+            .property("destination", destination)
+            .property("age", age)
+            .property("is-late", is_late)
+            .build()
+    }
+}
@@ -1,14 +1,20 @@
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use gtk::glib;
+use gtk::glib::{self, closure_local};
+use once_cell::sync::Lazy;
 
-use crate::filtered_message_model::FilteredMessageModel;
+use crate::{filtered_message_model::FilteredMessageModel, message::Message};
 
 mod imp {
     use std::cell::OnceCell;
 
+    use glib::subclass::Signal;
+
     use super::*;
-    use crate::statistics::{DurationsPage, FrequenciesPage, SizesPage};
+    use crate::statistics::{
+        DurationsPage, ErrorsPage, FrequenciesPage, MatrixPage, OwnershipPage, SizesPage,
+        UnansweredPage,
+    };
 
     #[derive(Debug, Default, glib::Properties, gtk::CompositeTemplate)]
     #[properties(wrapper_type = super::StatisticsWindow)]
@@ -22,6 +28,14 @@ mod imp {
         pub(super) sizes_page: TemplateChild<SizesPage>,
         #[template_child]
         pub(super) frequencies_page: TemplateChild<FrequenciesPage>,
+        #[template_child]
+        pub(super) unanswered_page: TemplateChild<UnansweredPage>,
+        #[template_child]
+        pub(super) errors_page: TemplateChild<ErrorsPage>,
+        #[template_child]
+        pub(super) matrix_page: TemplateChild<MatrixPage>,
+        #[template_child]
+        pub(super) ownership_page: TemplateChild<OwnershipPage>,
     }
 
     #[glib::object_subclass]
@@ -32,6 +46,7 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
+            klass.bind_template_instance_callbacks();
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -47,6 +62,20 @@ mod imp {
             self.durations_page.set_model(&model);
             self.frequencies_page.set_model(&model);
             self.sizes_page.set_model(&model);
+            self.unanswered_page.set_model(&model);
+            self.errors_page.set_model(&model);
+            self.matrix_page.set_model(&model);
+            self.ownership_page.set_model(&model);
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("show-message-request")
+                    .param_types([Message::static_type()])
+                    .build()]
+            });
+
+            SIGNALS.as_ref()
         }
     }
 
@@ -59,8 +88,28 @@ glib::wrapper! {
         @extends gtk::Widget, adw::Dialog;
 }
 
+#[gtk::template_callbacks]
 impl StatisticsWindow {
     pub fn new(model: &FilteredMessageModel) -> Self {
         glib::Object::builder().property("model", model).build()
     }
+
+    pub fn connect_show_message_request<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &Message) + 'static,
+    {
+        self.connect_closure(
+            "show-message-request",
+            false,
+            closure_local!(|obj: &Self, message: &Message| {
+                f(obj, message);
+            }),
+        )
+    }
+
+    #[template_callback]
+    fn ownership_page_show_message_request(&self, message: &Message) {
+        self.close();
+        self.emit_by_name::<()>("show-message-request", &[message]);
+    }
 }
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use crate::{bus_type::BusType, message_match::MessageMatch, monitor::Event};
+
+/// Ends a recording on its own, whichever condition is met first
+#[derive(Debug)]
+pub struct StopConditions {
+    duration: Option<Duration>,
+    n_messages: Option<usize>,
+    message_match: Option<MessageMatch>,
+    /// Bus name whose disappearance ends the recording
+    ///
+    /// Processes are not waited for by name, as recordings do not tell which
+    /// process owns a connection, but their bus names are.
+    bus_name: Option<String>,
+    /// Bus `bus_name` must disappear from, or None for any of them
+    bus_name_bus_type: Option<BusType>,
+
+    n_recorded: usize,
+    is_met: bool,
+}
+
+impl StopConditions {
+    pub fn new(
+        duration: Option<Duration>,
+        n_messages: Option<usize>,
+        message_match: Option<MessageMatch>,
+        bus_name: Option<String>,
+        bus_name_bus_type: Option<BusType>,
+    ) -> Self {
+        Self {
+            duration,
+            n_messages,
+            message_match,
+            bus_name,
+            bus_name_bus_type,
+            n_recorded: 0,
+            is_met: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.duration.is_none()
+            && self.n_messages.is_none()
+            && self.message_match.is_none()
+            && self.bus_name.is_none()
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.is_met
+    }
+
+    /// How long after it started the recording must stop
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn set_duration_elapsed(&mut self) {
+        self.is_met = true;
+    }
+
+    /// Marks the condition as met if `bus_name` on `bus_type` is the one to
+    /// wait for
+    pub fn set_bus_name_lost(&mut self, bus_type: BusType, bus_name: &str) {
+        if self.bus_name.as_deref() == Some(bus_name)
+            && self
+                .bus_name_bus_type
+                .is_none_or(|expected| expected == bus_type)
+        {
+            self.is_met = true;
+        }
+    }
+
+    /// Removes from `events` the ones received after the message count was
+    /// reached or after a message matched
+    pub fn truncate(&mut self, events: &mut Vec<(Event, BusType)>) {
+        if self.is_met {
+            events.clear();
+            return;
+        }
+
+        let end = events.iter().position(|(event, _)| {
+            self.n_recorded += 1;
+            self.n_messages.is_some_and(|max| self.n_recorded >= max)
+                || self
+                    .message_match
+                    .as_ref()
+                    .is_some_and(|message_match| message_match.matches(&event.message))
+        });
+        if let Some(end) = end {
+            events.truncate(end + 1);
+            self.is_met = true;
+        }
+    }
+}
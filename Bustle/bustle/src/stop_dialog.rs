@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+};
+use zbus::names::BusName;
+
+use crate::message_match::MessageMatch;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/stop_dialog.ui")]
+    pub struct StopDialog {
+        #[template_child]
+        pub(super) duration_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) count_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) condition_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) bus_name_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) bus_type_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub(super) auto_save_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub(super) clear_auto_save_button: TemplateChild<gtk::Button>,
+
+        pub(super) auto_save_directory: RefCell<Option<PathBuf>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for StopDialog {
+        const NAME: &'static str = "BustleStopDialog";
+        type Type = super::StopDialog;
+        type ParentType = adw::AlertDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for StopDialog {}
+    impl WidgetImpl for StopDialog {}
+    impl AdwDialogImpl for StopDialog {}
+    impl AdwAlertDialogImpl for StopDialog {}
+}
+
+glib::wrapper! {
+    /// Edits when new recordings stop on their own, and where they are saved
+    /// then
+    pub struct StopDialog(ObjectSubclass<imp::StopDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::AlertDialog;
+}
+
+#[gtk::template_callbacks]
+impl StopDialog {
+    /// Shows the stop conditions stored in `settings`, and stores the edited
+    /// ones unless cancelled
+    pub async fn edit(parent: &impl IsA<gtk::Widget>, settings: &gio::Settings) {
+        let this = glib::Object::new::<Self>();
+        let imp = this.imp();
+
+        imp.duration_row
+            .set_value(settings.uint("stop-duration").into());
+        imp.count_row.set_value(settings.uint("stop-count").into());
+        imp.condition_row
+            .set_text(&settings.string("stop-condition"));
+        imp.bus_name_row.set_text(&settings.string("stop-bus-name"));
+        imp.bus_type_row
+            .set_selected(match settings.string("stop-bus-type").as_str() {
+                "session" => 1,
+                "system" => 2,
+                _ => 0,
+            });
+        let auto_save_directory = settings.string("auto-save-directory");
+        this.set_auto_save_directory(
+            (!auto_save_directory.is_empty()).then(|| PathBuf::from(auto_save_directory.as_str())),
+        );
+        this.update_save_response_enabled();
+
+        match this.clone().choose_future(parent).await.as_str() {
+            "cancel" => {}
+            "save" => {
+                if let Err(err) = this.save(settings) {
+                    tracing::error!("Failed to save stop conditions: {:?}", err);
+                }
+            }
+            response_id => unreachable!("unexpected response id `{}`", response_id),
+        }
+    }
+
+    fn save(&self, settings: &gio::Settings) -> Result<(), glib::BoolError> {
+        let imp = self.imp();
+
+        settings.set_uint("stop-duration", imp.duration_row.value() as u32)?;
+        settings.set_uint("stop-count", imp.count_row.value() as u32)?;
+        settings.set_string("stop-condition", imp.condition_row.text().trim())?;
+        settings.set_string("stop-bus-name", imp.bus_name_row.text().trim())?;
+        settings.set_string(
+            "stop-bus-type",
+            match imp.bus_type_row.selected() {
+                1 => "session",
+                2 => "system",
+                _ => "any",
+            },
+        )?;
+        settings.set_string(
+            "auto-save-directory",
+            imp.auto_save_directory
+                .borrow()
+                .as_ref()
+                .and_then(|path| path.to_str())
+                .unwrap_or_default(),
+        )?;
+
+        Ok(())
+    }
+
+    fn set_auto_save_directory(&self, path: Option<PathBuf>) {
+        let imp = self.imp();
+
+        match &path {
+            Some(path) => imp.auto_save_row.set_subtitle(&path.display().to_string()),
+            None => imp.auto_save_row.set_subtitle(&gettext("Not saved")),
+        }
+        imp.clear_auto_save_button.set_visible(path.is_some());
+
+        imp.auto_save_directory.replace(path);
+    }
+
+    #[template_callback]
+    fn choose_auto_save_directory(&self) {
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Save Recordings to Folder"))
+            .modal(true)
+            .build();
+        let parent = self.root().and_downcast::<gtk::Window>();
+
+        glib::spawn_future_local(clone!(@weak self as obj => async move {
+            match chooser.select_folder_future(parent.as_ref()).await {
+                Ok(dir) => obj.set_auto_save_directory(dir.path()),
+                Err(err) if err.matches(gtk::DialogError::Dismissed) => {}
+                Err(err) => tracing::error!("Failed to choose folder: {:?}", err),
+            }
+        }));
+    }
+
+    #[template_callback]
+    fn clear_auto_save_directory(&self) {
+        self.set_auto_save_directory(None);
+    }
+
+    #[template_callback]
+    fn update_save_response_enabled(&self) {
+        let imp = self.imp();
+
+        let condition = imp.condition_row.text();
+        let is_condition_valid =
+            condition.trim().is_empty() || condition.parse::<MessageMatch>().is_ok();
+
+        let bus_name = imp.bus_name_row.text();
+        let is_bus_name_valid =
+            bus_name.trim().is_empty() || BusName::try_from(bus_name.trim()).is_ok();
+
+        self.set_response_enabled("save", is_condition_valid && is_bus_name_valid);
+    }
+}
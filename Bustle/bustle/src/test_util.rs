@@ -0,0 +1,52 @@
+//! Messages and events shared by the unit tests
+
+use std::time::Duration;
+
+use crate::{monitor::Event, timestamp::Timestamp};
+
+/// Returns `message` as received `millis` after the recording started
+pub fn event(message: zbus::Message, millis: u64) -> Event {
+    Event {
+        message,
+        timestamp: Timestamp::from(Duration::from_millis(millis)),
+    }
+}
+
+/// Returns a call of `org.example.Foo.Bar` from `sender` to `:1.1`
+pub fn call(sender: &str) -> zbus::Message {
+    zbus::Message::method("/org/example/Foo", "Bar")
+        .unwrap()
+        .interface("org.example.Foo")
+        .unwrap()
+        .sender(sender)
+        .unwrap()
+        .destination(":1.1")
+        .unwrap()
+        .build(&())
+        .unwrap()
+}
+
+/// Returns the reply of `:1.1` to `call`
+pub fn reply(call: &zbus::Message) -> zbus::Message {
+    zbus::Message::method_reply(call)
+        .unwrap()
+        .sender(":1.1")
+        .unwrap()
+        .build(&())
+        .unwrap()
+}
+
+/// Returns the `org.example.Foo.Baz` signal broadcast by `sender`
+pub fn signal(sender: &str) -> zbus::Message {
+    member_signal(sender, "Baz")
+}
+
+/// Returns the `member` signal of `org.example.Foo` broadcast by `sender`
+pub fn member_signal(sender: &str, member: &str) -> zbus::Message {
+    zbus::Message::signal("/org/example/Foo", "org.example.Foo", member)
+        .unwrap()
+        .sender(sender)
+        .unwrap()
+        .build(&())
+        .unwrap()
+}
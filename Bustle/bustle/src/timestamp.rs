@@ -27,6 +27,12 @@ impl Timestamp {
         // self.0 ::> Accesses the first field (Duration) of the Timestamp struct. In Rust, fields within a struct are accessed using dot notation with the variable (self in this case) followed by the field index or name (0 in this case because Duration is the first and only field in the struct).
         self.0.as_micros() as f64 / 1_000.0 // Convert duration to microseconds and then to milliseconds as f64
     }
+
+    // Define a method saturating_sub() that returns zero instead of
+    // underflowing if rhs is later than self
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
 }
 
 // Implement the Subtrait for Timestamp to support subtraction
@@ -34,8 +40,11 @@ impl ops::Sub for Timestamp {
     type Output = Self;
 
     // Define the sub() method to subtract two Timestamps
+    //
+    // This saturates at zero, as messages of different buses are stamped by
+    // different clocks and may be slightly out of order
     fn sub(self, rhs: Self) -> Self {
-        Self(self.0.sub(rhs.0)) // Subtract the durations and return the result as a new Timestamp
+        self.saturating_sub(rhs)
     }
 }
 
@@ -0,0 +1,116 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{bus_type::BusType, message_match::MessageMatch, monitor::Event};
+
+/// When to stop recording after the trigger, whichever comes first
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PostTrigger {
+    pub duration: Option<Duration>,
+    pub n_messages: Option<usize>,
+}
+
+#[derive(Debug)]
+enum State {
+    /// Waiting for a matching message, keeping the most recent ones
+    Armed { buffer: VecDeque<(Event, BusType)> },
+    /// Recording what comes after the matching message
+    Triggered {
+        trigger_time: Duration,
+        n_messages: usize,
+    },
+    /// Everything after the trigger was recorded
+    Done,
+}
+
+/// Holds back recorded messages until one matches `condition`, then only
+/// lets through the ones received `pre_trigger` before it and the ones after
+/// it, until `post_trigger` is reached
+#[derive(Debug)]
+pub struct Trigger {
+    condition: MessageMatch,
+    pre_trigger: Duration,
+    post_trigger: PostTrigger,
+    state: State,
+}
+
+impl Trigger {
+    pub fn new(condition: MessageMatch, pre_trigger: Duration, post_trigger: PostTrigger) -> Self {
+        Self {
+            condition,
+            pre_trigger,
+            post_trigger,
+            state: State::Armed {
+                buffer: VecDeque::new(),
+            },
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        !matches!(self.state, State::Armed { .. })
+    }
+
+    /// How long to keep recording after the trigger, if limited
+    pub fn post_trigger_duration(&self) -> Option<Duration> {
+        self.post_trigger.duration
+    }
+
+    /// Whether the messages after the trigger were all recorded, and so the
+    /// recording can be stopped
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// Returns the messages of `events` and the held back ones that must be
+    /// recorded now, in the order they were received
+    pub fn process(&mut self, events: Vec<Event>, bus_type: BusType) -> Vec<(Event, BusType)> {
+        let mut ret = Vec::new();
+
+        for event in events {
+            match &mut self.state {
+                State::Armed { buffer } => {
+                    let timestamp = Duration::from(event.timestamp);
+                    let oldest = timestamp.saturating_sub(self.pre_trigger);
+                    while buffer
+                        .front()
+                        .is_some_and(|(front, _)| Duration::from(front.timestamp) < oldest)
+                    {
+                        buffer.pop_front();
+                    }
+
+                    if self.condition.matches(&event.message) {
+                        ret.extend(buffer.drain(..));
+                        ret.push((event, bus_type));
+                        self.state = State::Triggered {
+                            trigger_time: timestamp,
+                            n_messages: 0,
+                        };
+                    } else {
+                        buffer.push_back((event, bus_type));
+                    }
+                }
+                State::Triggered {
+                    trigger_time,
+                    n_messages,
+                } => {
+                    let is_too_late = self.post_trigger.duration.is_some_and(|duration| {
+                        Duration::from(event.timestamp) > *trigger_time + duration
+                    });
+                    let is_too_many = self
+                        .post_trigger
+                        .n_messages
+                        .is_some_and(|max| *n_messages >= max);
+                    if is_too_late || is_too_many {
+                        self.state = State::Done;
+                        continue;
+                    }
+
+                    *n_messages += 1;
+                    ret.push((event, bus_type));
+                }
+                State::Done => {}
+            }
+        }
+
+        ret
+    }
+}
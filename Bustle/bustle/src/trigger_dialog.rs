@@ -0,0 +1,108 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::{gio, glib};
+
+use crate::message_match::MessageMatch;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/org/freedesktop/Bustle/ui/trigger_dialog.ui")]
+    pub struct TriggerDialog {
+        #[template_child]
+        pub(super) enabled_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(super) condition_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub(super) pre_duration_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) post_duration_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub(super) post_count_row: TemplateChild<adw::SpinRow>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TriggerDialog {
+        const NAME: &'static str = "BustleTriggerDialog";
+        type Type = super::TriggerDialog;
+        type ParentType = adw::AlertDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TriggerDialog {}
+    impl WidgetImpl for TriggerDialog {}
+    impl AdwDialogImpl for TriggerDialog {}
+    impl AdwAlertDialogImpl for TriggerDialog {}
+}
+
+glib::wrapper! {
+    /// Edits the trigger new recordings wait for
+    pub struct TriggerDialog(ObjectSubclass<imp::TriggerDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::AlertDialog;
+}
+
+#[gtk::template_callbacks]
+impl TriggerDialog {
+    /// Shows the trigger stored in `settings`, and stores the edited one
+    /// unless cancelled
+    pub async fn edit(parent: &impl IsA<gtk::Widget>, settings: &gio::Settings) {
+        let this = glib::Object::new::<Self>();
+        let imp = this.imp();
+
+        imp.enabled_row
+            .set_active(settings.boolean("trigger-enabled"));
+        imp.condition_row
+            .set_text(&settings.string("trigger-condition"));
+        imp.pre_duration_row
+            .set_value(settings.uint("trigger-pre-duration").into());
+        imp.post_duration_row
+            .set_value(settings.uint("trigger-post-duration").into());
+        imp.post_count_row
+            .set_value(settings.uint("trigger-post-count").into());
+        this.update_save_response_enabled();
+
+        match this.clone().choose_future(parent).await.as_str() {
+            "cancel" => {}
+            "save" => {
+                if let Err(err) = this.save(settings) {
+                    tracing::error!("Failed to save trigger: {:?}", err);
+                }
+            }
+            response_id => unreachable!("unexpected response id `{}`", response_id),
+        }
+    }
+
+    fn save(&self, settings: &gio::Settings) -> Result<(), glib::BoolError> {
+        let imp = self.imp();
+
+        settings.set_boolean("trigger-enabled", imp.enabled_row.is_active())?;
+        settings.set_string("trigger-condition", &imp.condition_row.text())?;
+        settings.set_uint("trigger-pre-duration", imp.pre_duration_row.value() as u32)?;
+        settings.set_uint(
+            "trigger-post-duration",
+            imp.post_duration_row.value() as u32,
+        )?;
+        settings.set_uint("trigger-post-count", imp.post_count_row.value() as u32)?;
+
+        Ok(())
+    }
+
+    #[template_callback]
+    fn update_save_response_enabled(&self) {
+        let imp = self.imp();
+
+        self.set_response_enabled(
+            "save",
+            !imp.enabled_row.is_active()
+                || imp.condition_row.text().parse::<MessageMatch>().is_ok(),
+        );
+    }
+}
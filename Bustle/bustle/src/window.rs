@@ -1,23 +1,46 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
 };
+use zbus::names::{BusName, WellKnownName};
 
 use crate::{
     address_dialog::AddressDialog,
     application::Application,
+    bus_name_list::item_name,
+    bus_type::BusType,
+    command_dialog::CommandDialog,
     config::{APP_ID, PROFILE, VERSION},
     details_view::DetailsView,
     diagram::Diagram,
-    i18n::gettext_f,
+    event_merger::{EventMerger, MERGE_DELAY},
+    i18n::{gettext_f, ngettext_f},
+    introspection::Introspection,
     message::Message,
-    message_list::MessageList,
-    monitor::{Cancelled, Monitor},
+    message_list::{MessageList, Retention},
+    message_type::MessageType,
+    mock_service::{MockService, MockServiceHandle},
+    monitor::{Cancelled, Event, Monitor},
+    replay::{Replay, Timing},
+    retention_dialog::RetentionDialog,
     statistics::StatisticsWindow,
+    stop_conditions::StopConditions,
+    stop_dialog::StopDialog,
+    timestamp::Timestamp,
+    trigger::{PostTrigger, Trigger},
+    trigger_dialog::TriggerDialog,
 };
 
 #[derive(Default, Debug, Copy, Clone, glib::Enum, PartialEq)]
@@ -42,11 +65,7 @@ mod imp {
         #[template_child]
         pub(super) toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
-        pub(super) tab_view: TemplateChild<adw::TabView>,
-        // To be commented ------------------------------------------------
-        #[template_child]
         pub(super) main_stack: TemplateChild<gtk::Stack>,
-        // ----------------------------------------------------------------
         #[template_child]
         pub(super) empty_page: TemplateChild<adw::ToolbarView>,
         #[template_child]
@@ -76,7 +95,19 @@ mod imp {
 
         pub(super) settings: gio::Settings,
 
-        pub(super) monitor: RefCell<Option<Monitor>>,
+        pub(super) monitors: RefCell<Vec<Monitor>>,
+        // The trigger the current recording waits for, if any
+        pub(super) trigger: RefCell<Option<Trigger>>,
+        pub(super) trigger_timeout: RefCell<Option<glib::SourceId>>,
+        // When the current recording stops on its own, if ever
+        pub(super) stop_conditions: RefCell<Option<StopConditions>>,
+        pub(super) stop_timeout: RefCell<Option<glib::SourceId>>,
+        // Puts the events of the monitors in timestamp order
+        pub(super) event_merger: RefCell<Option<EventMerger>>,
+        pub(super) merge_timeout: RefCell<Option<glib::SourceId>>,
+        // Cancels the replay shown in the window while it is still running
+        pub(super) replay_cancellable: RefCell<Option<gio::Cancellable>>,
+        pub(super) mock_service: RefCell<Option<MockServiceHandle>>,
         // The currently recorded filename
         pub(super) filename: RefCell<Option<String>>,
     }
@@ -85,7 +116,6 @@ mod imp {
         fn default() -> Self {
             Self {
                 toast_overlay: TemplateChild::default(),
-                tab_view: TemplateChild::default(),
                 // To be commented ------------------------------------------------
                 main_stack: TemplateChild::default(),
                 // ----------------------------------------------------------------
@@ -103,7 +133,15 @@ mod imp {
                 filtered_message_model: TemplateChild::default(),
                 details_view: TemplateChild::default(),
                 settings: gio::Settings::new(APP_ID),
-                monitor: RefCell::default(),
+                monitors: RefCell::default(),
+                trigger: RefCell::default(),
+                trigger_timeout: RefCell::default(),
+                stop_conditions: RefCell::default(),
+                stop_timeout: RefCell::default(),
+                event_merger: RefCell::default(),
+                merge_timeout: RefCell::default(),
+                replay_cancellable: RefCell::default(),
+                mock_service: RefCell::default(),
                 filename: RefCell::default(),
             }
         }
@@ -136,7 +174,7 @@ mod imp {
 
             klass.install_action_async("win.record-session-bus", None, |window, _, _| async move {
                 if let Err(err) = window
-                    .start_recording(Monitor::session(), &gettext("Recording session bus…"))
+                    .start_recording(vec![Monitor::session()], &gettext("Recording session bus…"))
                     .await
                 {
                     tracing::error!("Failed to record session: {err:?}");
@@ -146,7 +184,7 @@ mod imp {
 
             klass.install_action_async("win.record-system-bus", None, |window, _, _| async move {
                 if let Err(err) = window
-                    .start_recording(Monitor::system(), &gettext("Recording system bus…"))
+                    .start_recording(vec![Monitor::system()], &gettext("Recording system bus…"))
                     .await
                 {
                     tracing::error!("Failed to record system: {err:?}");
@@ -156,13 +194,34 @@ mod imp {
                 }
             });
 
+            klass.install_action_async(
+                "win.record-session-and-system-bus",
+                None,
+                |window, _, _| async move {
+                    if let Err(err) = window
+                        .start_recording(
+                            vec![Monitor::session(), Monitor::system()],
+                            &gettext("Recording session and system bus…"),
+                        )
+                        .await
+                    {
+                        tracing::error!("Failed to record session and system: {err:?}");
+                        if !err.is::<Cancelled>() {
+                            window.add_error_toast(&gettext(
+                                "Failed to record session and system bus",
+                            ));
+                        }
+                    }
+                },
+            );
+
             klass.install_action_async("win.record-address", None, |window, _, _| async move {
                 if let Ok(address) = AddressDialog::choose(&window).await {
                     let address_display = address.to_string();
 
                     if let Err(err) = window
                         .start_recording(
-                            Monitor::address(address),
+                            vec![Monitor::address(address)],
                             // Translators: Do NOT translate the contents between '{' and '}', this
                             // is a variable name.
                             &gettext_f("Recording {address}…", &[("address", &address_display)]),
@@ -180,10 +239,92 @@ mod imp {
                 }
             });
 
+            klass.install_action_async("win.record-fifo", None, |window, _, _| async move {
+                if let Err(err) = window.record_fifo().await {
+                    tracing::error!("Failed to record named pipe: {err:?}");
+                    window.add_error_toast(&gettext("Failed to record named pipe"));
+                }
+            });
+
+            klass.install_action_async("win.record-command", None, |window, _, _| async move {
+                if let Ok((command_line, argv)) = CommandDialog::choose(&window).await {
+                    if let Err(err) = window
+                        .start_recording(
+                            vec![Monitor::command(argv)],
+                            // Translators: Do NOT translate the contents between '{' and '}', this
+                            // is a variable name.
+                            &gettext_f("Recording {command}…", &[("command", &command_line)]),
+                        )
+                        .await
+                    {
+                        tracing::error!(
+                            command = command_line,
+                            "Failed to record command: {err:?}"
+                        );
+                        window.add_error_toast(&gettext("Failed to record command output"));
+                    }
+                }
+            });
+
+            klass.install_action_async("win.record-proxy", None, |window, _, _| async move {
+                if let Err(err) = window.record_proxy().await {
+                    tracing::error!("Failed to record through proxy: {err:?}");
+                    window.add_error_toast(&gettext("Failed to start proxy"));
+                }
+            });
+
+            klass.install_action_async("win.replay", None, |window, _, _| async move {
+                if let Err(err) = window.replay().await {
+                    tracing::error!("Failed to replay calls: {err:?}");
+                    window.add_error_toast(&gettext("Failed to replay calls"));
+                }
+            });
+
+            klass.install_action_async("win.run-mock-service", None, |window, _, _| async move {
+                if let Err(err) = window.run_mock_service().await {
+                    tracing::error!("Failed to run mock service: {err:?}");
+                    window.add_error_toast(&gettext("Failed to run mock service"));
+                }
+            });
+
+            klass.install_action("win.stop-mock-service", None, |window, _, _| {
+                window.imp().mock_service.take();
+                window.update_selection_actions(window.view());
+                window.add_message_toast(&gettext("Mock service stopped"));
+            });
+
+            klass.install_action_async("win.save-mock-service", None, |window, _, _| async move {
+                if let Err(err) = window.save_mock_service().await {
+                    tracing::error!("Could not save: {err:?}");
+                    if !err
+                        .downcast_ref::<glib::Error>()
+                        .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                    {
+                        window.add_error_toast(&gettext("Failed to save mock service source"));
+                    }
+                }
+            });
+
             klass.install_action("win.stop-recording", None, |window, _, _| {
                 window.stop_recording();
             });
 
+            klass.install_action_async("win.edit-trigger", None, |window, _, _| async move {
+                TriggerDialog::edit(&window, &window.imp().settings).await;
+            });
+
+            klass.install_action_async("win.edit-retention", None, |window, _, _| async move {
+                RetentionDialog::edit(&window, &window.imp().settings).await;
+            });
+
+            klass.install_action_async(
+                "win.edit-stop-conditions",
+                None,
+                |window, _, _| async move {
+                    StopDialog::edit(&window, &window.imp().settings).await;
+                },
+            );
+
             klass.install_action_async("win.open-log", None, |window, _, _| async move {
                 if let Err(err) = window.open_log().await {
                     tracing::error!("Could not open log: {err:?}");
@@ -196,6 +337,13 @@ mod imp {
                 }
             });
 
+            klass.install_action_async("win.follow-log", None, |window, _, _| async move {
+                if let Err(err) = window.follow_log().await {
+                    tracing::error!("Could not follow log: {err:?}");
+                    window.add_error_toast(&gettext("Failed to follow file"));
+                }
+            });
+
             // @new
             // klass.install_action_async("win.new-document", None, |window, _, _| async move {
             //     if window
@@ -251,8 +399,6 @@ mod imp {
                     {
                         window.add_error_toast(&gettext("Failed to save as PCAP"));
                     }
-                } else {
-                    window.add_message_toast(&gettext("Recording saved as PCAP"));
                 }
             });
 
@@ -269,6 +415,22 @@ mod imp {
                     window.add_message_toast(&gettext("Recording saved as DOT graph"))
                 }
             });
+
+            klass.install_action_async(
+                "win.save-introspection",
+                None,
+                |window, _, _| async move {
+                    if let Err(err) = window.save_introspection().await {
+                        tracing::error!("Could not save: {err:?}");
+                        if !err
+                            .downcast_ref::<glib::Error>()
+                            .is_some_and(|error| error.matches(gtk::DialogError::Dismissed))
+                        {
+                            window.add_error_toast(&gettext("Failed to save introspection XML"));
+                        }
+                    }
+                },
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -289,6 +451,15 @@ mod imp {
 
             self.empty_status_page.set_icon_name(Some(APP_ID));
 
+            obj.add_action(&self.settings.create_action("retention-mode"));
+            self.settings
+                .bind(
+                    "show-bus-driver",
+                    &*self.filtered_message_model,
+                    "shows-bus-driver",
+                )
+                .build();
+
             // Load latest window state
             obj.load_window_size();
 
@@ -363,8 +534,12 @@ impl Window {
         let filename = file.basename().unwrap_or_default().display().to_string();
 
         imp.diagram_title.set_title(&filename);
-        imp.filename
-            .replace(Some(filename.trim_end_matches(".pcap").to_string()));
+        imp.filename.replace(Some(
+            filename
+                .trim_end_matches(".pcapng")
+                .trim_end_matches(".pcap")
+                .to_string(),
+        ));
 
         self.set_view(View::Diagram);
         imp.diagram_page_stack
@@ -373,18 +548,23 @@ impl Window {
         Ok(())
     }
 
+    /// Whether messages are still being added to the shown message list,
+    /// either by monitors or by a running replay
+    fn is_recording(&self) -> bool {
+        let imp = self.imp();
+        !imp.monitors.borrow().is_empty() || imp.replay_cancellable.borrow().is_some()
+    }
+
     fn set_view(&self, view: View) {
         let imp = self.imp();
 
         match view {
             View::EmptyState => imp.main_stack.set_visible_child(&*imp.empty_page),
-            View::Loading => imp.tab_view.set_visible_child(&*imp.loading_page),
-            View::Diagram => {
-                imp.tab_view.set_visible_child(&*imp.diagram_page);
-            }
+            View::Loading => imp.main_stack.set_visible_child(&*imp.loading_page),
+            View::Diagram => imp.main_stack.set_visible_child(&*imp.diagram_page),
         }
 
-        let is_recording = imp.monitor.borrow().is_some();
+        let is_recording = self.is_recording();
 
         self.action_set_enabled(
             "win.record-session-bus",
@@ -394,10 +574,26 @@ impl Window {
             "win.record-system-bus",
             view == View::EmptyState || (view == View::Diagram && !is_recording),
         );
+        self.action_set_enabled(
+            "win.record-session-and-system-bus",
+            view == View::EmptyState || (view == View::Diagram && !is_recording),
+        );
         self.action_set_enabled(
             "win.record-address",
             view == View::EmptyState || (view == View::Diagram && !is_recording),
         );
+        self.action_set_enabled(
+            "win.record-fifo",
+            view == View::EmptyState || (view == View::Diagram && !is_recording),
+        );
+        self.action_set_enabled(
+            "win.record-command",
+            view == View::EmptyState || (view == View::Diagram && !is_recording),
+        );
+        self.action_set_enabled(
+            "win.record-proxy",
+            view == View::EmptyState || (view == View::Diagram && !is_recording),
+        );
 
         self.action_set_enabled("win.stop-recording", view == View::Diagram && is_recording);
 
@@ -409,11 +605,86 @@ impl Window {
             "win.open-pair-logs",
             view == View::EmptyState || (view == View::Diagram && !is_recording),
         );
+        self.action_set_enabled(
+            "win.follow-log",
+            view == View::EmptyState || (view == View::Diagram && !is_recording),
+        );
 
-        self.action_set_enabled("win.statistics", view == View::Diagram && !is_recording);
+        self.action_set_enabled(
+            "win.statistics",
+            view == View::Diagram && imp.replay_cancellable.borrow().is_none(),
+        );
+        self.update_selection_actions(view);
         self.action_set_enabled("win.filter-services", view == View::Diagram);
         self.action_set_enabled("win.save", view == View::Diagram && !is_recording);
         self.action_set_enabled("win.save-dot", view == View::Diagram && !is_recording);
+        self.action_set_enabled(
+            "win.save-introspection",
+            view == View::Diagram && !is_recording,
+        );
+    }
+
+    /// Updates the actions that act on the selected message
+    fn update_selection_actions(&self, view: View) {
+        let imp = self.imp();
+
+        let is_recording = self.is_recording();
+        let has_selected_sender = imp
+            .diagram
+            .selected_message()
+            .is_some_and(|message| message.sender().is_some());
+        let has_selected_service = self.selected_service_name().is_some();
+        let is_mocking = imp.mock_service.borrow().is_some();
+
+        self.action_set_enabled(
+            "win.replay",
+            view == View::Diagram && !is_recording && has_selected_sender,
+        );
+        self.action_set_enabled(
+            "win.run-mock-service",
+            view == View::Diagram && !is_recording && !is_mocking && has_selected_service,
+        );
+        self.action_set_enabled("win.stop-mock-service", is_mocking);
+        self.action_set_enabled(
+            "win.save-mock-service",
+            view == View::Diagram && !is_recording && has_selected_service,
+        );
+    }
+
+    /// Returns the well-known name of the service the selected message
+    /// belongs to, i.e., the destination of a call or the sender of a reply
+    /// or signal.
+    fn selected_service_name(&self) -> Option<WellKnownName<'static>> {
+        let imp = self.imp();
+
+        let message = imp.diagram.selected_message()?;
+        let message_list = imp.filtered_message_model.message_list()?;
+
+        let (message, name) = match message.message_type() {
+            MessageType::MethodCall => {
+                let name = message.destination()?.into_owned();
+                (message, name)
+            }
+            MessageType::MethodReturn | MessageType::Error => {
+                let call = message.associated_message()?;
+                let name = call.destination()?.into_owned();
+                (call, name)
+            }
+            MessageType::Signal => {
+                let name = BusName::from(message.sender()?.into_owned());
+                (message, name)
+            }
+        };
+
+        match item_name(&name) {
+            BusName::WellKnown(wk_name) => Some(wk_name),
+            unique_name @ BusName::Unique(_) => message_list
+                .bus_names()
+                .get(message.bus_type(), &unique_name)?
+                .wk_names(message.receive_index().into())
+                .first()
+                .cloned(),
+        }
     }
 
     fn view(&self) -> View {
@@ -571,25 +842,95 @@ impl Window {
 
         debug_assert!(imp.filtered_message_model.message_list().is_some());
 
-        StatisticsWindow::new(&imp.filtered_message_model).present(self);
+        let statistics_window = StatisticsWindow::new(&imp.filtered_message_model);
+        statistics_window.connect_show_message_request(
+            clone!(@weak self as obj => move |_, message| {
+                obj.details_view_show_message_request(message);
+            }),
+        );
+        statistics_window.present(self);
     }
 
     fn stop_recording(&self) {
         let imp = self.imp();
 
-        let monitor = imp
-            .monitor
+        // The replay finishes on its own once cancelled
+        if let Some(cancellable) = imp.replay_cancellable.borrow().as_ref() {
+            cancellable.cancel();
+            return;
+        }
+
+        if let Some(merge_timeout) = imp.merge_timeout.take() {
+            merge_timeout.remove();
+        }
+        let held_back = imp
+            .event_merger
             .take()
-            .expect("monitor must be set when recording");
-        drop(monitor);
+            .map(|mut event_merger| event_merger.pop_all())
+            .unwrap_or_default();
+        if let Some(message_list) = imp.filtered_message_model.message_list() {
+            self.push_recorded(&message_list, held_back);
+        }
+
+        let monitors = imp.monitors.take();
+        debug_assert!(!monitors.is_empty(), "monitors must be set when recording");
+        imp.trigger.take();
+        if let Some(trigger_timeout) = imp.trigger_timeout.take() {
+            trigger_timeout.remove();
+        }
+        imp.stop_conditions.take();
+        if let Some(stop_timeout) = imp.stop_timeout.take() {
+            stop_timeout.remove();
+        }
+
+        let mut n_delayed = 0;
+        let mut n_dropped = 0;
+        for monitor in monitors {
+            monitor.stop();
+            n_delayed += monitor.delivery_stats().n_delayed();
+            n_dropped += monitor.delivery_stats().n_dropped();
+        }
+
+        tracing::debug!(n_delayed, n_dropped, "Stopped recording");
+        if n_dropped != 0 {
+            self.add_message_toast(&ngettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "{n_dropped} message was dropped as the recording could not keep up",
+                "{n_dropped} messages were dropped as the recording could not keep up",
+                n_dropped as u32,
+                &[("n_dropped", &n_dropped.to_string())],
+            ));
+        }
+        if n_delayed != 0 {
+            self.add_message_toast(&ngettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "{n_delayed} message was shown late as the recording could not keep up",
+                "{n_delayed} messages were shown late as the recording could not keep up",
+                n_delayed as u32,
+                &[("n_delayed", &n_delayed.to_string())],
+            ));
+        }
 
+        // Pending calls are only unanswered once they cannot get a reply
+        // anymore
+        if let Some(message_list) = imp.filtered_message_model.message_list() {
+            message_list.set_live(false);
+        }
+        imp.diagram.queue_draw_messages();
         imp.diagram.set_should_stick(false);
 
         let filename = glib::DateTime::now_local()
             .unwrap()
             .format("%Y-%m-%d %H:%M:%S")
             .unwrap();
-        imp.diagram_title.set_title(&format!("*{filename}.pcap"));
+        let extension = imp
+            .filtered_message_model
+            .message_list()
+            .map_or("pcap", |message_list| message_list.file_extension());
+        imp.diagram_title
+            .set_title(&format!("*{filename}.{extension}"));
         imp.filename.replace(Some(filename.to_string()));
 
         if imp.filtered_message_model.n_items() != 0 {
@@ -602,18 +943,164 @@ impl Window {
         }
     }
 
-    async fn start_recording(&self, mut monitor: Monitor, display_message: &str) -> Result<()> {
+    /// Finalizes the recording after the stream of a monitor ended, like a
+    /// piped `dbus-monitor --pcap` exiting
+    fn recording_finished(&self) {
+        // Another monitor of the same recording may have ended already
+        if self.imp().monitors.borrow().is_empty() {
+            return;
+        }
+
+        self.stop_recording();
+    }
+
+    /// Finalizes the recording after the monitor stopped on its own, offering
+    /// to save what was recorded so far
+    fn recording_failed(&self, message: &str) {
+        tracing::error!("Recording failed: {message}");
+
+        // Another monitor of the same recording may have failed already
+        if self.imp().monitors.borrow().is_empty() {
+            return;
+        }
+
+        self.stop_recording();
+
+        let toast = adw::Toast::builder()
+            .title(gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "Recording stopped unexpectedly: {error}",
+                &[("error", message)],
+            ))
+            .priority(adw::ToastPriority::High)
+            .build();
+        if self.view() == View::Diagram {
+            toast.set_button_label(Some(&gettext("Save")));
+            toast.set_action_name(Some("win.save"));
+        }
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Records the messages of all `monitors` into a single message list,
+    /// stopping the ones already started if any of them fails to start
+    async fn start_recording(&self, monitors: Vec<Monitor>, display_message: &str) -> Result<()> {
         let imp = self.imp();
 
-        let message_list = MessageList::default();
+        // Messages are told apart by their bus, so the monitors of a single
+        // recording must be on different buses
+        let bus_types = monitors
+            .iter()
+            .map(|monitor| monitor.bus_type())
+            .collect::<HashSet<_>>();
+        ensure!(
+            bus_types.len() == monitors.len(),
+            "Monitors of a recording must be on different buses"
+        );
 
-        monitor
-            .start(clone!(@weak message_list => move |event| {
-                message_list.push(Message::from_event(event));
-            }))
-            .await
-            .context("Failed to start monitor")?;
-        imp.monitor.replace(Some(monitor));
+        let message_list = MessageList::default();
+        message_list.set_retention(self.retention());
+        message_list.set_live(true);
+
+        let trigger = self.trigger()?;
+        let is_armed = trigger.is_some();
+        let stop_conditions = self.stop_conditions()?;
+        imp.trigger.replace(trigger);
+        imp.stop_conditions
+            .replace(Some(stop_conditions).filter(|conditions| !conditions.is_empty()));
+        imp.event_merger.replace(Some(EventMerger::new(
+            monitors.iter().map(|monitor| monitor.bus_type()),
+        )));
+
+        // A monitor may fail or finish as soon as it is started, even before
+        // the others are, which is only handled once they all are
+        let early_error = Rc::new(RefCell::new(None));
+        let finished_early = Rc::new(Cell::new(false));
+        for (i, monitor) in monitors.iter().enumerate() {
+            monitor.connect_error(
+                clone!(@weak self as obj, @strong early_error => move |_, message| {
+                    if obj.imp().monitors.borrow().is_empty() {
+                        early_error.borrow_mut().get_or_insert_with(|| message.to_owned());
+                    } else {
+                        obj.recording_failed(message);
+                    }
+                }),
+            );
+            monitor.connect_finished(
+                clone!(@weak self as obj, @strong finished_early => move |_| {
+                    if obj.imp().monitors.borrow().is_empty() {
+                        finished_early.set(true);
+                    } else {
+                        obj.recording_finished();
+                    }
+                }),
+            );
+
+            let bus_type = monitor.bus_type();
+            let res = monitor
+                .start(
+                    clone!(@weak self as obj, @weak message_list => move |events| {
+                        obj.merge_recorded(&message_list, events, bus_type);
+                    }),
+                )
+                .await
+                .context("Failed to start monitor");
+            if let Err(err) = res {
+                for started in &monitors[..i] {
+                    started.stop();
+                }
+                imp.trigger.take();
+                imp.stop_conditions.take();
+                imp.event_merger.take();
+                return Err(err);
+            }
+        }
+        // A single monitor delivers its events in order, so they are never
+        // held back
+        if monitors.len() > 1 {
+            let merge_timeout = glib::timeout_add_local(
+                MERGE_DELAY,
+                clone!(
+                    @weak self as obj, @weak message_list =>
+                    @default-return glib::ControlFlow::Break, move || {
+                        obj.flush_recorded(&message_list);
+                        glib::ControlFlow::Continue
+                    }
+                ),
+            );
+            imp.merge_timeout.replace(Some(merge_timeout));
+        }
+        imp.monitors.replace(monitors);
+
+        message_list.bus_names().connect_name_lost(
+            clone!(@weak self as obj => move |_, bus_type, name| {
+                if let Some(stop_conditions) = obj.imp().stop_conditions.borrow_mut().as_mut() {
+                    stop_conditions.set_bus_name_lost(bus_type, name);
+                    if stop_conditions.is_met() {
+                        obj.stop_recording_later();
+                    }
+                };
+            }),
+        );
+        let duration = imp
+            .stop_conditions
+            .borrow()
+            .as_ref()
+            .and_then(|stop_conditions| stop_conditions.duration());
+        if let Some(duration) = duration {
+            let stop_timeout = glib::timeout_add_local_once(
+                duration,
+                clone!(@weak self as obj => move || {
+                    let imp = obj.imp();
+                    imp.stop_timeout.take();
+                    if let Some(stop_conditions) = imp.stop_conditions.borrow_mut().as_mut() {
+                        stop_conditions.set_duration_elapsed();
+                    }
+                    obj.stop_recording_later();
+                }),
+            );
+            imp.stop_timeout.replace(Some(stop_timeout));
+        }
 
         imp.diagram.set_should_stick(true);
 
@@ -622,11 +1109,516 @@ impl Window {
         imp.diagram_title.set_title(display_message);
         imp.diagram_title.set_subtitle("");
 
+        imp.waiting_sub_page.set_description(Some(&if is_armed {
+            gettext("Waiting for the Trigger…")
+        } else {
+            gettext("Waiting for D-Bus Traffic…")
+        }));
+
         self.set_view(View::Diagram);
         imp.diagram_page_stack
             .set_visible_child(&*imp.waiting_sub_page);
         imp.record_button_stack.set_visible_child_name("stop");
 
+        if let Some(message) = early_error.take() {
+            self.recording_failed(&message);
+        } else if finished_early.get() {
+            self.recording_finished();
+        }
+
+        Ok(())
+    }
+
+    /// Merges the `events` recorded on `bus_type` with the ones of the other
+    /// monitors, and adds the events that are in order to `message_list`
+    fn merge_recorded(&self, message_list: &MessageList, events: Vec<Event>, bus_type: BusType) {
+        if let Some(event_merger) = self.imp().event_merger.borrow_mut().as_mut() {
+            event_merger.push(events, bus_type);
+        }
+        self.flush_recorded(message_list);
+    }
+
+    /// Adds the merged events that can no longer be preceded by events of the
+    /// other monitors to `message_list`
+    fn flush_recorded(&self, message_list: &MessageList) {
+        let ready = self
+            .imp()
+            .event_merger
+            .borrow_mut()
+            .as_mut()
+            .map(|event_merger| event_merger.pop_ready(Timestamp::now()))
+            .unwrap_or_default();
+        self.push_recorded(message_list, ready);
+    }
+
+    /// Adds the recorded `events` to `message_list`, unless the recording
+    /// waits for a trigger that holds them back or must stop before them
+    fn push_recorded(&self, message_list: &MessageList, events: Vec<(Event, BusType)>) {
+        let imp = self.imp();
+
+        if events.is_empty() {
+            return;
+        }
+
+        let mut trigger = imp.trigger.borrow_mut();
+        let mut stop_conditions = imp.stop_conditions.borrow_mut();
+        if trigger.is_none() && stop_conditions.is_none() {
+            drop((trigger, stop_conditions));
+            push_by_bus(message_list, events);
+            return;
+        }
+
+        let (was_triggered, was_done) = trigger.as_ref().map_or((true, false), |trigger| {
+            (trigger.is_triggered(), trigger.is_done())
+        });
+        let mut released = match trigger.as_mut() {
+            Some(trigger) => split_by_bus(events)
+                .into_iter()
+                .flat_map(|(events, bus_type)| trigger.process(events, bus_type))
+                .collect(),
+            None => events,
+        };
+        let (is_triggered, is_done) = trigger.as_ref().map_or((true, false), |trigger| {
+            (trigger.is_triggered(), trigger.is_done())
+        });
+        let post_trigger_duration = trigger
+            .as_ref()
+            .and_then(|trigger| trigger.post_trigger_duration());
+
+        let was_met = stop_conditions
+            .as_ref()
+            .is_some_and(|conditions| conditions.is_met());
+        if let Some(stop_conditions) = stop_conditions.as_mut() {
+            stop_conditions.truncate(&mut released);
+        }
+        let is_met = stop_conditions
+            .as_ref()
+            .is_some_and(|conditions| conditions.is_met());
+
+        // Pushing may notify the name-lost handler, which borrows the stop
+        // conditions
+        drop((trigger, stop_conditions));
+
+        push_by_bus(message_list, released);
+
+        if !was_triggered && is_triggered {
+            self.add_message_toast(&gettext("Recording triggered"));
+
+            // Later messages would only be checked against the duration once
+            // they arrive, if they ever do
+            if let Some(duration) = post_trigger_duration {
+                let trigger_timeout = glib::timeout_add_local_once(
+                    duration,
+                    clone!(@weak self as obj => move || {
+                        obj.imp().trigger_timeout.take();
+                        obj.stop_recording_later();
+                    }),
+                );
+                imp.trigger_timeout.replace(Some(trigger_timeout));
+            }
+        }
+
+        if (!was_done && is_done) || (!was_met && is_met) {
+            self.stop_recording_later();
+        }
+    }
+
+    /// Stops the recording once back in the main loop, as the monitors
+    /// cannot be stopped from within their own callback, and saves what was
+    /// recorded if an auto-save directory is set
+    fn stop_recording_later(&self) {
+        glib::idle_add_local_once(clone!(@weak self as obj => move || {
+            // The recording may have been stopped in the meantime
+            if obj.imp().monitors.borrow().is_empty() {
+                return;
+            }
+            obj.stop_recording();
+
+            glib::spawn_future_local(clone!(@weak obj => async move {
+                if let Err(err) = obj.auto_save().await {
+                    tracing::error!("Could not save automatically: {err:?}");
+                    obj.add_error_toast(&gettext("Failed to save recording automatically"));
+                }
+            }));
+        }));
+    }
+
+    /// Saves the stopped recording to the auto-save directory, if set
+    async fn auto_save(&self) -> Result<()> {
+        let imp = self.imp();
+
+        let dir = imp.settings.string("auto-save-directory");
+        if dir.is_empty() || imp.filtered_message_model.n_items() == 0 {
+            return Ok(());
+        }
+
+        let message_list = imp
+            .filtered_message_model
+            .message_list()
+            .expect("message list must be set before saving");
+        let filename = imp
+            .filename
+            .borrow()
+            .clone()
+            .expect("filename must be set after recording");
+        let extension = message_list.file_extension();
+        let path = Path::new(dir.as_str()).join(format!("{filename}.{extension}"));
+        message_list.save_to_file(&path).await?;
+
+        imp.diagram_title.set_title(&filename);
+        self.add_message_toast(&gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Recording saved to {path}",
+            &[("path", &path.display().to_string())],
+        ));
+
+        Ok(())
+    }
+
+    /// Returns the trigger new recordings must wait for, if enabled
+    fn trigger(&self) -> Result<Option<Trigger>> {
+        let settings = &self.imp().settings;
+
+        if !settings.boolean("trigger-enabled") {
+            return Ok(None);
+        }
+
+        let condition = settings
+            .string("trigger-condition")
+            .parse()
+            .context("Invalid trigger condition")?;
+        let pre_trigger = Duration::from_secs(settings.uint("trigger-pre-duration").into());
+        let post_trigger = PostTrigger {
+            duration: Some(settings.uint("trigger-post-duration"))
+                .filter(|secs| *secs != 0)
+                .map(|secs| Duration::from_secs(secs.into())),
+            n_messages: Some(settings.uint("trigger-post-count") as usize)
+                .filter(|n_messages| *n_messages != 0),
+        };
+
+        Ok(Some(Trigger::new(condition, pre_trigger, post_trigger)))
+    }
+
+    /// Returns when new recordings must stop on their own
+    fn stop_conditions(&self) -> Result<StopConditions> {
+        let settings = &self.imp().settings;
+
+        let duration = Some(settings.uint("stop-duration"))
+            .filter(|secs| *secs != 0)
+            .map(|secs| Duration::from_secs(secs.into()));
+        let n_messages =
+            Some(settings.uint("stop-count") as usize).filter(|n_messages| *n_messages != 0);
+        let condition = settings.string("stop-condition");
+        let message_match = if condition.is_empty() {
+            None
+        } else {
+            Some(condition.parse().context("Invalid stop condition")?)
+        };
+        let bus_name = Some(settings.string("stop-bus-name").to_string())
+            .filter(|bus_name| !bus_name.is_empty());
+        let bus_name_bus_type = match settings.string("stop-bus-type").as_str() {
+            "session" => Some(BusType::Session),
+            "system" => Some(BusType::System),
+            _ => None,
+        };
+
+        Ok(StopConditions::new(
+            duration,
+            n_messages,
+            message_match,
+            bus_name,
+            bus_name_bus_type,
+        ))
+    }
+
+    /// Returns the retention to use for new recordings
+    fn retention(&self) -> Retention {
+        let settings = &self.imp().settings;
+        match settings.string("retention-mode").as_str() {
+            "count" => Retention::Count(settings.uint("retention-count") as usize),
+            "duration" => Retention::Duration(Duration::from_secs(
+                settings.uint("retention-duration").into(),
+            )),
+            _ => Retention::Unlimited,
+        }
+    }
+
+    async fn replay(&self) -> Result<()> {
+        let imp = self.imp();
+
+        let (bus_type, sender) = imp
+            .diagram
+            .selected_message()
+            .and_then(|message| {
+                let sender = message.sender()?.into_owned();
+                Some((message.bus_type(), sender))
+            })
+            .context("No sender selected")?;
+
+        let replay = Replay::new(&*imp.filtered_message_model, bus_type, &sender)?;
+        let n_skipped_calls = replay.n_skipped_calls();
+        if n_skipped_calls != 0 {
+            self.add_message_toast(&ngettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "{n_calls} method call passing file descriptors cannot be replayed",
+                "{n_calls} method calls passing file descriptors cannot be replayed",
+                n_skipped_calls as u32,
+                &[("n_calls", &n_skipped_calls.to_string())],
+            ));
+        }
+        if replay.n_calls() == 0 {
+            self.add_message_toast(&gettext("No method calls to replay"));
+            return Ok(());
+        }
+
+        let Ok((address, scale)) =
+            AddressDialog::choose_replay(self, imp.settings.double("replay-delay-scale")).await
+        else {
+            return Ok(());
+        };
+        imp.settings.set_double("replay-delay-scale", scale)?;
+        let timing = if scale == 1.0 {
+            Timing::Original
+        } else {
+            Timing::Scaled(scale)
+        };
+        let address_display = address.to_string();
+
+        let app = self
+            .application()
+            .and_downcast::<Application>()
+            .context("Window has no application")?;
+        let window = Self::with_group(&app);
+        window.present();
+
+        let message_list = MessageList::default();
+        message_list.set_live(true);
+        let window_imp = window.imp();
+        let cancellable = gio::Cancellable::new();
+        window_imp
+            .replay_cancellable
+            .replace(Some(cancellable.clone()));
+        window_imp.diagram.set_should_stick(true);
+        window_imp
+            .filtered_message_model
+            .set_message_list(Some(&message_list));
+        window_imp.diagram_title.set_title(&gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Replaying {sender} to {address}…",
+            &[("sender", sender.as_str()), ("address", &address_display)],
+        ));
+        window.set_view(View::Diagram);
+        window_imp
+            .record_button_stack
+            .set_visible_child_name("stop");
+        window_imp
+            .diagram_page_stack
+            .set_visible_child(&*window_imp.waiting_sub_page);
+
+        let handler_id = message_list.connect_items_changed(
+            clone!(@weak window => move |message_list, _, _, _| {
+                if message_list.n_items() != 0 {
+                    let imp = window.imp();
+                    imp.diagram_page_stack
+                        .set_visible_child(&*imp.split_view_sub_page);
+                }
+            }),
+        );
+        let res = replay
+            .run(address, timing, &message_list, &cancellable)
+            .await;
+        message_list.disconnect(handler_id);
+
+        message_list.set_live(false);
+        window_imp.replay_cancellable.take();
+        window_imp
+            .record_button_stack
+            .set_visible_child_name("record");
+        window_imp.diagram.queue_draw_messages();
+        window_imp.diagram.set_should_stick(false);
+        window_imp.diagram_title.set_title(&gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Replay of {sender}",
+            &[("sender", sender.as_str())],
+        ));
+        window_imp
+            .filename
+            .replace(Some(format!("replay-{}", sender.trim_start_matches(':'))));
+        window_imp
+            .diagram_page_stack
+            .set_visible_child(&*window_imp.split_view_sub_page);
+        window.set_view(View::Diagram);
+
+        res
+    }
+
+    async fn run_mock_service(&self) -> Result<()> {
+        let imp = self.imp();
+
+        let name = self
+            .selected_service_name()
+            .context("No service selected")?;
+        let message_list = imp
+            .filtered_message_model
+            .message_list()
+            .context("No message list")?;
+        let mock_service = MockService::from_message_list(&message_list, &name)?;
+
+        let Ok(address) = AddressDialog::choose_with(
+            self,
+            Some(&gettext("Run Mock Service on Address")),
+            Some(&gettext("_Run")),
+        )
+        .await
+        else {
+            return Ok(());
+        };
+        let address_display = address.to_string();
+
+        let handle = mock_service.serve(address).await?;
+        imp.mock_service.replace(Some(handle));
+        self.update_selection_actions(self.view());
+
+        self.add_message_toast(&gettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Serving {name} on {address}",
+            &[("name", name.as_str()), ("address", &address_display)],
+        ));
+
+        Ok(())
+    }
+
+    /// Records the PCAP stream written to the standard input of this process
+    pub async fn record_stdin(&self) {
+        if let Err(err) = self
+            .start_recording(
+                vec![Monitor::stdin()],
+                &gettext("Recording standard input…"),
+            )
+            .await
+        {
+            tracing::error!("Failed to record standard input: {err:?}");
+            self.add_error_toast(&gettext("Failed to record standard input"));
+        }
+    }
+
+    async fn record_fifo(&self) -> Result<()> {
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Record Named Pipe"))
+            .accept_label(gettext("_Record"))
+            .modal(true)
+            .build();
+
+        let file = match chooser.open_future(Some(self)).await {
+            Err(err) if err.matches(gtk::DialogError::Dismissed) => return Ok(()),
+            res => res?,
+        };
+        let path = file.path().context("Named pipe has no path")?;
+        let path_display = path.display().to_string();
+
+        self.start_recording(
+            vec![Monitor::fifo(path)],
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            &gettext_f("Recording {path}…", &[("path", &path_display)]),
+        )
+        .await
+    }
+
+    /// Records the traffic of the clients connecting to a new socket instead
+    /// of the chosen bus or peer
+    async fn record_proxy(&self) -> Result<()> {
+        let Ok(target) = AddressDialog::choose_with(
+            self,
+            Some(&gettext("Record Through Proxy")),
+            Some(&gettext("_Start")),
+        )
+        .await
+        else {
+            return Ok(());
+        };
+
+        let monitor = Monitor::proxy(target);
+        let proxy_address = monitor
+            .proxy_address()
+            .expect("proxy must have an address")
+            .to_string();
+
+        self.start_recording(
+            vec![monitor],
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            &gettext_f("Recording {address}…", &[("address", &proxy_address)]),
+        )
+        .await?;
+
+        let toast = adw::Toast::builder()
+            .title(gettext_f(
+                // Translators: Do NOT translate the contents between '{' and '}', this
+                // is a variable name.
+                "Point clients to the proxy with DBUS_SESSION_BUS_ADDRESS={address}",
+                &[("address", &proxy_address)],
+            ))
+            .use_markup(false)
+            .button_label(gettext("Copy"))
+            .timeout(0)
+            .build();
+        toast.connect_button_clicked(clone!(@weak self as obj => move |_| {
+            obj.clipboard()
+                .set_text(&format!("DBUS_SESSION_BUS_ADDRESS={proxy_address}"));
+        }));
+        self.imp().toast_overlay.add_toast(toast);
+
+        Ok(())
+    }
+
+    async fn save_mock_service(&self) -> Result<()> {
+        let imp = self.imp();
+
+        let name = self
+            .selected_service_name()
+            .context("No service selected")?;
+        let message_list = imp
+            .filtered_message_model
+            .message_list()
+            .context("No message list")?;
+        let source = MockService::from_message_list(&message_list, &name)?.to_rust_source()?;
+
+        let filter = gtk::FileFilter::new();
+        // Translators: Rust is a programming language, do not translate.
+        filter.set_property("name", gettext("Rust Source"));
+        filter.add_mime_type("text/rust");
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Save Mock Service"))
+            .filters(&filters)
+            .initial_name(format!("{}.rs", name.replace('.', "_").to_lowercase()))
+            .modal(true)
+            .build();
+
+        let file = match chooser.save_future(Some(self)).await {
+            Err(err) if err.matches(gtk::DialogError::Dismissed) => return Ok(()),
+            res => res?,
+        };
+        file.replace_contents_future(
+            source,
+            None,
+            false,
+            gio::FileCreateFlags::REPLACE_DESTINATION,
+        )
+        .await
+        .map_err(|e| e.1)?;
+
+        self.add_message_toast(&gettext("Mock service saved as Rust source"));
+
         Ok(())
     }
 
@@ -635,6 +1627,7 @@ impl Window {
         // Translators: PCAP is a type of file, do not translate.
         filter.set_property("name", gettext("PCAP Files"));
         filter.add_mime_type("application/vnd.tcpdump.pcap");
+        filter.add_mime_type("application/x-pcapng");
 
         let filters = gio::ListStore::new::<gtk::FileFilter>();
         filters.append(&filter);
@@ -653,8 +1646,9 @@ impl Window {
         Ok(())
     }
 
-    async fn save(&self) -> anyhow::Result<()> {
-        let imp = self.imp();
+    /// Shows the packets of a log and the ones appended to it while it is
+    /// being written, until stopped like a recording
+    async fn follow_log(&self) -> anyhow::Result<()> {
         let filter = gtk::FileFilter::new();
         // Translators: PCAP is a type of file, do not translate.
         filter.set_property("name", gettext("PCAP Files"));
@@ -663,12 +1657,59 @@ impl Window {
         let filters = gio::ListStore::new::<gtk::FileFilter>();
         filters.append(&filter);
 
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Follow Log"))
+            .accept_label(gettext("_Follow"))
+            .filters(&filters)
+            .modal(true)
+            .build();
+
+        let file = match chooser.open_future(Some(self)).await {
+            Err(err) if err.matches(gtk::DialogError::Dismissed) => return Ok(()),
+            res => res?,
+        };
+        let path = file.path().context("File has no path")?;
+        let filename = file.basename().unwrap_or_default().display().to_string();
+
+        self.start_recording(
+            vec![Monitor::follow(path)],
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            &gettext_f("Following {filename}…", &[("filename", &filename)]),
+        )
+        .await
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let imp = self.imp();
+        let message_list = imp
+            .filtered_message_model
+            .message_list()
+            .expect("message list must be set before saving");
+        // Only PCAPNG files tell the messages of several buses apart
+        let has_multiple_buses = message_list.has_multiple_buses();
+
+        let filter = gtk::FileFilter::new();
+        if has_multiple_buses {
+            // Translators: PCAPNG is a type of file, do not translate.
+            filter.set_property("name", gettext("PCAPNG Files"));
+            filter.add_mime_type("application/x-pcapng");
+        } else {
+            // Translators: PCAP is a type of file, do not translate.
+            filter.set_property("name", gettext("PCAP Files"));
+            filter.add_mime_type("application/vnd.tcpdump.pcap");
+        }
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
         let mut builder = gtk::FileDialog::builder()
             .title(gettext("Save Log"))
             .filters(&filters)
             .modal(true);
         if let Some(filename) = imp.filename.borrow().as_ref() {
-            builder = builder.initial_name(format!("{}.pcap", filename));
+            let extension = message_list.file_extension();
+            builder = builder.initial_name(format!("{}.{}", filename, extension));
         }
         let chooser = builder.build();
 
@@ -677,16 +1718,17 @@ impl Window {
             res => res?,
         };
         let path = file.path().unwrap();
-        let message_list = imp
-            .filtered_message_model
-            .message_list()
-            .expect("message list must be set before saving");
         message_list.save_to_file(path).await?;
         // Update the title once the save operation is done
         // removing the `*` prefix
         if let Some(filename) = imp.filename.borrow().as_ref() {
             imp.diagram_title.set_title(filename);
         }
+        if has_multiple_buses {
+            self.add_message_toast(&gettext("Recording saved as PCAPNG"));
+        } else {
+            self.add_message_toast(&gettext("Recording saved as PCAP"));
+        }
         Ok(())
     }
 
@@ -722,6 +1764,42 @@ impl Window {
         Ok(())
     }
 
+    async fn save_introspection(&self) -> Result<()> {
+        let imp = self.imp();
+
+        let message_list = imp
+            .filtered_message_model
+            .message_list()
+            .expect("message list must be set before saving");
+        let introspection = Introspection::from_message_list(&message_list)?;
+        if introspection.services().next().is_none() {
+            self.add_message_toast(&gettext("No interfaces to infer introspection from"));
+            return Ok(());
+        }
+
+        let chooser = gtk::FileDialog::builder()
+            .title(gettext("Save Introspection XML to Folder"))
+            .modal(true)
+            .build();
+
+        let dir = match chooser.select_folder_future(Some(self)).await {
+            Err(err) if err.matches(gtk::DialogError::Dismissed) => return Ok(()),
+            res => res?,
+        };
+        let n_saved = introspection.save_to_dir(&dir).await?;
+
+        self.add_message_toast(&ngettext_f(
+            // Translators: Do NOT translate the contents between '{' and '}', this
+            // is a variable name.
+            "Saved introspection XML of {n_services} service",
+            "Saved introspection XML of {n_services} services",
+            n_saved as u32,
+            &[("n_services", &n_saved.to_string())],
+        ));
+
+        Ok(())
+    }
+
     // @new
     // pub fn add_message_toast(&self, message: &str) {
     //     self.add_toast(adw::Toast::new(message));
@@ -757,6 +1835,7 @@ impl Window {
     #[template_callback]
     fn diagram_selected_message_notify(&self) {
         self.update_details_view();
+        self.update_selection_actions(self.view());
     }
 
     #[template_callback]
@@ -766,7 +1845,7 @@ impl Window {
         }
 
         let imp = self.imp();
-        let is_recording = imp.monitor.borrow().is_some();
+        let is_recording = self.is_recording();
 
         if is_recording {
             let n_messages = imp.filtered_message_model.n_items();
@@ -803,3 +1882,23 @@ impl Window {
         }
     }
 }
+
+/// Splits `events` into runs of consecutive events of the same bus
+fn split_by_bus(events: Vec<(Event, BusType)>) -> Vec<(Vec<Event>, BusType)> {
+    let mut runs: Vec<(Vec<Event>, BusType)> = Vec::new();
+    for (event, bus_type) in events {
+        match runs.last_mut() {
+            Some((run, run_bus_type)) if *run_bus_type == bus_type => run.push(event),
+            _ => runs.push((vec![event], bus_type)),
+        }
+    }
+    runs
+}
+
+/// Adds `events` to `message_list`, keeping their order even if they come
+/// from several buses
+fn push_by_bus(message_list: &MessageList, events: Vec<(Event, BusType)>) {
+    for (events, bus_type) in split_by_bus(events) {
+        message_list.push_batch(events, bus_type);
+    }
+}